@@ -1,13 +1,19 @@
 use criterion::{criterion_group, criterion_main, Criterion};
+use gmboy::auxiliary::clock::Clock;
 use gmboy::auxiliary::timer::Timer;
 use gmboy::bus::Bus;
+use gmboy::cart::Cart;
 use gmboy::cpu::instructions::{
     AddressMode, ExecutableInstruction, Instruction, INSTRUCTIONS_BY_OPCODES,
 };
 use gmboy::cpu::interrupts::Interrupts;
 use gmboy::cpu::{CounterCpuCallback, Cpu};
+use gmboy::emu::read_bytes;
+use gmboy::headless::Headless;
 use gmboy::ppu::Ppu;
 
+const ROM_FRAMES: usize = 60;
+
 pub fn instructions(cpu: &mut Cpu, ctx: &mut CounterCpuCallback) {
     for (opcode, instr) in INSTRUCTIONS_BY_OPCODES.iter().enumerate() {
         match instr {
@@ -59,6 +65,19 @@ pub fn ppu_tick(ppu: &mut Ppu, bus: &mut Bus) {
     }
 }
 
+pub fn clock_m_cycles(clock: &mut Clock, bus: &mut Bus) {
+    for _ in 0..1000 {
+        clock.m_cycles(1, bus);
+    }
+}
+
+/// Runs a whole second of a ROM from power on.
+pub fn rom_frames(path: &str) {
+    let cart = Cart::new(read_bytes(path).unwrap()).unwrap();
+    let mut headless = Headless::new(cart);
+    headless.run_frames(ROM_FRAMES).unwrap();
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let mut callback = CounterCpuCallback::default();
     let mut cpu = Cpu::new(Bus::with_bytes(vec![10; 100000])); // Pre-allocate memory
@@ -78,6 +97,22 @@ fn criterion_benchmark(c: &mut Criterion) {
     let mut bus = Bus::with_bytes(vec![10; 100000]);
     let mut ppu = Ppu::default();
     c.bench_function("ppu tick", |b| b.iter(|| ppu_tick(&mut ppu, &mut bus)));
+
+    let mut bus = Bus::new(Cart::new(vec![0; 0x8000]).unwrap());
+    let mut clock = Clock::with_ppu(Ppu::default());
+    c.bench_function("clock m-cycles", |b| {
+        b.iter(|| clock_m_cycles(&mut clock, &mut bus))
+    });
+
+    let mut group = c.benchmark_group("rom");
+    group.sample_size(10);
+    group.bench_function("cpu_instrs", |b| {
+        b.iter(|| rom_frames("tests/blargg/roms/cpu_instrs.gb"))
+    });
+    group.bench_function("dmg-acid2", |b| {
+        b.iter(|| rom_frames("tests/roms/dmg-acid2.gb"))
+    });
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
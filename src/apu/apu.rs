@@ -1,19 +1,22 @@
-use crate::apu::channels::channel::ChannelType;
+use crate::apu::channels::channel::{ChannelInfo, ChannelType};
 use crate::apu::channels::noise_channel::{NoiseChannel, CH4_END_ADDRESS, CH4_START_ADDRESS};
 use crate::apu::channels::square_channel::{
     SquareChannel, CH1_END_ADDRESS, CH1_START_ADDRESS, CH2_END_ADDRESS, CH2_START_ADDRESS,
 };
 use crate::apu::channels::wave_channel::{
-    WaveChannel, CH3_END_ADDRESS, CH3_START_ADDRESS, CH3_WAVE_RAM_END, CH3_WAVE_RAM_START,
+    WaveChannel, WaveRam, CH3_END_ADDRESS, CH3_START_ADDRESS, CH3_WAVE_RAM_END, CH3_WAVE_RAM_START,
 };
+use crate::apu::blip::BlipBuf;
 use crate::apu::dac::apply_dac;
 use crate::apu::hpf::Hpf;
 use crate::apu::mixer::Mixer;
+use crate::apu::scope::Scope;
 use crate::channels::noise_channel::NR41_CH4_LENGTH_TIMER_ADDRESS;
 use crate::channels::square_channel::{
     NR11_CH1_LEN_TIMER_DUTY_CYCLE_ADDRESS, NR21_CH2_LEN_TIMER_DUTY_CYCLE_ADDRESS,
 };
 use crate::{get_bit_flag, set_bit, CPU_CLOCK_SPEED};
+use serde::{Deserialize, Serialize};
 
 pub const AUDIO_START_ADDRESS: u16 = 0xFF10;
 pub const AUDIO_END_ADDRESS: u16 = 0xFF26;
@@ -28,7 +31,7 @@ pub const AUDIO_BUFFER_SIZE: usize = 512;
 
 pub const FRAME_SEQUENCER_DIV: u16 = (CPU_CLOCK_SPEED / APU_CLOCK_SPEED as u32) as u16;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Apu {
     // internal
     ch1: SquareChannel,
@@ -41,13 +44,36 @@ pub struct Apu {
     // other data
     frame_sequencer_step: u8,
     ticks_count: u32,
+    /// Samples not played yet, dropped by save states.
+    #[serde(skip, default = "new_output_buffer")]
     output_buffer: Box<[f32; AUDIO_BUFFER_SIZE]>,
+    #[serde(skip)]
     output_buffer_idx: usize,
+    sample_rate: u32,
+    blip: BlipBuf,
+    /// Mixer registers or flags changed since the last mix.
+    is_mix_dirty: bool,
     hpf: Hpf,
+    #[serde(skip)]
+    scope: Scope,
+    /// Raw DAC output of each channel, collected only while `capture_channels` is set.
+    #[serde(skip)]
+    channels_output: Vec<[f32; 4]>,
+    capture_channels: bool,
+}
+
+fn new_output_buffer() -> Box<[f32; AUDIO_BUFFER_SIZE]> {
+    Box::new([0.0; AUDIO_BUFFER_SIZE])
 }
 
 impl Default for Apu {
     fn default() -> Self {
+        Apu::new(SAMPLING_FREQ as u32)
+    }
+}
+
+impl Apu {
+    pub fn new(sample_rate: u32) -> Self {
         Self {
             ch1: SquareChannel::ch1(),
             ch2: SquareChannel::ch2(),
@@ -57,36 +83,99 @@ impl Default for Apu {
             mixer: Default::default(),
             frame_sequencer_step: 0,
             ticks_count: 0,
-            output_buffer: Box::new([0.0; AUDIO_BUFFER_SIZE]),
+            output_buffer: new_output_buffer(),
             output_buffer_idx: 0,
-            hpf: Hpf::new(SAMPLING_FREQ as i32),
+            sample_rate,
+            blip: BlipBuf::new(CPU_CLOCK_SPEED, sample_rate),
+            is_mix_dirty: false,
+            hpf: Hpf::new(sample_rate as i32),
+            scope: Default::default(),
+            channels_output: Vec::new(),
+            capture_channels: false,
         }
     }
-}
 
-impl Apu {
     pub fn tick(&mut self) {
         self.ticks_count = self.ticks_count.wrapping_add(1);
         self.sequence_frame();
 
-        self.ch1.tick();
-        self.ch2.tick();
-        self.ch3.tick();
-        self.ch4.tick();
+        if self.nr52.is_audio_on() {
+            self.ch1.tick();
+            self.ch2.tick();
+            self.ch3.tick();
+            self.ch4.tick();
+        }
+
+        // channel outputs change at most every 2 ticks (CH3 runs at 2 MHz)
+        if self.ticks_count % 2 != 0 {
+            return;
+        }
+
+        self.output(2);
+    }
 
-        // down sample by nearest-neighbor
-        let ticks_per_sample = CPU_CLOCK_SPEED / SAMPLING_FREQ as u32;
+    /// Ticks which `skip` can run at once: until the tick before the next frame sequencer step
+    /// or a channel timer expiring. Channels are stopped while the APU is off.
+    pub fn get_ticks_to_event(&self) -> usize {
+        let div = FRAME_SEQUENCER_DIV as u32;
+        let ticks = (div - 1 - self.ticks_count % div) as u16;
 
-        if self.ticks_count % ticks_per_sample == 0 {
+        if !self.nr52.is_audio_on() {
+            return ticks as usize;
+        }
+
+        ticks
+            .min(self.ch1.get_idle_ticks())
+            .min(self.ch2.get_idle_ticks())
+            .min(self.ch3.get_idle_ticks())
+            .min(self.ch4.get_idle_ticks()) as usize
+    }
+
+    /// Runs ticks in which channel outputs do not change, see `get_ticks_to_event`. They are
+    /// mixed once, so the number of ticks must be even and span less than an output sample.
+    pub fn skip(&mut self, ticks: usize) {
+        self.ticks_count = self.ticks_count.wrapping_add(ticks as u32);
+
+        if self.nr52.is_audio_on() {
+            self.ch1.skip(ticks as u16);
+            self.ch2.skip(ticks as u16);
+            self.ch3.skip(ticks as u16);
+            self.ch4.skip(ticks as u16);
+        }
+
+        self.output(ticks as u32);
+    }
+
+    /// Mixes the channels and resamples the elapsed clocks.
+    fn output(&mut self, clocks: u32) {
+        let (dac1_enabled, sample1) = apply_dac(self.nr52, &self.ch1);
+        let (dac2_enabled, sample2) = apply_dac(self.nr52, &self.ch2);
+        let (dac3_enabled, sample3) = apply_dac(self.nr52, &self.ch3);
+        let (dac4_enabled, sample4) = apply_dac(self.nr52, &self.ch4);
+        let channel_samples = [sample1, sample2, sample3, sample4];
+        self.hpf.dac1_enabled = dac1_enabled;
+        self.hpf.dac2_enabled = dac2_enabled;
+        self.hpf.dac3_enabled = dac3_enabled;
+        self.hpf.dac4_enabled = dac4_enabled;
+
+        // every amplitude change is resampled as a band-limited step
+        if self.is_mix_dirty || channel_samples != self.mixer.get_samples() {
+            self.is_mix_dirty = false;
+            self.mixer.set_samples(channel_samples);
+            let (mixed_left, mixed_right) = self.mixer.mix();
+            self.blip.set_amplitudes(mixed_left, mixed_right);
+        }
+
+        if let Some((output_left, output_right)) = self.blip.clock(clocks) {
             if self.output_buffer_idx >= AUDIO_BUFFER_SIZE {
                 self.output_buffer_idx = 0;
             }
 
-            (self.hpf.dac1_enabled, self.mixer.sample1) = apply_dac(self.nr52, &self.ch1);
-            (self.hpf.dac2_enabled, self.mixer.sample2) = apply_dac(self.nr52, &self.ch2);
-            (self.hpf.dac3_enabled, self.mixer.sample3) = apply_dac(self.nr52, &self.ch3);
-            (self.hpf.dac4_enabled, self.mixer.sample4) = apply_dac(self.nr52, &self.ch4);
-            let (output_left, output_right) = self.mixer.mix();
+            self.scope.push(channel_samples);
+
+            if self.capture_channels {
+                self.channels_output.push(channel_samples);
+            }
 
             self.output_buffer[self.output_buffer_idx] = self.hpf.apply_filter(output_left);
             self.output_buffer[self.output_buffer_idx + 1] = self.hpf.apply_filter(output_right);
@@ -94,6 +183,25 @@ impl Apu {
         }
     }
 
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Changes the output rate, pending output is discarded.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.blip = BlipBuf::new(CPU_CLOCK_SPEED, sample_rate);
+        self.hpf = Hpf::new(sample_rate as i32);
+        self.output_buffer_idx = 0;
+    }
+
+    /// Produces `ratio` times more samples than the sample rate asks for. Used to make up for
+    /// small drift between emulation and the audio device.
+    pub fn set_resample_ratio(&mut self, ratio: f64) {
+        self.blip
+            .set_rates(CPU_CLOCK_SPEED as f64, self.sample_rate as f64 * ratio);
+    }
+
     pub fn take_output(&mut self) -> &[f32] {
         let buffer = &self.output_buffer[0..self.output_buffer_idx];
         self.output_buffer_idx = 0;
@@ -101,10 +209,54 @@ impl Apu {
         buffer
     }
 
+    /// Interleaved stereo samples produced since the last `take_output`.
+    pub fn get_output(&self) -> &[f32] {
+        &self.output_buffer[0..self.output_buffer_idx]
+    }
+
+    pub fn set_channels_capture(&mut self, enabled: bool) {
+        self.capture_channels = enabled;
+
+        if !enabled {
+            self.channels_output.clear();
+        }
+    }
+
+    pub fn take_channels_output(&mut self) -> Vec<[f32; 4]> {
+        std::mem::take(&mut self.channels_output)
+    }
+
     pub fn output_ready(&self) -> bool {
         self.output_buffer_idx >= AUDIO_BUFFER_SIZE
     }
 
+    pub fn get_mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    pub fn get_mixer_mut(&mut self) -> &mut Mixer {
+        self.is_mix_dirty = true;
+
+        &mut self.mixer
+    }
+
+    pub fn get_scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    pub fn get_channels_info(&self) -> [ChannelInfo; 4] {
+        [
+            self.ch1.info(self.nr52),
+            self.ch2.info(self.nr52),
+            self.ch3.info(self.nr52),
+            self.ch4.info(self.nr52),
+        ]
+    }
+
+    pub fn get_wave_ram(&self) -> &WaveRam {
+        &self.ch3.wave_ram
+    }
+
     pub fn write(&mut self, address: u16, value: u8) {
         if (CH3_WAVE_RAM_START..=CH3_WAVE_RAM_END).contains(&address) {
             self.ch3.wave_ram.write(address, value);
@@ -153,8 +305,14 @@ impl Apu {
                     self.ch3.wave_ram.reset_sample_index();
                 }
             }
-            SOUND_PLANNING_ADDRESS => self.mixer.nr51_panning.byte = value,
-            MASTER_VOLUME_ADDRESS => self.mixer.nr50_volume.byte = value,
+            SOUND_PLANNING_ADDRESS => {
+                self.mixer.nr51_panning.byte = value;
+                self.is_mix_dirty = true;
+            }
+            MASTER_VOLUME_ADDRESS => {
+                self.mixer.nr50_volume.byte = value;
+                self.is_mix_dirty = true;
+            }
             CH3_WAVE_RAM_START..=CH3_WAVE_RAM_END => self.ch3.wave_ram.write(address, value),
             _ => {
                 if (AUDIO_START_ADDRESS..=AUDIO_END_ADDRESS).contains(&address) {
@@ -252,7 +410,7 @@ impl Apu {
 }
 
 /// FF26 — NR52: Audio master control
-#[derive(Debug, Clone, Default, Copy)]
+#[derive(Debug, Clone, Default, Copy, Serialize, Deserialize)]
 pub struct NR52 {
     byte: u8,
 }
@@ -361,7 +519,7 @@ impl NR52 {
 /// FF25 — NR51:
 /// Each channel can be panned hard left, center, hard right, or ignored entirely.
 /// Setting a bit to 1 enables the channel to go into the selected output.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NR51 {
     pub byte: u8,
 }
@@ -395,7 +553,7 @@ impl NR51 {
 
 /// FF24 — NR50: Master volume & VIN panning
 /// A value of 0 is treated as a volume of 1 (very quiet), and a value of 7 is treated as a volume of 8 (no volume reduction). Importantly, the amplifier never mutes a non-silent input.
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct NR50 {
     pub byte: u8,
 }
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Kernel half width in output samples, it is also the output delay.
+const HALF_WIDTH: usize = 8;
+const KERNEL_WIDTH: usize = HALF_WIDTH * 2;
+const PHASE_BITS: u32 = 6;
+const PHASES: usize = 1 << PHASE_BITS;
+const FRAC_BITS: u32 = 32;
+const TIME_UNIT: u64 = 1 << FRAC_BITS;
+const BUFFER_SIZE: usize = 32;
+const BUFFER_MASK: usize = BUFFER_SIZE - 1;
+/// Cutoff relative to the output Nyquist frequency.
+const CUTOFF: f64 = 0.9;
+
+/// One extra phase to interpolate the last one with.
+type Kernel = [[f64; KERNEL_WIDTH]; PHASES + 1];
+
+/// Stereo band-limited step synthesis in the spirit of blip_buf. Amplitude changes of the
+/// input clock are added as windowed-sinc impulses at their exact sub-sample position and the
+/// output is their running sum, so square waves and noise are resampled without aliasing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlipBuf {
+    /// Output samples per input clock, fixed point with `FRAC_BITS`.
+    factor: u64,
+    /// Position of the current clock after the next sample to be read, fixed point.
+    time: u64,
+    buffers: [[f64; BUFFER_SIZE]; 2],
+    read_idx: usize,
+    integrators: [f64; 2],
+    amplitudes: [f32; 2],
+}
+
+impl BlipBuf {
+    pub fn new(clock_rate: u32, sample_rate: u32) -> Self {
+        Self {
+            factor: ((sample_rate as f64 / clock_rate as f64) * TIME_UNIT as f64).round() as u64,
+            time: 0,
+            buffers: [[0.0; BUFFER_SIZE]; 2],
+            read_idx: 0,
+            integrators: [0.0; 2],
+            amplitudes: [0.0; 2],
+        }
+    }
+
+    /// Changes the resampling ratio without resetting pending output.
+    pub fn set_rates(&mut self, clock_rate: f64, sample_rate: f64) {
+        self.factor = ((sample_rate / clock_rate) * TIME_UNIT as f64).round() as u64;
+    }
+
+    /// Sets the input amplitudes at the current clock.
+    pub fn set_amplitudes(&mut self, left: f32, right: f32) {
+        self.set_amplitude(0, left);
+        self.set_amplitude(1, right);
+    }
+
+    fn set_amplitude(&mut self, idx: usize, amplitude: f32) {
+        if amplitude == self.amplitudes[idx] {
+            return;
+        }
+
+        let delta = (amplitude - self.amplitudes[idx]) as f64;
+        self.amplitudes[idx] = amplitude;
+        let buffer = &mut self.buffers[idx];
+        let phase = (self.time >> (FRAC_BITS - PHASE_BITS)) as usize;
+        let interp_bits = FRAC_BITS - PHASE_BITS;
+        let interp = (self.time & ((1 << interp_bits) - 1)) as f64 / (1u64 << interp_bits) as f64;
+        let kernel = get_kernel();
+        let taps = kernel[phase].iter().zip(kernel[phase + 1].iter());
+
+        for (i, (k1, k2)) in taps.enumerate() {
+            let k = k1 + (k2 - k1) * interp;
+            buffer[(self.read_idx + i) & BUFFER_MASK] += k * delta;
+        }
+    }
+
+    /// Advances input clocks, returns an output sample when one is complete. The clocks
+    /// must span less than an output sample.
+    pub fn clock(&mut self, clocks: u32) -> Option<(f32, f32)> {
+        self.time += self.factor * clocks as u64;
+
+        if self.time < TIME_UNIT {
+            return None;
+        }
+
+        self.time -= TIME_UNIT;
+
+        for (buffer, integrator) in self.buffers.iter_mut().zip(self.integrators.iter_mut()) {
+            *integrator += buffer[self.read_idx];
+            buffer[self.read_idx] = 0.0;
+        }
+
+        self.read_idx = (self.read_idx + 1) & BUFFER_MASK;
+
+        Some((self.integrators[0] as f32, self.integrators[1] as f32))
+    }
+}
+
+fn get_kernel() -> &'static Kernel {
+    static KERNEL: OnceLock<Box<Kernel>> = OnceLock::new();
+
+    KERNEL.get_or_init(|| {
+        let mut kernel = Box::new([[0.0; KERNEL_WIDTH]; PHASES + 1]);
+
+        for (phase, taps) in kernel.iter_mut().enumerate() {
+            let frac = phase as f64 / PHASES as f64;
+
+            for (i, tap) in taps.iter_mut().enumerate() {
+                let x = (i + 1) as f64 - HALF_WIDTH as f64 - frac;
+                *tap = sinc(x * CUTOFF) * blackman(x / HALF_WIDTH as f64);
+            }
+
+            // every step must end at exactly its delta, otherwise DC would drift
+            let sum: f64 = taps.iter().sum();
+            taps.iter_mut().for_each(|tap| *tap /= sum);
+        }
+
+        kernel
+    })
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        return 1.0;
+    }
+
+    let x = x * std::f64::consts::PI;
+    x.sin() / x
+}
+
+/// Window over -1..1, zero outside.
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+
+    let x = (x + 1.0) * std::f64::consts::PI;
+    0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CPU_CLOCK_SPEED;
+
+    const SAMPLE_RATE: u32 = 48000;
+    const FFT_SIZE: usize = 4096;
+    const AUDIBLE_LIMIT: f64 = 16000.0;
+
+    #[test]
+    fn test_step_settles() {
+        let mut blip = BlipBuf::new(CPU_CLOCK_SPEED, SAMPLE_RATE);
+        blip.set_amplitudes(1.0, -1.0);
+        let samples: Vec<(f32, f32)> = (0..CPU_CLOCK_SPEED / 100)
+            .filter_map(|_| blip.clock(1))
+            .collect();
+
+        assert!(samples.len().abs_diff(SAMPLE_RATE as usize / 100) <= 1);
+        assert!(samples[0].0.abs() < 0.01);
+        assert!((samples[samples.len() - 1].0 - 1.0).abs() < 1e-6);
+        assert!((samples[samples.len() - 1].1 + 1.0).abs() < 1e-6);
+    }
+
+    /// Renders a square wave sweep with band-limited and nearest-neighbor resampling and
+    /// compares the energy of audible components that are not harmonics of the tone.
+    #[test]
+    fn test_sweep_aliasing() {
+        for freq in (1170..=9000).step_by(1130) {
+            // as on hardware the period is a whole number of clocks
+            let period = (CPU_CLOCK_SPEED / freq) & !1;
+            let freq = CPU_CLOCK_SPEED as f64 / period as f64;
+            let blip_samples = render_square(period, |amplitude, blip| {
+                blip.set_amplitudes(amplitude, amplitude);
+                blip.clock(1).map(|(left, _)| left)
+            });
+            let nearest_samples =
+                render_square(period, |amplitude, blip| blip.clock(1).map(|_| amplitude));
+
+            let blip_aliasing = aliasing_ratio(&blip_samples, freq);
+            let nearest_aliasing = aliasing_ratio(&nearest_samples, freq);
+
+            assert!(
+                blip_aliasing < 1e-6,
+                "{} Hz: aliasing {} (nearest {})",
+                freq,
+                blip_aliasing,
+                nearest_aliasing
+            );
+            assert!(blip_aliasing * 1000.0 < nearest_aliasing);
+        }
+    }
+
+    fn render_square(period: u32, mut f: impl FnMut(f32, &mut BlipBuf) -> Option<f32>) -> Vec<f32> {
+        let mut blip = BlipBuf::new(CPU_CLOCK_SPEED, SAMPLE_RATE);
+        let mut samples = Vec::new();
+        let warmup = 64;
+        let mut clock = 0;
+
+        while samples.len() < FFT_SIZE + warmup {
+            let amplitude = if clock % period < period / 2 {
+                1.0
+            } else {
+                -1.0
+            };
+
+            if let Some(sample) = f(amplitude, &mut blip) {
+                samples.push(sample);
+            }
+
+            clock += 1;
+        }
+
+        samples.split_off(warmup)
+    }
+
+    /// Energy below `AUDIBLE_LIMIT` outside of the tone harmonics relative to total energy.
+    fn aliasing_ratio(samples: &[f32], freq: f64) -> f64 {
+        let spectrum = power_spectrum(samples);
+        let bin_width = SAMPLE_RATE as f64 / FFT_SIZE as f64;
+        let total: f64 = spectrum.iter().sum();
+        let mut aliasing = 0.0;
+
+        for (bin, power) in spectrum.iter().enumerate() {
+            let bin_freq = bin as f64 * bin_width;
+            let harmonic = (bin_freq / freq).round();
+            let is_harmonic = (bin_freq - harmonic * freq).abs() <= bin_width * 4.0;
+
+            if bin_freq < AUDIBLE_LIMIT && !is_harmonic {
+                aliasing += power;
+            }
+        }
+
+        aliasing / total
+    }
+
+    /// Blackman-Harris windowed radix-2 FFT, returns power of bins up to Nyquist.
+    fn power_spectrum(samples: &[f32]) -> Vec<f64> {
+        let n = samples.len();
+        let mut re: Vec<f64> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let x = 2.0 * std::f64::consts::PI * i as f64 / n as f64;
+                let w = 0.35875 - 0.48829 * x.cos() + 0.14128 * (2.0 * x).cos()
+                    - 0.01168 * (3.0 * x).cos();
+                *s as f64 * w
+            })
+            .collect();
+        let mut im = vec![0.0; n];
+
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= n {
+            let angle = -2.0 * std::f64::consts::PI / len as f64;
+
+            for start in (0..n).step_by(len) {
+                for k in 0..len / 2 {
+                    let (w_im, w_re) = (angle * k as f64).sin_cos();
+                    let (a, b) = (start + k, start + k + len / 2);
+                    let t_re = re[b] * w_re - im[b] * w_im;
+                    let t_im = re[b] * w_im + im[b] * w_re;
+                    re[b] = re[a] - t_re;
+                    im[b] = im[a] - t_im;
+                    re[a] += t_re;
+                    im[a] += t_im;
+                }
+            }
+
+            len <<= 1;
+        }
+
+        (0..n / 2).map(|i| re[i] * re[i] + im[i] * im[i]).collect()
+    }
+}
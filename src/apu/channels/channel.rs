@@ -1,12 +1,13 @@
 use crate::apu::channels::noise_channel::CH4_START_ADDRESS;
 use crate::apu::channels::square_channel::{CH1_START_ADDRESS, CH2_START_ADDRESS};
 use crate::apu::channels::wave_channel::CH3_START_ADDRESS;
+use serde::{Deserialize, Serialize};
 
 // Square 1: Sweep -> Timer -> Duty -> Length Counter -> Envelope -> Mixer
 // Square 2:          Timer -> Duty -> Length Counter -> Envelope -> Mixer
 // Wave:              Timer -> Wave -> Length Counter -> Volume   -> Mixer
 // Noise:             Timer -> LFSR -> Length Counter -> Envelope -> Mixer
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ChannelType {
     CH1,
     CH2,
@@ -14,7 +15,33 @@ pub enum ChannelType {
     CH4,
 }
 
+/// Snapshot of a channel state for the debug view.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChannelInfo {
+    pub ch_type: ChannelType,
+    /// Channel's generation circuit is on (NR52)
+    pub is_on: bool,
+    pub is_dac_enabled: bool,
+    /// Frequency of the produced tone, LFSR clock for the noise channel.
+    pub frequency: f32,
+    /// Index in `WAVE_DUTY_PATTERNS`, only square channels have it.
+    pub duty: Option<u8>,
+    /// Envelope volume 0-15. For the wave channel it is the output level applied to 15.
+    pub volume: u8,
+    pub length_counter: u16,
+    pub is_length_enabled: bool,
+}
+
 impl ChannelType {
+    pub fn index(&self) -> usize {
+        match self {
+            ChannelType::CH1 => 0,
+            ChannelType::CH2 => 1,
+            ChannelType::CH3 => 2,
+            ChannelType::CH4 => 3,
+        }
+    }
+
     pub fn get_start_address(&self) -> u16 {
         match self {
             ChannelType::CH1 => CH1_START_ADDRESS,
@@ -1,10 +1,11 @@
 use crate::apu::registers::{NRx2, NRx4};
-use crate::channels::channel::ChannelType;
+use crate::channels::channel::{ChannelInfo, ChannelType};
 use crate::dac::{DacEnable, DigitalSampleProducer};
 use crate::registers::NRx1;
 use crate::timers::envelope_timer::EnvelopeTimer;
 use crate::timers::length_timer::LengthTimer;
-use crate::{get_bit_flag, NR52};
+use crate::{get_bit_flag, CPU_CLOCK_SPEED, NR52};
+use serde::{Deserialize, Serialize};
 
 pub const CH4_START_ADDRESS: u16 = NR41_CH4_LENGTH_TIMER_ADDRESS;
 pub const CH4_END_ADDRESS: u16 = NR44_CH4_CONTROL_ADDRESS;
@@ -16,7 +17,7 @@ pub const NR44_CH4_CONTROL_ADDRESS: u16 = 0xFF23;
 
 const DIVISORS: [u16; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NoiseChannel {
     nrx1_len: NRx1,
     nrx2_envelope_and_dac: NRx2,
@@ -95,6 +96,15 @@ impl NoiseChannel {
         }
     }
 
+    /// Ticks before the frequency timer expires.
+    pub fn get_idle_ticks(&self) -> u16 {
+        self.freq_timer.saturating_sub(1)
+    }
+
+    pub fn skip(&mut self, ticks: u16) {
+        self.freq_timer -= ticks;
+    }
+
     pub fn tick(&mut self) {
         if self.freq_timer > 0 {
             self.freq_timer -= 1;
@@ -117,6 +127,22 @@ impl NoiseChannel {
         }
     }
 
+    pub fn info(&self, nr52: NR52) -> ChannelInfo {
+        let divisor = DIVISORS[self.nr43_freq_and_rnd.clock_divider() as usize] as u32;
+
+        ChannelInfo {
+            ch_type: ChannelType::CH4,
+            is_on: nr52.is_ch4_on(),
+            is_dac_enabled: self.is_dac_enabled(),
+            frequency: CPU_CLOCK_SPEED as f32
+                / (divisor << self.nr43_freq_and_rnd.clock_shift()) as f32,
+            duty: None,
+            volume: self.envelope_timer.get_volume(),
+            length_counter: self.length_timer.get_counter(),
+            is_length_enabled: self.nrx4_ctrl.is_length_enabled(),
+        }
+    }
+
     pub fn tick_length(&mut self, master_ctrl: &mut NR52) {
         self.length_timer.tick(master_ctrl, &mut self.nrx4_ctrl);
     }
@@ -145,7 +171,7 @@ impl NoiseChannel {
 
 /// FF22 — NR43: Channel 4 frequency & randomness
 /// This register allows controlling the way the amplitude is randomly switched.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NR43 {
     byte: u8,
 }
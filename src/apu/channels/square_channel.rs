@@ -1,4 +1,4 @@
-use crate::apu::channels::channel::ChannelType;
+use crate::apu::channels::channel::{ChannelInfo, ChannelType};
 use crate::apu::dac::{DacEnable, DigitalSampleProducer};
 use crate::apu::registers::{NRx1, NRx2, NRx3x4};
 use crate::apu::timers::length_timer::LengthTimer;
@@ -7,6 +7,7 @@ use crate::apu::NR52;
 use crate::get_bit_flag;
 use crate::timers::envelope_timer::EnvelopeTimer;
 use crate::timers::sweep_timer::SweepTimer;
+use serde::{Deserialize, Serialize};
 
 pub const CH1_START_ADDRESS: u16 = NR10_CH1_SWEEP_ADDRESS;
 pub const CH1_END_ADDRESS: u16 = NR14_CH1_PERIOD_HIGH_CONTROL_ADDRESS;
@@ -34,7 +35,7 @@ pub const WAVE_DUTY_PATTERNS: [[u8; 8]; 4] = [
     [0, 1, 1, 1, 1, 1, 1, 0],
 ];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SquareChannel {
     // registers
     sweep_timer: Option<SweepTimer>,
@@ -148,6 +149,21 @@ impl SquareChannel {
         }
     }
 
+    pub fn info(&self, nr52: NR52) -> ChannelInfo {
+        let period = self.nrx3x4_period_and_ctrl.get_period();
+
+        ChannelInfo {
+            ch_type: self.ch_type,
+            is_on: nr52.is_ch_on(self.ch_type),
+            is_dac_enabled: self.is_dac_enabled(),
+            frequency: 131072.0 / (2048 - period) as f32,
+            duty: Some(self.nrx1_len_timer_duty_cycle.get_duty_cycle_idx()),
+            volume: self.envelope_timer.get_volume(),
+            length_counter: self.length_timer.get_counter(),
+            is_length_enabled: self.nrx3x4_period_and_ctrl.nrx4.is_length_enabled(),
+        }
+    }
+
     fn get_offset(&self, address: u16) -> u16 {
         address - self.ch_type.get_start_address()
     }
@@ -167,6 +183,15 @@ impl SquareChannel {
             .tick(master_ctrl, &mut self.nrx3x4_period_and_ctrl.nrx4);
     }
 
+    /// Ticks before the period timer expires.
+    pub fn get_idle_ticks(&self) -> u16 {
+        self.period_timer.get_idle_ticks()
+    }
+
+    pub fn skip(&mut self, ticks: u16) {
+        self.period_timer.skip(ticks);
+    }
+
     pub fn tick(&mut self) {
         if self.period_timer.tick(&self.nrx3x4_period_and_ctrl) {
             self.duty_sequence = (self.duty_sequence + 1) & 0x07;
@@ -192,7 +217,7 @@ impl SquareChannel {
 
 /// FF10 — NR10: Channel 1 sweep
 /// This register controls CH1’s period sweep functionality.
-#[derive(Debug, Clone, Default, Copy)]
+#[derive(Debug, Clone, Default, Copy, Serialize, Deserialize)]
 pub struct NR10 {
     pub byte: u8,
 }
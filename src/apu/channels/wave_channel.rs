@@ -1,9 +1,10 @@
-use crate::apu::channels::channel::ChannelType;
+use crate::apu::channels::channel::{ChannelInfo, ChannelType};
 use crate::apu::dac::{DacEnable, DigitalSampleProducer};
 use crate::apu::registers::{NRx1, NRx3x4};
 use crate::apu::timers::length_timer::LengthTimer;
 use crate::apu::timers::period_timer::PeriodTimer;
 use crate::apu::NR52;
+use serde::{Deserialize, Serialize};
 
 pub const CH3_START_ADDRESS: u16 = CH3_NR30_DAC_ENABLE_ADDRESS;
 pub const CH3_END_ADDRESS: u16 = CH3_NR33_PERIOD_HIGH_CONTROL_ADDRESS;
@@ -36,7 +37,7 @@ impl DigitalSampleProducer for WaveChannel {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WaveChannel {
     // registers
     nrx0_dac_enable: NR30,
@@ -99,11 +100,35 @@ impl WaveChannel {
         }
     }
 
+    pub fn info(&self, nr52: NR52) -> ChannelInfo {
+        let period = self.nrx3x4_period_and_ctrl.get_period();
+
+        ChannelInfo {
+            ch_type: ChannelType::CH3,
+            is_on: nr52.is_ch3_on(),
+            is_dac_enabled: self.is_dac_enabled(),
+            frequency: 65536.0 / (2048 - period) as f32,
+            duty: None,
+            volume: 0x0F >> self.volume_shift,
+            length_counter: self.length_timer.get_counter(),
+            is_length_enabled: self.nrx3x4_period_and_ctrl.nrx4.is_length_enabled(),
+        }
+    }
+
     pub fn tick_length(&mut self, master_ctrl: &mut NR52) {
         self.length_timer
             .tick(master_ctrl, &mut self.nrx3x4_period_and_ctrl.nrx4);
     }
 
+    /// Ticks before the period timer expires.
+    pub fn get_idle_ticks(&self) -> u16 {
+        self.period_timer.get_idle_ticks()
+    }
+
+    pub fn skip(&mut self, ticks: u16) {
+        self.period_timer.skip(ticks);
+    }
+
     pub fn tick(&mut self) {
         if self.period_timer.tick(&self.nrx3x4_period_and_ctrl) {
             self.wave_ram.inc_sample_index();
@@ -123,7 +148,7 @@ impl WaveChannel {
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct WaveRam {
     // 32 samples, 4 bit each
     bytes: [u8; 16],
@@ -142,6 +167,22 @@ impl WaveRam {
         self.bytes[index as usize] = value;
     }
 
+    /// All 32 4-bit samples in playback order.
+    pub fn get_samples(&self) -> [u8; 32] {
+        let mut samples = [0; 32];
+
+        for (i, byte) in self.bytes.iter().enumerate() {
+            samples[i * 2] = byte >> 4;
+            samples[i * 2 + 1] = byte & 0x0F;
+        }
+
+        samples
+    }
+
+    pub fn get_sample_index(&self) -> usize {
+        self.sample_index
+    }
+
     fn read_sample(&self) -> u8 {
         let byte_index = self.sample_index / 2;
         let is_high_nibble = self.sample_index % 2 == 0;
@@ -169,7 +210,7 @@ impl WaveRam {
 }
 
 // DAC enable
-#[derive(Clone, Debug, Default, Copy)]
+#[derive(Clone, Debug, Default, Copy, Serialize, Deserialize)]
 pub struct NR30 {
     byte: u8,
 }
@@ -185,7 +226,7 @@ impl NR30 {
 }
 
 /// Output level
-#[derive(Clone, Debug, Default, Copy)]
+#[derive(Clone, Debug, Default, Copy, Serialize, Deserialize)]
 pub struct NR32 {
     byte: u8,
 }
@@ -1,6 +1,7 @@
 use crate::apu::{NR50, NR51};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Mixer {
     pub nr51_panning: NR51,
     pub nr50_volume: NR50,
@@ -9,48 +10,74 @@ pub struct Mixer {
     pub sample2: f32,
     pub sample3: f32,
     pub sample4: f32,
+
+    /// Per channel (CH1-CH4) flags for debugging and ripping, not part of the hardware.
+    pub muted: [bool; 4],
+    /// When any channel is soloed only soloed channels are heard.
+    pub soloed: [bool; 4],
 }
 
 impl Mixer {
+    pub fn get_samples(&self) -> [f32; 4] {
+        [self.sample1, self.sample2, self.sample3, self.sample4]
+    }
+
+    pub fn set_samples(&mut self, samples: [f32; 4]) {
+        [self.sample1, self.sample2, self.sample3, self.sample4] = samples;
+    }
+
+    /// Returns false for muted channels or when another channel is soloed.
+    pub fn is_audible(&self, ch_idx: usize) -> bool {
+        if self.soloed.iter().any(|s| *s) {
+            return self.soloed[ch_idx];
+        }
+
+        !self.muted[ch_idx]
+    }
+
     /// Combines samples from all channels
     pub fn mix(&self) -> (f32, f32) {
         let mut left_sample = 0.0;
         let mut right_sample = 0.0;
+        let sample1 = self.audible_sample(0, self.sample1);
+        let sample2 = self.audible_sample(1, self.sample2);
+        let sample3 = self.audible_sample(2, self.sample3);
+        let sample4 = self.audible_sample(3, self.sample4);
 
         // Channel 1
         if self.nr51_panning.ch1_left() {
-            left_sample += self.sample1;
+            left_sample += sample1;
         }
 
         if self.nr51_panning.ch1_right() {
-            right_sample += self.sample1;
+            right_sample += sample1;
         }
 
         // Channel 2
         if self.nr51_panning.ch2_left() {
-            left_sample += self.sample2;
+            left_sample += sample2;
         }
 
         if self.nr51_panning.ch2_right() {
-            right_sample += self.sample2;
+            right_sample += sample2;
         }
 
         // Channel 3
         if self.nr51_panning.ch3_left() {
-            left_sample += self.sample3;
+            left_sample += sample3;
         }
 
         if self.nr51_panning.ch3_right() {
-            right_sample += self.sample3;
+            right_sample += sample3;
         }
 
         // Channel 4
         if self.nr51_panning.ch4_left() {
-            left_sample += self.sample4;
+            left_sample += sample4;
         }
 
         if self.nr51_panning.ch4_right() {
-            right_sample += self.sample4;
+            right_sample += sample4;
         }
 
         let (left_sample, right_sample) = self.amplify(left_sample, right_sample);
@@ -58,6 +85,14 @@ impl Mixer {
         (adjust_volume(left_sample / 4.0), adjust_volume(right_sample / 4.0))
     }
 
+    fn audible_sample(&self, ch_idx: usize, sample: f32) -> f32 {
+        if self.is_audible(ch_idx) {
+            sample
+        } else {
+            0.0
+        }
+    }
+
     fn amplify(&self, sample_left: f32, sample_right: f32) -> (f32, f32) {
         let left_sample = apply_volume(sample_left, self.nr50_volume.left_volume());
         let right_sample = apply_volume(sample_right, self.nr50_volume.right_volume());
@@ -74,3 +109,41 @@ fn apply_volume(sample: f32, volume: u8) -> f32 {
     let volume_factor = (volume as f32 + 1.0) / 8.0;
     sample * volume_factor
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_mixer() -> Mixer {
+        let mut mixer = Mixer::default();
+        mixer.nr51_panning.byte = 0xFF;
+        mixer.nr50_volume.byte = 0x77;
+        mixer.sample1 = 1.0;
+        mixer.sample2 = 1.0;
+        mixer.sample3 = 1.0;
+        mixer.sample4 = 1.0;
+
+        mixer
+    }
+
+    #[test]
+    fn test_mute() {
+        let mut mixer = new_mixer();
+        assert_eq!(mixer.mix(), (1.0, 1.0));
+
+        mixer.muted[0] = true;
+        mixer.muted[3] = true;
+        assert_eq!(mixer.mix(), (0.5, 0.5));
+    }
+
+    #[test]
+    fn test_solo_overrides_mute() {
+        let mut mixer = new_mixer();
+        mixer.muted[1] = true;
+        mixer.soloed[1] = true;
+
+        assert_eq!(mixer.mix(), (0.25, 0.25));
+        assert!(!mixer.is_audible(0));
+        assert!(mixer.is_audible(1));
+    }
+}
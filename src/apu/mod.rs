@@ -4,6 +4,8 @@ pub mod timers;
 pub mod channels;
 pub mod mixer;
 pub mod dac;
+pub mod scope;
+pub mod blip;
 mod hpf;
 
 pub use apu::*;
@@ -1,10 +1,11 @@
 use crate::apu::channels::channel::ChannelType;
 use crate::{get_bit_flag, set_bit, LittleEndianBytes};
+use serde::{Deserialize, Serialize};
 
 pub const NRX4_LENGTH_ENABLE_POS: u8 = 6;
 
 /// FF11 — NR11: Channel 1 length timer & duty cycle
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct NRx1 {
     pub byte: u8,
     ch_type: ChannelType,
@@ -46,7 +47,7 @@ impl NRx1 {
 
 /// FF12 — NR12: Channel 1 volume & envelope
 /// This register controls the digital amplitude of the “high” part of the pulse, and the sweep applied to that setting.
-#[derive(Debug, Clone, Default, Copy)]
+#[derive(Debug, Clone, Default, Copy, Serialize, Deserialize)]
 pub struct NRx2 {
     pub byte: u8,
 }
@@ -71,7 +72,7 @@ impl NRx2 {
 }
 
 /// Merged together NRX3 and NRX4 for convenience
-#[derive(Clone, Debug, Default, Copy)]
+#[derive(Clone, Debug, Default, Copy, Serialize, Deserialize)]
 pub struct NRx3x4 {
     pub period_low: NRx3,
     pub nrx4: NRx4,
@@ -94,7 +95,7 @@ impl NRx3x4 {
 }
 
 ///  Period low, write-only
-#[derive(Clone, Debug, Default, Copy)]
+#[derive(Clone, Debug, Default, Copy, Serialize, Deserialize)]
 pub struct NRx3 {
     byte: u8,
 }
@@ -106,7 +107,7 @@ impl NRx3 {
 }
 
 /// Period high & length timer control
-#[derive(Clone, Debug, Default, Copy)]
+#[derive(Clone, Debug, Default, Copy, Serialize, Deserialize)]
 pub struct NRx4 {
     byte: u8,
 }
@@ -0,0 +1,83 @@
+pub const SCOPE_SIZE: usize = 1024;
+pub const CHANNELS_COUNT: usize = 4;
+
+/// Ring buffers with the latest DAC output of every channel, before panning and muting.
+/// Storage is allocated on first push and dropped on clone, so rewind snapshots stay small.
+#[derive(Debug, Default)]
+pub struct Scope {
+    samples: Option<Box<[[f32; SCOPE_SIZE]; CHANNELS_COUNT]>>,
+    idx: usize,
+}
+
+impl Clone for Scope {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Scope {
+    pub fn push(&mut self, samples: [f32; CHANNELS_COUNT]) {
+        let buffers = self
+            .samples
+            .get_or_insert_with(|| Box::new([[0.0; SCOPE_SIZE]; CHANNELS_COUNT]));
+
+        for (buffer, sample) in buffers.iter_mut().zip(samples) {
+            buffer[self.idx] = sample;
+        }
+
+        self.idx = (self.idx + 1) % SCOPE_SIZE;
+    }
+
+    /// Returns the channel samples from the oldest to the newest.
+    pub fn get_samples(&self, ch_idx: usize) -> Vec<f32> {
+        let Some(buffers) = self.samples.as_ref() else {
+            return vec![0.0; SCOPE_SIZE];
+        };
+
+        let buffer = &buffers[ch_idx];
+        buffer[self.idx..]
+            .iter()
+            .chain(buffer[..self.idx].iter())
+            .copied()
+            .collect()
+    }
+}
+
+/// Finds a rising edge in the first half of the samples so the trace stays in place between
+/// frames. Returns the window of `len` samples starting there.
+pub fn trigger_window(samples: &[f32], len: usize) -> &[f32] {
+    let len = len.min(samples.len());
+    let search_end = samples.len() - len;
+    let start = (1..=search_end)
+        .find(|&i| samples[i - 1] <= 0.0 && samples[i] > 0.0)
+        .unwrap_or(search_end);
+
+    &samples[start..start + len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_order() {
+        let mut scope = Scope::default();
+
+        for i in 0..SCOPE_SIZE + 2 {
+            scope.push([i as f32, 0.0, 0.0, 0.0]);
+        }
+
+        let samples = scope.get_samples(0);
+        assert_eq!(samples[0], 2.0);
+        assert_eq!(samples[SCOPE_SIZE - 1], (SCOPE_SIZE + 1) as f32);
+        assert!(scope.clone().samples.is_none());
+    }
+
+    #[test]
+    fn test_trigger_window() {
+        let samples = [0.5, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0];
+        let window = trigger_window(&samples, 4);
+
+        assert_eq!(window, &[1.0, 1.0, -1.0, -1.0]);
+    }
+}
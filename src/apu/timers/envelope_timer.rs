@@ -1,6 +1,7 @@
 use crate::registers::NRx2;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct EnvelopeTimer {
     counter: u8,
     volume: u8,
@@ -1,6 +1,7 @@
 use crate::apu::channels::channel::ChannelType;
 use crate::apu::registers::{NRx1, NRx4};
 use crate::apu::NR52;
+use serde::{Deserialize, Serialize};
 
 //A length counter disables a channel when it decrements to zero. It contains an internal counter
 // and enabled flag. Writing a byte to NRx1 loads the counter with 64-data (256-data for wave channel).
@@ -12,7 +13,7 @@ use crate::apu::NR52;
 //
 // Each length counter is clocked at 256 Hz by the frame sequencer. When clocked while enabled by NRx4
 // and the counter is not zero, it is decremented. If it becomes zero, the channel is disabled.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LengthTimer {
     counter: u16,
     ch_type: ChannelType,
@@ -38,6 +39,10 @@ impl LengthTimer {
         }
     }
 
+    pub fn get_counter(&self) -> u16 {
+        self.counter
+    }
+
     pub fn is_expired(&self) -> bool {
         self.counter == 0
     }
@@ -1,7 +1,8 @@
 use crate::apu::channels::channel::ChannelType;
 use crate::apu::registers::NRx3x4;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PeriodTimer {
     counter: u16,
     ch_type: ChannelType,
@@ -29,6 +30,16 @@ impl PeriodTimer {
         false
     }
 
+    /// Ticks before the one which expires the counter.
+    pub fn get_idle_ticks(&self) -> u16 {
+        self.counter.saturating_sub(1)
+    }
+
+    /// Runs ticks which do not expire the counter, see `get_idle_ticks`.
+    pub fn skip(&mut self, ticks: u16) {
+        self.counter -= ticks;
+    }
+
     pub fn reload(&mut self, nrx3x4: &NRx3x4) {
         self.counter = (2048 - nrx3x4.get_period()) * self.get_multiplier();
     }
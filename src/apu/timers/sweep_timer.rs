@@ -2,8 +2,9 @@ use crate::channels::channel::ChannelType;
 use crate::channels::square_channel::NR10;
 use crate::registers::NRx3x4;
 use crate::NR52;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct SweepTimer {
     counter: u8,
     shadow_frequency: u16,
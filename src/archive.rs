@@ -0,0 +1,326 @@
+use flate2::read::GzDecoder;
+use sevenz_rust::{Password, SevenZReader};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+pub const ROM_EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+pub const ARCHIVE_EXTENSIONS: [&str; 3] = ["zip", "gz", "7z"];
+/// Separates an archive from the ROM inside it, as in `games.zip#tetris.gb`.
+pub const ENTRY_SEPARATOR: char = '#';
+
+/// ROM read from a plain file or from an archive.
+#[derive(Debug, Clone)]
+pub struct RomFile {
+    /// File name of the ROM itself, for archives the name of the entry.
+    pub name: String,
+    pub bytes: Vec<u8>,
+    /// File names of the patches applied to `bytes`.
+    pub patches: Vec<String>,
+}
+
+impl RomFile {
+    /// Battery saves are named after the ROM, not the archive it is packed in. A patched
+    /// image gets the CRC32 of its bytes appended, so it doesn't share the original's save.
+    pub fn get_save_name(&self) -> String {
+        let stem = Path::new(&self.name)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+
+        if self.patches.is_empty() {
+            format!("{}.sav", stem)
+        } else {
+            format!("{}_{:08X}.sav", stem, crc32fast::hash(&self.bytes))
+        }
+    }
+}
+
+/// Reads a ROM file, an archive holding a single ROM, or an `<ARCHIVE>#<ENTRY>` path.
+pub fn read_rom_file(path: &str) -> Result<RomFile, String> {
+    let (archive_path, entry) = split_entry_path(path);
+
+    if !Path::new(archive_path).exists() {
+        return Err(format!("File not found: {}", archive_path));
+    }
+
+    if !is_archive_path(Path::new(archive_path)) {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+        return Ok(RomFile {
+            name: get_file_name(Path::new(path)),
+            bytes,
+            patches: Vec::new(),
+        });
+    }
+
+    let entry = match entry {
+        Some(entry) => entry.to_string(),
+        None => {
+            let mut roms = list_archive_roms(archive_path)?;
+
+            match roms.len() {
+                0 => return Err(format!("No ROMs found in {}", archive_path)),
+                1 => roms.remove(0),
+                _ => {
+                    return Err(format!(
+                        "Several ROMs found in {}, choose one with {}{}<NAME>: {}",
+                        archive_path,
+                        archive_path,
+                        ENTRY_SEPARATOR,
+                        roms.join(", ")
+                    ))
+                }
+            }
+        }
+    };
+    let bytes = read_archive_entry(archive_path, &entry)?;
+
+    Ok(RomFile {
+        name: get_file_name(Path::new(&entry)),
+        bytes,
+        patches: Vec::new(),
+    })
+}
+
+/// Names of the ROM entries in the archive, sorted.
+pub fn list_archive_roms(path: &str) -> Result<Vec<String>, String> {
+    let mut names = match get_extension(Path::new(path)).as_str() {
+        "zip" => {
+            let archive = ZipArchive::new(open_file(path)?).map_err(|e| e.to_string())?;
+            archive.file_names().map(|name| name.to_string()).collect()
+        }
+        "7z" => {
+            let reader = SevenZReader::open(path, Password::empty()).map_err(|e| e.to_string())?;
+            reader
+                .archive()
+                .files
+                .iter()
+                .filter(|entry| !entry.is_directory())
+                .map(|entry| entry.name().to_string())
+                .collect()
+        }
+        // a single stream, named after the archive
+        "gz" => vec![get_gz_entry_name(path)],
+        _ => return Err(format!("Unsupported archive: {}", path)),
+    };
+    names.retain(|name| is_rom_path(Path::new(name)));
+    names.sort();
+
+    Ok(names)
+}
+
+fn read_archive_entry(path: &str, entry: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+
+    match get_extension(Path::new(path)).as_str() {
+        "zip" => {
+            let mut archive = ZipArchive::new(open_file(path)?).map_err(|e| e.to_string())?;
+            let mut file = archive
+                .by_name(entry)
+                .map_err(|e| format!("Failed to find {}: {}", entry, e))?;
+            file.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        }
+        "7z" => {
+            let mut reader =
+                SevenZReader::open(path, Password::empty()).map_err(|e| e.to_string())?;
+            let mut is_found = false;
+            reader
+                .for_each_entries(|archive_entry, data| {
+                    if archive_entry.name() != entry {
+                        return Ok(true);
+                    }
+
+                    data.read_to_end(&mut bytes)?;
+                    is_found = true;
+
+                    Ok(false)
+                })
+                .map_err(|e| e.to_string())?;
+
+            if !is_found {
+                return Err(format!("Failed to find {} in {}", entry, path));
+            }
+        }
+        "gz" => {
+            GzDecoder::new(open_file(path)?)
+                .read_to_end(&mut bytes)
+                .map_err(|e| e.to_string())?;
+        }
+        _ => return Err(format!("Unsupported archive: {}", path)),
+    }
+
+    Ok(bytes)
+}
+
+/// Splits `<ARCHIVE>#<ENTRY>` at the first `#` which follows an archive extension, any other
+/// `#` is a part of a file name.
+pub fn split_entry_path(path: &str) -> (&str, Option<&str>) {
+    for (index, _) in path.match_indices(ENTRY_SEPARATOR) {
+        let archive = &path[..index];
+
+        if is_archive_path(Path::new(archive)) {
+            return (archive, Some(&path[index + 1..]));
+        }
+    }
+
+    (path, None)
+}
+
+pub fn get_entry_path(archive: &str, entry: &str) -> String {
+    format!("{}{}{}", archive, ENTRY_SEPARATOR, entry)
+}
+
+pub fn is_rom_path(path: &Path) -> bool {
+    ROM_EXTENSIONS.contains(&get_extension(path).as_str())
+}
+
+pub fn is_archive_path(path: &Path) -> bool {
+    ARCHIVE_EXTENSIONS.contains(&get_extension(path).as_str())
+}
+
+/// `tetris.gb.gz` holds `tetris.gb`, a bare `tetris.gz` is assumed to hold a `.gb`.
+fn get_gz_entry_name(path: &str) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    if is_rom_path(Path::new(&stem)) {
+        stem
+    } else {
+        format!("{}.gb", stem)
+    }
+}
+
+fn get_extension(path: &Path) -> String {
+    path.extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+fn get_file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn open_file(path: &str) -> Result<File, String> {
+    File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    fn get_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+
+        for (name, bytes) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_read_zip() {
+        let dir = get_dir("gmboy_test_archive_zip");
+        let single = dir.join("single.zip");
+        let several = dir.join("several.zip");
+        write_zip(&single, &[("readme.txt", b"hi"), ("Game.gb", &[1, 2, 3])]);
+        write_zip(&several, &[("b.gbc", &[2]), ("a.gb", &[1])]);
+
+        let rom = read_rom_file(single.to_str().unwrap()).unwrap();
+        assert_eq!(rom.name, "Game.gb");
+        assert_eq!(rom.bytes, vec![1, 2, 3]);
+        assert_eq!(rom.get_save_name(), "Game.sav");
+        let mut patched = rom.clone();
+        patched.patches.push("Game.ips".to_string());
+        assert_eq!(patched.get_save_name(), "Game_55BC801D.sav");
+
+        let several = several.to_str().unwrap();
+        assert_eq!(list_archive_roms(several).unwrap(), vec!["a.gb", "b.gbc"]);
+        assert!(read_rom_file(several).unwrap_err().contains("a.gb, b.gbc"));
+
+        let rom = read_rom_file(&get_entry_path(several, "b.gbc")).unwrap();
+        assert_eq!(rom.name, "b.gbc");
+        assert_eq!(rom.bytes, vec![2]);
+        assert!(read_rom_file(&get_entry_path(several, "c.gb")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_gz() {
+        let dir = get_dir("gmboy_test_archive_gz");
+        let path = dir.join("tetris.gb.gz");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        encoder.write_all(&[4, 5, 6]).unwrap();
+        encoder.finish().unwrap();
+
+        let rom = read_rom_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(rom.name, "tetris.gb");
+        assert_eq!(rom.bytes, vec![4, 5, 6]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_7z() {
+        let dir = get_dir("gmboy_test_archive_7z");
+        let path = dir.join("roms.7z");
+        let mut writer = SevenZWriter::create(&path).unwrap();
+
+        for (name, bytes) in [("a.gb", [7u8, 8]), ("b.gb", [9, 10])] {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            entry.has_stream = true;
+            writer.push_archive_entry(entry, Some(&bytes[..])).unwrap();
+        }
+
+        writer.finish().unwrap();
+
+        let path = path.to_str().unwrap();
+        assert_eq!(list_archive_roms(path).unwrap(), vec!["a.gb", "b.gb"]);
+        let rom = read_rom_file(&get_entry_path(path, "b.gb")).unwrap();
+        assert_eq!(rom.bytes, vec![9, 10]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_entry_path() {
+        assert_eq!(
+            split_entry_path("roms/all.zip#a#1.gb"),
+            ("roms/all.zip", Some("a#1.gb"))
+        );
+        assert_eq!(
+            split_entry_path("roms/#1.zip#a.gb"),
+            ("roms/#1.zip", Some("a.gb"))
+        );
+        assert_eq!(split_entry_path("roms/#1.gb"), ("roms/#1.gb", None));
+    }
+}
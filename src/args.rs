@@ -0,0 +1,186 @@
+use crate::capture::screenshot::ScreenshotFormat;
+use crate::capture::video_recorder::VideoFormat;
+use std::path::PathBuf;
+
+pub const USAGE: &str =
+    "Usage: gmboy [CART] [--headless --frames N] [--benchmark [--frames N]] [--record-audio FILE] [--record-channels] [--record-video FILE.avi|gif] [--patch FILE.ips|ups|bps]... [--sample-rate HZ] [--screenshot FILE] [--screenshot-format rgba|ids|xN] [--camera FILE.png|DIR]... [--camera-photos DIR]";
+
+/// Command line arguments of the `gmboy` binary.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    /// ROM, archive with a single ROM, or `<ARCHIVE>#<ROM>`.
+    pub cart_path: Option<String>,
+    /// Runs without window and audio device as fast as possible.
+    pub headless: bool,
+    /// Runs the cart, or the bundled test ROMs without one, uncapped and reports the speed.
+    pub benchmark: bool,
+    /// Number of frames to run in headless or benchmark mode.
+    pub frames: Option<usize>,
+    /// Applied to the cart in the given order instead of the patches found next to it.
+    pub patches: Vec<PathBuf>,
+    pub record_audio: Option<PathBuf>,
+    pub record_channels: bool,
+    pub record_video: Option<PathBuf>,
+    pub sample_rate: Option<u32>,
+    /// PNG of the last frame, saved when a headless run ends.
+    pub screenshot: Option<PathBuf>,
+    pub screenshot_format: ScreenshotFormat,
+    /// What the Game Boy Camera sees: a PNG, or a sequence from several PNGs or a directory.
+    pub camera_images: Vec<PathBuf>,
+    /// Directory for the photos in camera SRAM, saved when a headless run ends.
+    pub camera_photos: Option<PathBuf>,
+}
+
+impl Args {
+    /// Parses arguments without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => parsed.headless = true,
+                "--benchmark" => parsed.benchmark = true,
+                "--frames" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let frames = value
+                        .parse()
+                        .map_err(|_| format!("Invalid frames count: {}", value))?;
+                    parsed.frames = Some(frames);
+                }
+                "--record-audio" => {
+                    parsed.record_audio = Some(PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                "--record-channels" => parsed.record_channels = true,
+                "--patch" => parsed
+                    .patches
+                    .push(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--record-video" => {
+                    let path = PathBuf::from(next_value(&mut args, &arg)?);
+                    VideoFormat::from_path(&path)?;
+                    parsed.record_video = Some(path);
+                }
+                "--sample-rate" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let sample_rate = value
+                        .parse()
+                        .ok()
+                        .filter(|rate| (8000..=192000).contains(rate))
+                        .ok_or(format!("Invalid sample rate: {}", value))?;
+                    parsed.sample_rate = Some(sample_rate);
+                }
+                "--screenshot" => {
+                    parsed.screenshot = Some(PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                "--screenshot-format" => {
+                    parsed.screenshot_format =
+                        ScreenshotFormat::parse(&next_value(&mut args, &arg)?)?
+                }
+                "--camera" => parsed
+                    .camera_images
+                    .push(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--camera-photos" => {
+                    parsed.camera_photos = Some(PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+                _ if parsed.cart_path.is_none() => parsed.cart_path = Some(arg),
+                _ => return Err(format!("Unexpected argument: {}", arg)),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Checks the options go together, once `cart_path` has its fallback applied.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.headless && (self.cart_path.is_none() || self.frames.is_none()) {
+            return Err("Headless mode needs a cart and --frames".to_string());
+        }
+
+        if self.headless && self.benchmark {
+            return Err("--headless and --benchmark can't be combined".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, name: &str) -> Result<String, String> {
+    args.next().ok_or(format!("Missing value for {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        let args = Args::parse(args.split_whitespace().map(|s| s.to_string()))?;
+        args.validate()?;
+
+        Ok(args)
+    }
+
+    #[test]
+    fn test_parse_headless() {
+        let args = parse(
+            "game.gb --headless --frames 600 --record-audio out.wav --record-channels \
+            --record-video out.gif --sample-rate 44100 --patch en.ips --patch fix.bps \
+            --screenshot out.png --screenshot-format x2 --camera a.png --camera b.png \
+            --camera-photos photos",
+        )
+        .unwrap();
+
+        assert_eq!(
+            args,
+            Args {
+                cart_path: Some("game.gb".to_string()),
+                headless: true,
+                benchmark: false,
+                frames: Some(600),
+                patches: vec![PathBuf::from("en.ips"), PathBuf::from("fix.bps")],
+                record_audio: Some(PathBuf::from("out.wav")),
+                record_channels: true,
+                record_video: Some(PathBuf::from("out.gif")),
+                sample_rate: Some(44100),
+                screenshot: Some(PathBuf::from("out.png")),
+                screenshot_format: ScreenshotFormat::Scaled(2),
+                camera_images: vec![PathBuf::from("a.png"), PathBuf::from("b.png")],
+                camera_photos: Some(PathBuf::from("photos")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_fallback_cart() {
+        let mut args = Args::parse(["--headless", "--frames", "10"].map(String::from)).unwrap();
+        assert!(args.validate().is_err());
+
+        args.cart_path = Some("game.gb".to_string());
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_parse_benchmark() {
+        let args = parse("--benchmark --frames 100").unwrap();
+
+        assert!(args.benchmark);
+        assert_eq!(args.cart_path, None);
+        assert_eq!(args.frames, Some(100));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("").unwrap(), Args::default());
+        assert!(parse("game.gb --headless").is_err());
+        assert!(parse("game.gb --frames x").is_err());
+        assert!(parse("game.gb --record-audio").is_err());
+        assert!(parse("game.gb --patch").is_err());
+        assert!(parse("game.gb --camera").is_err());
+        assert!(parse("game.gb --unknown").is_err());
+        assert!(parse("a.gb b.gb").is_err());
+        assert!(parse("game.gb --sample-rate 100").is_err());
+        assert!(parse("game.gb --screenshot-format bmp").is_err());
+        assert!(parse("game.gb --record-video out.mp4").is_err());
+        assert!(parse("game.gb --headless --benchmark --frames 10").is_err());
+    }
+}
@@ -1,14 +1,22 @@
 use crate::auxiliary::dma::Dma;
+use crate::auxiliary::profiler::{Profiler, Subsystem};
+use crate::auxiliary::scheduler::{EventType, Scheduler};
 use crate::bus::Bus;
 use crate::ppu::Ppu;
+use serde::{Deserialize, Serialize};
+use std::thread;
 use std::time::{Duration, Instant};
 
 pub const T_CYCLES_PER_M_CYCLE: usize = 4;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Clock {
     pub t_cycles: usize,
     pub ppu: Option<Ppu>,
+    /// Set to time the subsystems, used by the benchmark mode.
+    #[serde(skip)]
+    pub profiler: Option<Profiler>,
+    scheduler: Scheduler,
 }
 
 impl Clock {
@@ -16,14 +24,39 @@ impl Clock {
         Self {
             t_cycles: 0,
             ppu: Some(ppu),
+            profiler: None,
+            scheduler: Default::default(),
         }
     }
 
+    /// Components are ticked per T-cycle only in M-cycles which contain their deadline, the
+    /// rest are advanced at once. The CPU accesses the bus between M-cycles, so it always
+    /// sees the state it would with ticking every T-cycle.
     pub fn m_cycles(&mut self, m_cycles: usize, bus: &mut Bus) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.begin();
+        }
+
         for _ in 0..m_cycles {
+            if bus.take_io_written() {
+                self.schedule_all(bus);
+            }
+
+            let now = self.t_cycles;
             self.t_cycles(T_CYCLES_PER_M_CYCLE, bus);
-            Dma::tick(bus);
+
+            if self
+                .scheduler
+                .is_due(EventType::Dma, now, T_CYCLES_PER_M_CYCLE)
+            {
+                Dma::tick(bus);
+                self.schedule_dma(bus);
+            }
+
+            self.lap(Subsystem::Dma);
         }
+
+        bus.cart.tick(m_cycles * T_CYCLES_PER_M_CYCLE);
     }
 
     pub fn get_m_cycles(&self) -> usize {
@@ -31,23 +64,169 @@ impl Clock {
     }
 
     fn t_cycles(&mut self, t_cycles: usize, bus: &mut Bus) {
-        for _ in 0..t_cycles {
-            self.t_cycles = self.t_cycles.wrapping_add(1);
+        let now = self.t_cycles;
+        self.t_cycles = self.t_cycles.wrapping_add(t_cycles);
 
-            bus.io.timer.tick(&mut bus.io.interrupts);
+        if self.scheduler.is_due(EventType::Timer, now, t_cycles) {
+            for _ in 0..t_cycles {
+                bus.io.timer.tick(&mut bus.io.interrupts);
+            }
+
+            self.schedule_timer(bus);
+        } else {
+            bus.io.timer.skip(t_cycles);
+        }
+
+        self.lap(Subsystem::Timer);
+
+        if let Some(ppu) = self.ppu.as_mut() {
+            if self.scheduler.is_due(EventType::Ppu, now, t_cycles) {
+                for _ in 0..t_cycles {
+                    ppu.tick(bus);
+                }
 
-            if let Some(ppu) = self.ppu.as_mut() {
-                ppu.tick(bus);
+                self.schedule_ppu(bus);
+            } else {
+                ppu.skip(t_cycles, bus);
             }
-            
-            bus.io.apu.tick();
+
+            self.lap(Subsystem::Ppu);
+        }
+
+        if self.scheduler.is_due(EventType::Apu, now, t_cycles) {
+            for _ in 0..t_cycles {
+                bus.io.apu.tick();
+            }
+
+            self.schedule_apu(bus);
+        } else {
+            bus.io.apu.skip(t_cycles);
+        }
+
+        self.lap(Subsystem::Apu);
+    }
+
+    fn lap(&mut self, subsystem: Subsystem) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.lap(subsystem);
         }
     }
+
+    /// Registers written by the CPU may move any deadline.
+    fn schedule_all(&mut self, bus: &Bus) {
+        self.schedule_timer(bus);
+        self.schedule_ppu(bus);
+        self.schedule_apu(bus);
+        self.schedule_dma(bus);
+    }
+
+    fn schedule_timer(&mut self, bus: &Bus) {
+        let ticks = bus.io.timer.get_ticks_to_event();
+        self.scheduler
+            .schedule(EventType::Timer, self.t_cycles, ticks);
+    }
+
+    fn schedule_ppu(&mut self, bus: &Bus) {
+        let ticks = self.ppu.as_ref().map(|ppu| ppu.get_ticks_to_event(bus));
+        self.scheduler
+            .schedule(EventType::Ppu, self.t_cycles, ticks);
+    }
+
+    fn schedule_apu(&mut self, bus: &Bus) {
+        let ticks = bus.io.apu.get_ticks_to_event();
+        self.scheduler
+            .schedule(EventType::Apu, self.t_cycles, Some(ticks));
+    }
+
+    fn schedule_dma(&mut self, bus: &Bus) {
+        let ticks = bus.dma.is_active.then_some(0);
+        self.scheduler
+            .schedule(EventType::Dma, self.t_cycles, ticks);
+    }
 }
 
-pub fn spin_wait(duration: Duration) {
+/// Sleeping may overshoot by about a scheduler tick, so only the rest is spun.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+/// Sleeps most of the duration and spins the remainder for precision.
+pub fn precise_wait(duration: Duration) {
     let start = Instant::now();
+
+    if duration > SPIN_THRESHOLD {
+        thread::sleep(duration - SPIN_THRESHOLD);
+    }
+
     while start.elapsed() < duration {
         std::hint::spin_loop();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart::Cart;
+
+    /// Ticks every component each T-cycle, as the clock did before the scheduler.
+    fn tick_reference(ppu: &mut Ppu, bus: &mut Bus) {
+        for _ in 0..T_CYCLES_PER_M_CYCLE {
+            bus.io.timer.tick(&mut bus.io.interrupts);
+            ppu.tick(bus);
+            bus.io.apu.tick();
+        }
+
+        Dma::tick(bus);
+    }
+
+    #[test]
+    fn test_scheduler_matches_ticking() {
+        let new_bus = || Bus::new(Cart::new(vec![0; 0x8000]).unwrap());
+        let mut bus = new_bus();
+        let mut reference_bus = new_bus();
+        let mut clock = Clock::with_ppu(Ppu::default());
+        let mut reference_ppu = Ppu::default();
+        // register writes at M-cycles: timer on, STAT sources, LCD off and on, APU on, DMA
+        let writes = [
+            (100, 0xFF07, 0b101),
+            (5000, 0xFF41, 0b0111_1000),
+            (5000, 0xFF45, 3),
+            (20000, 0xFF40, 0x11),
+            (30000, 0xFF40, 0x91),
+            (31000, 0xFF26, 0x80),
+            (31000, 0xFF25, 0xFF),
+            (31000, 0xFF24, 0x77),
+            (31001, 0xFF12, 0xF3),
+            (31001, 0xFF13, 0x40),
+            (31002, 0xFF14, 0x87),
+            (32000, 0xFF46, 0xC0),
+            (40000, 0xFF05, 0xF0),
+            (50000, 0xFF07, 0b110),
+        ];
+
+        for m_cycle in 0..70224 {
+            for (at, address, value) in writes {
+                if at == m_cycle {
+                    bus.write(address, value);
+                    reference_bus.write(address, value);
+                }
+            }
+
+            clock.m_cycles(1, &mut bus);
+            tick_reference(&mut reference_ppu, &mut reference_bus);
+
+            for address in [0xFF04, 0xFF05, 0xFF0F, 0xFF41, 0xFF44, 0xFF26] {
+                assert_eq!(
+                    bus.read(address),
+                    reference_bus.read(address),
+                    "{:04X} at {}",
+                    address,
+                    m_cycle
+                );
+            }
+        }
+
+        let ppu = clock.ppu.as_ref().unwrap();
+        assert_eq!(ppu.current_frame, reference_ppu.current_frame);
+        assert_eq!(ppu.line_ticks, reference_ppu.line_ticks);
+        assert_eq!(bus.io.apu.get_output(), reference_bus.io.apu.get_output());
+    }
+}
@@ -5,6 +5,7 @@ use crate::auxiliary::timer::{Timer, TIMER_DIV_ADDRESS, TIMER_TAC_ADDRESS};
 use crate::channels::wave_channel::{CH3_WAVE_RAM_END, CH3_WAVE_RAM_START};
 use crate::cpu::interrupts::Interrupts;
 use crate::ppu::lcd::{Lcd, LCD_ADDRESS_END, LCD_ADDRESS_START};
+use serde::{Deserialize, Serialize};
 
 const IO_IF_UNUSED_MASK: u8 = 0b1110_0000;
 
@@ -33,7 +34,7 @@ impl From<u16> for IoAddress {
 // unreadable so they return 1. Some exceptions are:
 // - Unknown purpose (if any) registers. Some bits of them can be read and written.
 // - The IE register (only the 5 lower bits are used, but the upper 3 can hold any value).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Io {
     pub serial: Serial,
     pub timer: Timer,
@@ -98,7 +99,7 @@ impl Io {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Serial {
     /// FF01 — SB: Serial transfer data
     sb: u8,
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 pub const JOYPAD_ADDR: u16 = 0xFF00;
 
 pub const A_RIGHT_BIT: u8 = 0x00;
@@ -8,7 +10,45 @@ pub const START_DOWN_BIT: u8 = 0x03;
 pub const SELECT_DIRECTIONS_BIT: u8 = 0x04;
 pub const SELECT_ACTIONS_BIT: u8 = 0x05;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoypadButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    Start,
+    Select,
+}
+
+impl JoypadButton {
+    pub const ALL: [JoypadButton; 8] = [
+        JoypadButton::Up,
+        JoypadButton::Down,
+        JoypadButton::Left,
+        JoypadButton::Right,
+        JoypadButton::A,
+        JoypadButton::B,
+        JoypadButton::Start,
+        JoypadButton::Select,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            JoypadButton::Up => "UP",
+            JoypadButton::Down => "DOWN",
+            JoypadButton::Left => "LEFT",
+            JoypadButton::Right => "RIGHT",
+            JoypadButton::A => "A",
+            JoypadButton::B => "B",
+            JoypadButton::Start => "START",
+            JoypadButton::Select => "SELECT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Joypad {
     pub start: bool,
     pub select: bool,
@@ -42,6 +82,25 @@ impl Joypad {
         0xCF
     }
 
+    pub fn set_button(&mut self, button: JoypadButton, is_pressed: bool) {
+        match button {
+            JoypadButton::Up => self.up = is_pressed,
+            JoypadButton::Down => self.down = is_pressed,
+            JoypadButton::Left => self.left = is_pressed,
+            JoypadButton::Right => self.right = is_pressed,
+            JoypadButton::A => self.a = is_pressed,
+            JoypadButton::B => self.b = is_pressed,
+            JoypadButton::Start => self.start = is_pressed,
+            JoypadButton::Select => self.select = is_pressed,
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        for button in JoypadButton::ALL {
+            self.set_button(button, false);
+        }
+    }
+
     pub fn set_byte(&mut self, value: u8) {
         self.directions_selected = (value >> SELECT_DIRECTIONS_BIT) & 0x01 == 0;
         self.actions_selected = (value >> SELECT_ACTIONS_BIT) & 0x01 == 0;
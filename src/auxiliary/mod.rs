@@ -2,5 +2,7 @@ pub mod clock;
 pub mod dma;
 pub mod io;
 pub mod joypad;
+pub mod profiler;
 pub mod ram;
+pub mod scheduler;
 pub mod timer;
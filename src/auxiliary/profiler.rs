@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+/// One in this many clock calls is timed, timing all of them would cost more than the work.
+pub const SAMPLE_PERIOD: usize = 61;
+
+const CALIBRATION_LAPS: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    /// The CPU and everything else done between clock calls.
+    Cpu,
+    Timer,
+    Ppu,
+    Apu,
+    Dma,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 5] = [
+        Subsystem::Cpu,
+        Subsystem::Timer,
+        Subsystem::Ppu,
+        Subsystem::Apu,
+        Subsystem::Dma,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Subsystem::Cpu => "cpu",
+            Subsystem::Timer => "timer",
+            Subsystem::Ppu => "ppu",
+            Subsystem::Apu => "apu",
+            Subsystem::Dma => "dma",
+        }
+    }
+
+    fn get_index(&self) -> usize {
+        match self {
+            Subsystem::Cpu => 0,
+            Subsystem::Timer => 1,
+            Subsystem::Ppu => 2,
+            Subsystem::Apu => 3,
+            Subsystem::Dma => 4,
+        }
+    }
+}
+
+/// Samples time spent per subsystem. A sample is a clock call followed by the CPU work up
+/// to the next one, each part is the time since the previous lap.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    durations: [Duration; Subsystem::ALL.len()],
+    calls: usize,
+    lap_start: Option<Instant>,
+    /// Cost of a lap itself, subtracted from each one so cheap subsystems aren't inflated.
+    lap_overhead: Duration,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let start = Instant::now();
+        let mut now = start;
+
+        for _ in 0..CALIBRATION_LAPS {
+            now = std::hint::black_box(Instant::now());
+        }
+
+        Self {
+            lap_overhead: (now - start) / CALIBRATION_LAPS,
+            ..Default::default()
+        }
+    }
+
+    /// Called when the clock is entered, ends the CPU part of a running sample.
+    pub fn begin(&mut self) {
+        self.lap(Subsystem::Cpu);
+        self.calls += 1;
+        self.lap_start = self.calls.is_multiple_of(SAMPLE_PERIOD).then(Instant::now);
+    }
+
+    pub fn lap(&mut self, subsystem: Subsystem) {
+        if let Some(start) = self.lap_start {
+            let now = Instant::now();
+            self.durations[subsystem.get_index()] +=
+                (now - start).saturating_sub(self.lap_overhead);
+            self.lap_start = Some(now);
+        }
+    }
+
+    pub fn get_duration(&self, subsystem: Subsystem) -> Duration {
+        self.durations[subsystem.get_index()]
+    }
+
+    /// Fraction of the sampled time spent in the subsystem.
+    pub fn get_share(&self, subsystem: Subsystem) -> f64 {
+        let total: Duration = self.durations.iter().sum();
+
+        if total.is_zero() {
+            return 0.0;
+        }
+
+        self.get_duration(subsystem).as_secs_f64() / total.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiler_samples() {
+        let mut profiler = Profiler::default();
+
+        for _ in 0..SAMPLE_PERIOD * 4 {
+            profiler.begin();
+            std::thread::sleep(Duration::from_micros(20));
+            profiler.lap(Subsystem::Ppu);
+        }
+
+        assert!(profiler.get_duration(Subsystem::Ppu) >= Duration::from_micros(80));
+        assert_eq!(profiler.get_duration(Subsystem::Timer), Duration::ZERO);
+        assert!(profiler.get_share(Subsystem::Ppu) > 0.5);
+    }
+}
@@ -1,11 +1,15 @@
+use serde::{Deserialize, Serialize};
+
 pub const W_RAM_SIZE: usize = 0x2000;
 const H_RAM_SIZE: usize = 0x80;
 const W_RAM_ADDR_START: usize = 0xC000;
 const H_RAM_ADDR_START: usize = 0xFF80;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ram {
+    #[serde(with = "crate::big_array")]
     working_ram: [u8; W_RAM_SIZE],
+    #[serde(with = "crate::big_array")]
     high_ram: [u8; H_RAM_SIZE],
 }
 
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+/// Components which need to be ticked one T-cycle at a time only around their deadlines,
+/// between them they are advanced in bulk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    /// The next TIMA increment or a pending overflow.
+    Timer,
+    /// The next PPU mode change, the PPU is ticked per dot during OAM scan and transfer.
+    Ppu,
+    /// The next frame sequencer step, the APU is ticked per T-cycle while it is on.
+    Apu,
+    /// An active OAM DMA transfer.
+    Dma,
+}
+
+impl EventType {
+    pub const ALL: [EventType; 4] = [
+        EventType::Timer,
+        EventType::Ppu,
+        EventType::Apu,
+        EventType::Dma,
+    ];
+
+    fn get_index(&self) -> usize {
+        match self {
+            EventType::Timer => 0,
+            EventType::Ppu => 1,
+            EventType::Apu => 2,
+            EventType::Dma => 3,
+        }
+    }
+}
+
+/// Absolute T-cycle deadlines of components.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scheduler {
+    deadlines: [usize; EventType::ALL.len()],
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            // due at once, so the first cycles compute the real deadlines
+            deadlines: [0; EventType::ALL.len()],
+        }
+    }
+}
+
+impl Scheduler {
+    /// The event is due after `ticks` T-cycles from `now`, `None` when it never is.
+    pub fn schedule(&mut self, event: EventType, now: usize, ticks: Option<usize>) {
+        self.deadlines[event.get_index()] = match ticks {
+            Some(ticks) => now.saturating_add(ticks),
+            None => usize::MAX,
+        };
+    }
+
+    pub fn get_deadline(&self, event: EventType) -> Option<usize> {
+        let deadline = self.deadlines[event.get_index()];
+
+        (deadline != usize::MAX).then_some(deadline)
+    }
+
+    /// Whether the event falls within the next `t_cycles` from `now`.
+    pub fn is_due(&self, event: EventType, now: usize, t_cycles: usize) -> bool {
+        self.deadlines[event.get_index()] < now.saturating_add(t_cycles)
+    }
+
+    pub fn get_next_deadline(&self) -> Option<usize> {
+        EventType::ALL
+            .iter()
+            .filter_map(|event| self.get_deadline(*event))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let mut scheduler = Scheduler::default();
+        assert!(EventType::ALL
+            .iter()
+            .all(|event| scheduler.is_due(*event, 0, 4)));
+
+        scheduler.schedule(EventType::Timer, 100, Some(10));
+        scheduler.schedule(EventType::Ppu, 100, Some(3));
+        scheduler.schedule(EventType::Apu, 100, None);
+        scheduler.schedule(EventType::Dma, 100, None);
+
+        assert!(!scheduler.is_due(EventType::Timer, 104, 4));
+        assert!(scheduler.is_due(EventType::Timer, 108, 4));
+        assert!(scheduler.is_due(EventType::Ppu, 100, 4));
+        assert!(!scheduler.is_due(EventType::Apu, usize::MAX - 4, 4));
+        assert_eq!(scheduler.get_deadline(EventType::Apu), None);
+        assert_eq!(scheduler.get_next_deadline(), Some(103));
+    }
+}
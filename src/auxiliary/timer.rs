@@ -1,5 +1,6 @@
 use crate::cpu::interrupts::{InterruptType, Interrupts};
 use crate::{get_bit_flag, get_bit_flag16};
+use serde::{Deserialize, Serialize};
 
 pub const TIMER_DIV_ADDRESS: u16 = 0xFF04;
 pub const TIMER_TIMA_ADDRESS: u16 = 0xFF05;
@@ -37,7 +38,7 @@ const TAC_ENABLE_BIT: u8 = 2;
 
 // #4 If TMA is written the same cycle it is loaded to TIMA [B], TIMA is also loaded with that value.
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FallingEdgeDetector {
     pub prev_result: bool,
 }
@@ -69,7 +70,7 @@ fn get_clock_bit_position(tac: u8) -> u8 {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Timer {
     // registers
     div: u16,
@@ -123,6 +124,36 @@ impl Timer {
         }
     }
 
+    /// Ticks which `skip` can run at once: until the tick before the next TIMA increment.
+    /// Zero while an overflow is handled, `None` while the timer is stopped.
+    pub fn get_ticks_to_event(&self) -> Option<usize> {
+        let and_result = self.get_and_result(self.div);
+
+        if self.tima_overflow_ticks.is_some()
+            || self.falling_edge_detector.prev_result != and_result
+        {
+            return Some(0);
+        }
+
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let period = 1 << (self.get_clock_bit_position() + 1);
+
+        Some(period - 1 - (self.div as usize & (period - 1)))
+    }
+
+    /// Runs ticks in which the falling edge detector does not fire, see `get_ticks_to_event`.
+    pub fn skip(&mut self, ticks: usize) {
+        self.div = self.div.wrapping_add(ticks as u16);
+        self.falling_edge_detector.prev_result = self.get_and_result(self.div);
+    }
+
+    fn get_and_result(&self, div: u16) -> bool {
+        self.is_enabled() && get_bit_flag16(div, self.get_clock_bit_position())
+    }
+
     fn inc_tima(&mut self) {
         let (tima, tima_overflow) = self.tima.overflowing_add(1);
         self.write_tima(tima);
@@ -439,4 +470,40 @@ mod tests {
 
         assert_eq!(1, timer.tima);
     }
+
+    #[test]
+    pub fn test_timer_skip() {
+        let mut ticked = Timer {
+            tac: 0b101,
+            div: 3,
+
+            ..Timer::default()
+        };
+        let mut skipped = ticked.clone();
+        let mut interrupts = Interrupts::default();
+
+        // TIMA is incremented every 16 ticks
+        assert_eq!(skipped.get_ticks_to_event(), Some(12));
+
+        for _ in 0..1000 {
+            let ticks = skipped.get_ticks_to_event().unwrap();
+
+            if ticks > 0 {
+                skipped.skip(ticks);
+            } else {
+                skipped.tick(&mut interrupts);
+            }
+
+            for _ in 0..ticks.max(1) {
+                ticked.tick(&mut interrupts);
+            }
+
+            assert_eq!(ticked.div, skipped.div);
+            assert_eq!(ticked.tima, skipped.tima);
+        }
+
+        // overflows were handled tick by tick
+        assert_ne!(interrupts.int_flags, 0);
+        assert_eq!(Timer::default().get_ticks_to_event(), None);
+    }
 }
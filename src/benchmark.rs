@@ -0,0 +1,145 @@
+use crate::auxiliary::profiler::{Profiler, Subsystem};
+use crate::emu::read_cart;
+use crate::headless::Headless;
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BENCHMARK_FRAMES: usize = 600;
+
+/// T-cycles per second of the original hardware.
+pub const DMG_CLOCK_HZ: f64 = 4_194_304.0;
+
+/// Test ROMs run when no cart is given, mixing CPU, PPU and timer heavy code. Relative to
+/// the source tree, see `get_benchmark_roms`.
+pub const BENCHMARK_ROMS: [&str; 6] = [
+    "tests/blargg/roms/cpu_instrs.gb",
+    "tests/blargg/roms/instr_timing.gb",
+    "tests/blargg/roms/mem_timing.gb",
+    "tests/mooneye/acceptance/ppu/intr_2_mode0_timing_sprites.gb",
+    "tests/mooneye/acceptance/timer/tim00.gb",
+    "tests/roms/dmg-acid2.gb",
+];
+
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    pub path: String,
+    pub frames: usize,
+    pub t_cycles: usize,
+    pub elapsed: Duration,
+    pub profiler: Profiler,
+}
+
+impl BenchmarkResult {
+    pub fn get_fps(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Emulated T-cycles per second.
+    pub fn get_cycles_per_sec(&self) -> f64 {
+        self.t_cycles as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Times faster than the original hardware.
+    pub fn get_speed(&self) -> f64 {
+        self.get_cycles_per_sec() / DMG_CLOCK_HZ
+    }
+
+    /// Elapsed time spent in the subsystem, estimated from the sampled share.
+    pub fn get_subsystem_time(&self, subsystem: Subsystem) -> Duration {
+        self.elapsed.mul_f64(self.profiler.get_share(subsystem))
+    }
+}
+
+impl fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.path)?;
+        writeln!(
+            f,
+            "  {} frames in {:.3} s: {:.1} fps, {:.2} MHz, {:.1}x speed",
+            self.frames,
+            self.elapsed.as_secs_f64(),
+            self.get_fps(),
+            self.get_cycles_per_sec() / 1_000_000.0,
+            self.get_speed(),
+        )?;
+
+        for subsystem in Subsystem::ALL {
+            writeln!(
+                f,
+                "  {:<6} {:>9.3} ms {:>5.1}%",
+                subsystem.get_name(),
+                self.get_subsystem_time(subsystem).as_secs_f64() * 1000.0,
+                self.profiler.get_share(subsystem) * 100.0,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Paths of `BENCHMARK_ROMS` in the source tree the binary was built from.
+pub fn get_benchmark_roms() -> Result<Vec<String>, String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    BENCHMARK_ROMS
+        .iter()
+        .map(|rom| {
+            let path = dir.join(rom);
+
+            if !path.is_file() {
+                return Err(format!(
+                    "Test ROM not found: {}, pass a cart to benchmark instead",
+                    path.display()
+                ));
+            }
+
+            Ok(path.to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+/// Runs the cart from power on for the frames without frame limiting.
+pub fn run_benchmark(path: &str, frames: usize) -> Result<BenchmarkResult, String> {
+    let mut headless = Headless::new(read_cart(path, &[])?);
+    headless.ctx.clock.profiler = Some(Profiler::new());
+
+    let start = Instant::now();
+    headless.run_frames(frames)?;
+    let elapsed = start.elapsed();
+
+    let clock = &mut headless.ctx.clock;
+
+    Ok(BenchmarkResult {
+        path: path.to_string(),
+        frames,
+        t_cycles: clock.t_cycles,
+        elapsed,
+        profiler: clock.profiler.take().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_benchmark() {
+        let result = run_benchmark("tests/roms/dmg-acid2.gb", 10).unwrap();
+
+        assert_eq!(result.frames, 10);
+        // the ROM turns the LCD off for a while, which delays the frames
+        assert!(result.t_cycles >= 10 * 70224);
+        assert!(result.profiler.get_share(Subsystem::Cpu) > 0.0);
+        assert!(result.profiler.get_share(Subsystem::Ppu) > 0.0);
+        assert!(result.to_string().contains("fps"));
+    }
+
+    #[test]
+    fn test_get_benchmark_roms() {
+        let paths = get_benchmark_roms().unwrap();
+
+        assert_eq!(paths.len(), BENCHMARK_ROMS.len());
+        assert!(paths.iter().all(|path| Path::new(path).is_absolute()));
+    }
+}
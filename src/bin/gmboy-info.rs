@@ -0,0 +1,95 @@
+use gmboy::archive::read_rom_file;
+use gmboy::cart::info::CartInfo;
+use gmboy::gamedb::Dat;
+use serde::Serialize;
+use std::env;
+
+const USAGE: &str = "Usage: gmboy-info [--json] [--dat FILE.dat] ROM...";
+
+/// Report of a ROM, or why it couldn't be read.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Report {
+    Info(Box<CartInfo>),
+    Error { file: String, error: String },
+}
+
+impl Report {
+    fn is_valid(&self) -> bool {
+        match self {
+            Report::Info(info) => info.is_valid(),
+            Report::Error { .. } => false,
+        }
+    }
+}
+
+/// Prints the header report of each ROM, as a JSON array with `--json`. Exits with 1 when
+/// any of them has errors or can't be read.
+fn main() {
+    let mut is_json = false;
+    let mut dat = None;
+    let mut paths = Vec::new();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => is_json = true,
+            "--dat" => {
+                let path = args.next().unwrap_or_else(|| exit_with("Missing DAT file"));
+                dat = Some(Dat::from_file(&path).unwrap_or_else(|err| exit_with(&err)));
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("--") => exit_with(&format!("Unknown option: {}", arg)),
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        exit_with("No ROM given");
+    }
+
+    let reports: Vec<Report> = paths
+        .iter()
+        .map(|path| match read_info(path, dat.as_ref()) {
+            Ok(info) => Report::Info(Box::new(info)),
+            Err(error) => Report::Error {
+                file: path.clone(),
+                error,
+            },
+        })
+        .collect();
+
+    if is_json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        for report in reports.iter() {
+            match report {
+                Report::Info(info) => print!("{}", info),
+                Report::Error { file, error } => println!("{}\n\t ERROR: {}", file, error),
+            }
+        }
+    }
+
+    if reports.iter().any(|report| !report.is_valid()) {
+        std::process::exit(1);
+    }
+}
+
+fn read_info(path: &str, dat: Option<&Dat>) -> Result<CartInfo, String> {
+    let file = read_rom_file(path)?;
+    let mut info = CartInfo::new(path, &file.bytes)?;
+
+    if let Some(dat) = dat {
+        info.identify(dat, &file.bytes);
+    }
+
+    Ok(info)
+}
+
+fn exit_with(err: &str) -> ! {
+    eprintln!("{}\n{}", err, USAGE);
+    std::process::exit(2);
+}
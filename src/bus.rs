@@ -2,9 +2,10 @@ use crate::auxiliary::dma::Dma;
 use crate::auxiliary::io::Io;
 use crate::auxiliary::ram::Ram;
 use crate::cart::Cart;
-use crate::ppu::lcd::LCD_DMA_ADDRESS;
+use crate::ppu::lcd::{PpuMode, LCD_DMA_ADDRESS};
 use crate::ppu::oam::OamRam;
 use crate::ppu::vram::{VideoRam, VRAM_ADDR_END, VRAM_ADDR_START};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum BusAddrLocation {
@@ -55,15 +56,26 @@ impl From<u16> for BusAddrLocation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bus {
+    /// Not in save states, the ROM and the mapper are kept apart.
+    #[serde(skip)]
     pub cart: Cart,
     pub ram: Ram,
     pub io: Io,
+    #[serde(skip)]
     flat_mem: Option<Vec<u8>>,
     pub dma: Dma,
     pub video_ram: VideoRam,
     pub oam_ram: OamRam,
+    /// I/O registers were written, so the deadlines of the clock have to be computed again.
+    is_io_written: bool,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new(Cart::default())
+    }
 }
 
 impl Bus {
@@ -76,6 +88,7 @@ impl Bus {
             dma: self.dma.clone(),
             video_ram: self.video_ram.clone(),
             oam_ram: self.oam_ram.clone(),
+            is_io_written: true,
         }
     }
 
@@ -88,6 +101,7 @@ impl Bus {
             dma: Default::default(),
             video_ram: Default::default(),
             oam_ram: Default::default(),
+            is_io_written: true,
         }
     }
 
@@ -110,13 +124,19 @@ impl Bus {
 
         match location {
             BusAddrLocation::Oam => {
-                if self.dma.is_transferring() {
+                if self.dma.is_transferring() || self.is_oam_blocked() {
                     return 0xFF;
                 }
 
                 self.oam_ram.read(address)
             }
-            BusAddrLocation::VRAM => self.video_ram.read(address),
+            BusAddrLocation::VRAM => {
+                if self.is_vram_blocked() {
+                    return 0xFF;
+                }
+
+                self.video_ram.read(address)
+            }
             BusAddrLocation::RomBank0 | BusAddrLocation::RomBank1 | BusAddrLocation::CartRam => {
                 self.cart.read(address)
             }
@@ -148,14 +168,18 @@ impl Bus {
         let location = BusAddrLocation::from(address);
 
         match location {
-            BusAddrLocation::VRAM => self.video_ram.write(address, value),
+            BusAddrLocation::VRAM => {
+                if !self.is_vram_write_blocked() {
+                    self.video_ram.write(address, value)
+                }
+            }
             BusAddrLocation::EchoRam => {
                 let mirrored_addr = address - ECHO_MIRROR_OFFSET; // Redirect to WRAM (0xC000 - 0xDDFF)
                 self.ram.working_ram_write(mirrored_addr, value);
             }
             BusAddrLocation::Unusable => {}
             BusAddrLocation::Oam => {
-                if self.dma.is_active {
+                if self.dma.is_active || self.is_oam_write_blocked() {
                     return;
                 }
 
@@ -167,11 +191,50 @@ impl Bus {
             BusAddrLocation::WRamBank0 | BusAddrLocation::WRamBank1To7 => {
                 self.ram.working_ram_write(address, value)
             }
-            BusAddrLocation::IoRegisters => self.io.write(address, value),
+            BusAddrLocation::IoRegisters => {
+                self.is_io_written = true;
+                self.io.write(address, value)
+            }
             BusAddrLocation::HRam => self.ram.high_ram_write(address, value),
             BusAddrLocation::IeRegister => self.io.interrupts.ie_register = value,
         }
     }
+
+    /// Returns whether I/O registers were written since the last call.
+    pub fn take_io_written(&mut self) -> bool {
+        std::mem::take(&mut self.is_io_written)
+    }
+
+    /// The PPU owns VRAM while it draws a line, until STAT reports the end of it. On the first
+    /// line after the LCD is turned on there is no mode 2 and it waits for STAT as well.
+    fn is_vram_blocked(&self) -> bool {
+        let lcd = &self.io.lcd;
+
+        lcd.stat_mode == PpuMode::Transfer
+            || lcd.status.ppu_mode() == PpuMode::Transfer && lcd.stat_mode == PpuMode::Oam
+    }
+
+    /// The PPU owns OAM while it scans and draws a line, until STAT reports the end of it.
+    fn is_oam_blocked(&self) -> bool {
+        let lcd = &self.io.lcd;
+
+        lcd.status.ppu_mode() == PpuMode::Oam
+            || matches!(lcd.stat_mode, PpuMode::Oam | PpuMode::Transfer)
+    }
+
+    /// Writes only follow the mode reported in STAT.
+    fn is_vram_write_blocked(&self) -> bool {
+        self.io.lcd.stat_mode == PpuMode::Transfer
+    }
+
+    /// Writes follow the mode reported in STAT, but OAM is writable again at the switch from
+    /// mode 2 to mode 3.
+    fn is_oam_write_blocked(&self) -> bool {
+        let lcd = &self.io.lcd;
+
+        lcd.stat_mode == PpuMode::Transfer
+            || lcd.stat_mode == PpuMode::Oam && lcd.status.ppu_mode() == PpuMode::Oam
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,123 @@
+use crate::apu::Apu;
+use crate::capture::wav::WavWriter;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Writes the APU stereo output into a WAV file and, optionally, every channel's raw DAC
+/// output into its own mono `<name>_chN.wav` next to it.
+pub struct AudioRecorder {
+    path: PathBuf,
+    stereo: WavWriter,
+    channels: Option<Vec<WavWriter>>,
+    /// Stereo samples the APU produced before the recording started.
+    skipped: usize,
+}
+
+impl AudioRecorder {
+    /// Starts recording the APU right away, see `attach`.
+    pub fn new(path: &Path, per_channel: bool, apu: &mut Apu) -> Result<Self, String> {
+        let sample_rate = apu.get_sample_rate();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let channels = if per_channel {
+            let writers = (1..=4)
+                .map(|n| WavWriter::create(&get_channel_path(path, n), 1, sample_rate))
+                .collect::<Result<Vec<_>, String>>()?;
+
+            Some(writers)
+        } else {
+            None
+        };
+
+        let mut recorder = Self {
+            path: path.to_path_buf(),
+            stereo: WavWriter::create(path, 2, sample_rate)?,
+            channels,
+            skipped: 0,
+        };
+        recorder.attach(apu);
+
+        Ok(recorder)
+    }
+
+    /// Records from the current sample of the APU, so the channels start with the stereo
+    /// output. Called again when a cart or a state load replaces the APU.
+    pub fn attach(&mut self, apu: &mut Apu) {
+        apu.set_channels_capture(self.channels.is_some());
+        apu.take_channels_output();
+        self.skipped = apu.get_output().len();
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Must be called before the output is taken by the audio device (or discarded in
+    /// headless mode), i.e. whenever `Apu::output_ready` is true.
+    pub fn capture(&mut self, apu: &mut Apu) -> Result<(), String> {
+        self.write_channels(apu)?;
+
+        if apu.output_ready() {
+            self.write_stereo(apu)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the samples produced since the last full buffer too.
+    pub fn finish(mut self, apu: &mut Apu) -> Result<(), String> {
+        self.write_channels(apu)?;
+        self.write_stereo(apu)?;
+        apu.set_channels_capture(false);
+        self.stereo.finish()?;
+
+        for writer in self.channels.into_iter().flatten() {
+            writer.finish()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_channels(&mut self, apu: &mut Apu) -> Result<(), String> {
+        if let Some(channels) = self.channels.as_mut() {
+            for samples in apu.take_channels_output() {
+                for (writer, sample) in channels.iter_mut().zip(samples) {
+                    writer.write_samples(&[sample])?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_stereo(&mut self, apu: &Apu) -> Result<(), String> {
+        let output = apu.get_output();
+        self.stereo
+            .write_samples(&output[self.skipped.min(output.len())..])?;
+        self.skipped = 0;
+
+        Ok(())
+    }
+}
+
+pub fn get_channel_path(path: &Path, channel: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    path.with_file_name(format!("{}_ch{}.wav", stem, channel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_path() {
+        assert_eq!(
+            get_channel_path(Path::new("out/music.wav"), 3),
+            PathBuf::from("out/music_ch3.wav")
+        );
+    }
+}
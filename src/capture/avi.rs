@@ -0,0 +1,230 @@
+use crate::capture::wav::into_pcm16;
+use crate::ppu::ppu::{LINES_PER_FRAME, TICKS_PER_LINE};
+use crate::ppu::tile::Pixel;
+use crate::ppu::{LCD_X_RES, LCD_Y_RES};
+use crate::CPU_CLOCK_SPEED;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const WIDTH: u32 = LCD_X_RES as u32;
+const HEIGHT: u32 = LCD_Y_RES as u32;
+const FRAME_SIZE: u32 = WIDTH * HEIGHT * 3;
+const AUDIO_CHANNELS: u16 = 2;
+const AUDIO_BLOCK_ALIGN: u16 = AUDIO_CHANNELS * 2;
+/// Flags of the main header: has an index and is interleaved.
+const AVIF_FLAGS: u32 = 0x10 | 0x100;
+const AVIIF_KEYFRAME: u32 = 0x10;
+
+// offsets of the header fields which are only known on finish
+const RIFF_SIZE_OFFSET: u64 = 4;
+const TOTAL_FRAMES_OFFSET: u64 = 48;
+const VIDEO_LENGTH_OFFSET: u64 = 140;
+const AUDIO_LENGTH_OFFSET: u64 = 264;
+const MOVI_SIZE_OFFSET: u64 = 332;
+const MOVI_START: u32 = 336;
+
+/// Streams frames as uncompressed 24-bit RGB video and 16-bit stereo PCM into an AVI
+/// file. Every frame is followed by the audio produced since the previous one, sizes and
+/// the index are written on `finish`. The RIFF container limits recordings to 4 GiB,
+/// about 17 minutes.
+pub struct AviWriter {
+    writer: BufWriter<File>,
+    index: Vec<(&'static [u8; 4], u32, u32)>,
+    audio: Vec<u8>,
+    movi_size: u32,
+    frames_count: u32,
+    audio_frames_count: u32,
+}
+
+impl AviWriter {
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut obj = Self {
+            writer: BufWriter::new(file),
+            index: Vec::new(),
+            audio: Vec::new(),
+            movi_size: 4,
+            frames_count: 0,
+            audio_frames_count: 0,
+        };
+        obj.write_header(sample_rate).map_err(|e| e.to_string())?;
+
+        Ok(obj)
+    }
+
+    /// Writes the frame and the audio buffered for it.
+    pub fn write_frame(&mut self, buffer: &[Pixel]) -> Result<(), String> {
+        let mut data = Vec::with_capacity(FRAME_SIZE as usize);
+
+        // bottom-up BGR rows
+        for line in buffer.chunks(WIDTH as usize).rev() {
+            for pixel in line {
+                let (r, g, b, _a) = pixel.color.as_rgba();
+                data.extend_from_slice(&[b, g, r]);
+            }
+        }
+
+        self.write_chunk(b"00db", &data)?;
+        self.frames_count += 1;
+
+        if !self.audio.is_empty() {
+            let audio = std::mem::take(&mut self.audio);
+            self.write_chunk(b"01wb", &audio)?;
+            self.audio_frames_count += audio.len() as u32 / AUDIO_BLOCK_ALIGN as u32;
+        }
+
+        Ok(())
+    }
+
+    /// Buffers interleaved stereo samples in the -1.0..1.0 range until the next frame.
+    pub fn write_samples(&mut self, samples: &[f32]) {
+        for sample in samples {
+            self.audio
+                .extend_from_slice(&into_pcm16(*sample).to_le_bytes());
+        }
+    }
+
+    pub fn get_frames_count(&self) -> u32 {
+        self.frames_count
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.write_index().map_err(|e| e.to_string())
+    }
+
+    fn write_chunk(&mut self, id: &'static [u8; 4], data: &[u8]) -> Result<(), String> {
+        let size = data.len() as u32;
+        let padding = size % 2;
+        let w = &mut self.writer;
+
+        w.write_all(id)
+            .and_then(|_| w.write_all(&size.to_le_bytes()))
+            .and_then(|_| w.write_all(data))
+            .and_then(|_| w.write_all(&vec![0; padding as usize]))
+            .map_err(|e| e.to_string())?;
+
+        self.index.push((id, self.movi_size, size));
+        self.movi_size = self
+            .movi_size
+            .checked_add(8 + size + padding)
+            .filter(|size| *size < u32::MAX - MOVI_START - 16 * self.index.len() as u32)
+            .ok_or("AVI file is too large")?;
+
+        Ok(())
+    }
+
+    fn write_header(&mut self, sample_rate: u32) -> std::io::Result<()> {
+        let ticks_per_frame = (TICKS_PER_LINE * LINES_PER_FRAME) as u32;
+        let audio_bytes_rate = sample_rate * AUDIO_BLOCK_ALIGN as u32;
+        let w = &mut self.writer;
+
+        w.write_all(b"RIFF")?;
+        w.write_all(&0u32.to_le_bytes())?;
+        w.write_all(b"AVI ")?;
+
+        w.write_all(b"LIST")?;
+        w.write_all(&(MOVI_SIZE_OFFSET as u32 - 4 - 20).to_le_bytes())?;
+        w.write_all(b"hdrl")?;
+        w.write_all(b"avih")?;
+        w.write_all(&56u32.to_le_bytes())?;
+        let micros_per_frame = ticks_per_frame as u64 * 1_000_000 / CPU_CLOCK_SPEED as u64;
+        write_u32s(
+            w,
+            &[
+                micros_per_frame as u32,
+                FRAME_SIZE * 60 + audio_bytes_rate,
+                0,
+                AVIF_FLAGS,
+            ],
+        )?;
+        write_u32s(w, &[0, 0, 2, FRAME_SIZE, WIDTH, HEIGHT, 0, 0, 0, 0])?; // total frames at 48
+
+        // video stream
+        w.write_all(b"LIST")?;
+        w.write_all(&(4 + 64 + 48u32).to_le_bytes())?;
+        w.write_all(b"strl")?;
+        w.write_all(b"strh")?;
+        w.write_all(&56u32.to_le_bytes())?;
+        w.write_all(b"vidsDIB ")?;
+        write_u32s(w, &[0, 0, 0, ticks_per_frame, CPU_CLOCK_SPEED, 0])?;
+        write_u32s(w, &[0, FRAME_SIZE, u32::MAX, 0])?; // length at 140
+        write_u16s(w, &[0, 0, WIDTH as u16, HEIGHT as u16])?;
+        w.write_all(b"strf")?;
+        w.write_all(&40u32.to_le_bytes())?;
+        write_u32s(w, &[40, WIDTH, HEIGHT])?;
+        write_u16s(w, &[1, 24])?;
+        write_u32s(w, &[0, FRAME_SIZE, 0, 0, 0, 0])?;
+
+        // audio stream
+        w.write_all(b"LIST")?;
+        w.write_all(&(4 + 64 + 26u32).to_le_bytes())?;
+        w.write_all(b"strl")?;
+        w.write_all(b"strh")?;
+        w.write_all(&56u32.to_le_bytes())?;
+        w.write_all(b"auds")?;
+        write_u32s(
+            w,
+            &[0, 0, 0, 0, AUDIO_BLOCK_ALIGN as u32, audio_bytes_rate, 0],
+        )?;
+        write_u32s(
+            w,
+            &[0, audio_bytes_rate, u32::MAX, AUDIO_BLOCK_ALIGN as u32],
+        )?; // length at 264
+        write_u16s(w, &[0, 0, 0, 0])?;
+        w.write_all(b"strf")?;
+        w.write_all(&18u32.to_le_bytes())?;
+        write_u16s(w, &[1, AUDIO_CHANNELS])?; // PCM
+        write_u32s(w, &[sample_rate, audio_bytes_rate])?;
+        write_u16s(w, &[AUDIO_BLOCK_ALIGN, 16, 0])?;
+
+        // JUNK to keep `movi` at a fixed offset
+        w.write_all(b"JUNK")?;
+        w.write_all(&6u32.to_le_bytes())?;
+        w.write_all(&[0; 6])?;
+
+        w.write_all(b"LIST")?;
+        w.write_all(&0u32.to_le_bytes())?;
+        w.write_all(b"movi")
+    }
+
+    fn write_index(&mut self) -> std::io::Result<()> {
+        let w = &mut self.writer;
+        w.write_all(b"idx1")?;
+        w.write_all(&(self.index.len() as u32 * 16).to_le_bytes())?;
+
+        for (id, offset, size) in self.index.iter() {
+            w.write_all(*id)?;
+            write_u32s(w, &[AVIIF_KEYFRAME, *offset, *size])?;
+        }
+
+        let riff_size = MOVI_START - 8 + self.movi_size + 8 + self.index.len() as u32 * 16;
+        let patches = [
+            (RIFF_SIZE_OFFSET, riff_size),
+            (TOTAL_FRAMES_OFFSET, self.frames_count),
+            (VIDEO_LENGTH_OFFSET, self.frames_count),
+            (AUDIO_LENGTH_OFFSET, self.audio_frames_count),
+            (MOVI_SIZE_OFFSET, self.movi_size),
+        ];
+
+        for (offset, value) in patches {
+            w.seek(SeekFrom::Start(offset))?;
+            w.write_all(&value.to_le_bytes())?;
+        }
+
+        w.flush()
+    }
+}
+
+fn write_u32s(w: &mut impl Write, values: &[u32]) -> std::io::Result<()> {
+    values
+        .iter()
+        .try_for_each(|v| w.write_all(&v.to_le_bytes()))
+}
+
+fn write_u16s(w: &mut impl Write, values: &[u16]) -> std::io::Result<()> {
+    values
+        .iter()
+        .try_for_each(|v| w.write_all(&v.to_le_bytes()))
+}
@@ -0,0 +1,201 @@
+use crate::ppu::ppu::{LINES_PER_FRAME, TICKS_PER_LINE};
+use crate::ppu::tile::{Pixel, PixelColor};
+use crate::ppu::{LCD_X_RES, LCD_Y_RES};
+use crate::CPU_CLOCK_SPEED;
+use gif::{Encoder, Frame, Repeat};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Duration of one LCD frame in GIF delay units (1/100 s).
+const FRAME_CENTISECONDS: f64 =
+    (TICKS_PER_LINE * LINES_PER_FRAME) as f64 * 100.0 / CPU_CLOCK_SPEED as f64;
+/// Browsers replace shorter delays with a slow default, so frames are merged up to it.
+const MIN_DELAY: u32 = 2;
+
+/// Streams frames into an animated GIF. Pixels are indexed into the global palette, the
+/// LCD shades given on creation, colors which appear later are appended and written as a
+/// local palette. Repeated frames extend the previous delay and frames shorter than
+/// `MIN_DELAY` are dropped, so the result plays at up to ~30 fps in real time.
+pub struct GifWriter {
+    encoder: Encoder<BufWriter<File>>,
+    palette: Vec<PixelColor>,
+    global_palette_len: usize,
+    pending: Option<Vec<u8>>,
+    frames_count: u32,
+    written_delay: u32,
+}
+
+impl GifWriter {
+    pub fn create(path: &Path, palette: &[PixelColor]) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut encoder = Encoder::new(
+            BufWriter::new(file),
+            LCD_X_RES as u16,
+            LCD_Y_RES as u16,
+            &get_rgb_palette(palette),
+        )
+        .map_err(|e| e.to_string())?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            encoder,
+            palette: palette.to_vec(),
+            global_palette_len: palette.len(),
+            pending: None,
+            frames_count: 0,
+            written_delay: 0,
+        })
+    }
+
+    pub fn write_frame(&mut self, buffer: &[Pixel]) -> Result<(), String> {
+        let indices = self.index_pixels(buffer)?;
+        let elapsed = self.get_elapsed_delay();
+        self.frames_count += 1;
+
+        let Some(pending) = self.pending.take() else {
+            self.pending = Some(indices);
+            return Ok(());
+        };
+
+        if pending == indices {
+            self.pending = Some(pending);
+        } else if elapsed - self.written_delay >= MIN_DELAY {
+            self.write_pending(pending, elapsed - self.written_delay)?;
+            self.pending = Some(indices);
+        } else {
+            self.pending = Some(indices);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_frames_count(&self) -> u32 {
+        self.frames_count
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        if let Some(pending) = self.pending.take() {
+            let delay = self.get_elapsed_delay().saturating_sub(self.written_delay);
+            self.write_pending(pending, delay.max(MIN_DELAY))?;
+        }
+
+        self.encoder.into_inner().map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    fn get_elapsed_delay(&self) -> u32 {
+        (self.frames_count as f64 * FRAME_CENTISECONDS).round() as u32
+    }
+
+    fn index_pixels(&mut self, buffer: &[Pixel]) -> Result<Vec<u8>, String> {
+        buffer
+            .iter()
+            .map(|pixel| {
+                let idx = match self.palette.iter().position(|c| *c == pixel.color) {
+                    Some(idx) => idx,
+                    None if self.palette.len() < 256 => {
+                        self.palette.push(pixel.color);
+                        self.palette.len() - 1
+                    }
+                    None => return Err("GIF palette is limited to 256 colors".to_string()),
+                };
+
+                Ok(idx as u8)
+            })
+            .collect()
+    }
+
+    fn write_pending(&mut self, indices: Vec<u8>, delay: u32) -> Result<(), String> {
+        let is_global = indices
+            .iter()
+            .all(|idx| (*idx as usize) < self.global_palette_len);
+        let mut frame =
+            Frame::from_indexed_pixels(LCD_X_RES as u16, LCD_Y_RES as u16, indices, None);
+        frame.delay = delay.min(u16::MAX as u32) as u16;
+
+        if !is_global {
+            frame.palette = Some(get_rgb_palette(&self.palette));
+        }
+
+        self.encoder
+            .write_frame(&frame)
+            .map_err(|e| e.to_string())?;
+        self.written_delay += delay;
+
+        Ok(())
+    }
+}
+
+fn get_rgb_palette(colors: &[PixelColor]) -> Vec<u8> {
+    colors
+        .iter()
+        .flat_map(|color| {
+            let (r, g, b, _a) = color.as_rgba();
+            [r, g, b]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::lcd::BLACK_WHITE_PALLET;
+    use crate::ppu::tile::ColorId;
+    use gif::DecodeOptions;
+
+    #[test]
+    fn test_gif_frames() {
+        let path = std::env::temp_dir().join("gmboy_test_video.gif");
+        let mut writer = GifWriter::create(&path, &BLACK_WHITE_PALLET[..2]).unwrap();
+        let white = vec![Pixel::new(BLACK_WHITE_PALLET[0], ColorId::Lightest); 160 * 144];
+        let mut black = white.clone();
+        black[0] = Pixel::new(BLACK_WHITE_PALLET[1], ColorId::Light);
+
+        // 6 white frames, then frames alternate faster than the minimum delay
+        for _ in 0..6 {
+            writer.write_frame(&white).unwrap();
+        }
+
+        for i in 0..6 {
+            writer
+                .write_frame(if i % 2 == 0 { &black } else { &white })
+                .unwrap();
+        }
+
+        // a color outside of the global palette
+        let mut last = white.clone();
+        last[1] = Pixel::new(BLACK_WHITE_PALLET[3], ColorId::Darkest);
+        writer.write_frame(&last).unwrap();
+
+        assert_eq!(writer.get_frames_count(), 13);
+        writer.finish().unwrap();
+
+        let mut decoder = DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        assert_eq!(decoder.global_palette().unwrap().len(), 2 * 3);
+        let mut delays = vec![];
+        let mut palettes = vec![];
+
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay as u32);
+            palettes.push(frame.palette.as_ref().map(|palette| palette.len()));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(delays.len() < 13 && delays.len() > 2, "{:?}", delays);
+        assert!(delays.iter().all(|delay| *delay >= MIN_DELAY));
+        assert_eq!(delays[0], 10);
+        // total length stays in real time, 13 frames are ~21.8 cs
+        assert_eq!(delays.iter().sum::<u32>(), 22);
+        // padded to a power of two
+        assert_eq!(palettes.last(), Some(&Some(4 * 3)));
+        assert!(palettes[..palettes.len() - 1].iter().all(|p| p.is_none()));
+    }
+}
@@ -0,0 +1,31 @@
+use crate::bus::Bus;
+use crate::config::Config;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod audio_recorder;
+pub mod avi;
+pub mod gif;
+pub mod screenshot;
+pub mod video_recorder;
+pub mod wav;
+
+/// Returns `save/<DIR>/<TITLE>_<UNIX TIME MS>.<EXTENSION>`.
+pub fn get_capture_path(bus: &Bus, dir: &str, extension: &str) -> PathBuf {
+    let title: String = bus
+        .cart
+        .data
+        .get_title()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    Config::save_dir()
+        .join(dir)
+        .join(format!("{}_{}.{}", title, timestamp, extension))
+}
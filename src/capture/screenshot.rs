@@ -0,0 +1,159 @@
+use crate::ppu::tile::Pixel;
+use crate::ppu::{LCD_X_RES, LCD_Y_RES};
+use png::{BitDepth, ColorType, Encoder};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+/// What a screenshot stores for every pixel of the frame buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ScreenshotFormat {
+    /// 160x144 RGBA in the current palette.
+    #[default]
+    Rgba,
+    /// 160x144 grayscale of the raw 2-bit color ids before any palette is applied,
+    /// id 0 is white and id 3 is black.
+    ColorIds,
+    /// RGBA upscaled by the integer factor, e.g. the window scale.
+    Scaled(u32),
+}
+
+impl ScreenshotFormat {
+    /// Parses `rgba`, `ids` or `x<N>` for a scaled image.
+    pub fn parse(value: &str) -> Result<ScreenshotFormat, String> {
+        match value {
+            "rgba" => Ok(ScreenshotFormat::Rgba),
+            "ids" => Ok(ScreenshotFormat::ColorIds),
+            _ => value
+                .strip_prefix('x')
+                .and_then(|scale| scale.parse().ok())
+                .filter(|scale| (1..=16).contains(scale))
+                .map(ScreenshotFormat::Scaled)
+                .ok_or(format!("Invalid screenshot format: {}", value)),
+        }
+    }
+}
+
+/// Writes a 160x144 frame buffer to a PNG file.
+pub fn save_screenshot(
+    buffer: &[Pixel],
+    path: &Path,
+    format: ScreenshotFormat,
+) -> Result<(), String> {
+    let (width, height) = (LCD_X_RES as usize, LCD_Y_RES as usize);
+
+    if buffer.len() != width * height {
+        return Err(format!("Invalid frame buffer size: {}", buffer.len()));
+    }
+
+    let scale = match format {
+        ScreenshotFormat::Scaled(scale) => scale.max(1) as usize,
+        _ => 1,
+    };
+
+    let (color_type, data) = match format {
+        ScreenshotFormat::ColorIds => {
+            let data = buffer
+                .iter()
+                .map(|pixel| 255 - pixel.color_id as u8 * 85)
+                .collect();
+
+            (ColorType::Grayscale, data)
+        }
+        _ => {
+            let mut data = Vec::with_capacity(buffer.len() * scale * scale * 4);
+
+            for line in buffer.chunks(width) {
+                for _ in 0..scale {
+                    for pixel in line {
+                        let (r, g, b, a) = pixel.color.as_rgba();
+
+                        for _ in 0..scale {
+                            data.extend_from_slice(&[r, g, b, a]);
+                        }
+                    }
+                }
+            }
+
+            (ColorType::Rgba, data)
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = Encoder::new(
+        BufWriter::new(file),
+        (width * scale) as u32,
+        (height * scale) as u32,
+    );
+    encoder.set_color(color_type);
+    encoder.set_depth(BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&data).map_err(|e| e.to_string())?;
+
+    writer.finish().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::lcd::BLACK_WHITE_PALLET;
+    use crate::ppu::tile::ColorId;
+    use png::Decoder;
+
+    fn decode(path: &Path) -> (png::OutputInfo, Vec<u8>) {
+        let decoder = Decoder::new(File::open(path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        fs::remove_file(path).unwrap();
+
+        (info, data)
+    }
+
+    #[test]
+    fn test_save_screenshot() {
+        let mut buffer = vec![Pixel::new(BLACK_WHITE_PALLET[0], ColorId::Lightest); 160 * 144];
+        buffer[1] = Pixel::new(BLACK_WHITE_PALLET[3], ColorId::Darkest);
+        let path = std::env::temp_dir().join("gmboy_test_screenshot.png");
+
+        save_screenshot(&buffer, &path, ScreenshotFormat::Rgba).unwrap();
+        let (info, data) = decode(&path);
+        assert_eq!((info.width, info.height), (160, 144));
+        assert_eq!(info.color_type, ColorType::Rgba);
+        let (r, g, b, a) = BLACK_WHITE_PALLET[3].as_rgba();
+        assert_eq!(&data[4..8], &[r, g, b, a]);
+
+        save_screenshot(&buffer, &path, ScreenshotFormat::ColorIds).unwrap();
+        let (info, data) = decode(&path);
+        assert_eq!(info.color_type, ColorType::Grayscale);
+        assert_eq!(&data[..2], &[255, 0]);
+
+        save_screenshot(&buffer, &path, ScreenshotFormat::Scaled(3)).unwrap();
+        let (info, data) = decode(&path);
+        assert_eq!((info.width, info.height), (480, 432));
+        // second pixel covers columns 3..6 of the first three rows
+        assert_eq!(&data[12..16], &[r, g, b, a]);
+        assert_eq!(&data[480 * 4 * 2 + 20..480 * 4 * 2 + 24], &[r, g, b, a]);
+        assert_ne!(&data[24..28], &[r, g, b, a]);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(
+            ScreenshotFormat::parse("ids"),
+            Ok(ScreenshotFormat::ColorIds)
+        );
+        assert_eq!(
+            ScreenshotFormat::parse("x4"),
+            Ok(ScreenshotFormat::Scaled(4))
+        );
+        assert!(ScreenshotFormat::parse("x0").is_err());
+        assert!(ScreenshotFormat::parse("jpeg").is_err());
+    }
+}
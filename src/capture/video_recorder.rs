@@ -0,0 +1,161 @@
+use crate::apu::Apu;
+use crate::capture::avi::AviWriter;
+use crate::capture::gif::GifWriter;
+use crate::ppu::tile::{Pixel, PixelColor};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum VideoFormat {
+    /// Uncompressed RGB video with PCM audio.
+    #[default]
+    Avi,
+    /// Frames only.
+    Gif,
+}
+
+impl VideoFormat {
+    pub fn from_path(path: &Path) -> Result<VideoFormat, String> {
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+
+        match extension.to_ascii_lowercase().as_str() {
+            "avi" => Ok(VideoFormat::Avi),
+            "gif" => Ok(VideoFormat::Gif),
+            _ => Err(format!("Unsupported video file: {}", path.display())),
+        }
+    }
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            VideoFormat::Avi => "avi",
+            VideoFormat::Gif => "gif",
+        }
+    }
+}
+
+enum VideoWriter {
+    Avi(AviWriter),
+    Gif(GifWriter),
+}
+
+/// Records every completed PPU frame and, for AVI, the APU output between frames.
+/// The format is picked by the file extension.
+pub struct VideoRecorder {
+    path: PathBuf,
+    writer: VideoWriter,
+}
+
+impl VideoRecorder {
+    /// `palette` seeds the GIF palette, usually the current LCD shades.
+    pub fn new(path: &Path, sample_rate: u32, palette: &[PixelColor]) -> Result<Self, String> {
+        let format = VideoFormat::from_path(path)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let writer = match format {
+            VideoFormat::Avi => VideoWriter::Avi(AviWriter::create(path, sample_rate)?),
+            VideoFormat::Gif => VideoWriter::Gif(GifWriter::create(path, palette)?),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer,
+        })
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Must be called once per frame, right after the PPU finished it.
+    pub fn capture_frame(&mut self, buffer: &[Pixel]) -> Result<(), String> {
+        match &mut self.writer {
+            VideoWriter::Avi(writer) => writer.write_frame(buffer),
+            VideoWriter::Gif(writer) => writer.write_frame(buffer),
+        }
+    }
+
+    /// Must be called before the output is taken by the audio device (or discarded in
+    /// headless mode), i.e. whenever `Apu::output_ready` is true.
+    pub fn capture_audio(&mut self, apu: &Apu) {
+        if let VideoWriter::Avi(writer) = &mut self.writer {
+            if apu.output_ready() {
+                writer.write_samples(apu.get_output());
+            }
+        }
+    }
+
+    pub fn get_frames_count(&self) -> u32 {
+        match &self.writer {
+            VideoWriter::Avi(writer) => writer.get_frames_count(),
+            VideoWriter::Gif(writer) => writer.get_frames_count(),
+        }
+    }
+
+    pub fn finish(self) -> Result<(), String> {
+        match self.writer {
+            VideoWriter::Avi(writer) => writer.finish(),
+            VideoWriter::Gif(writer) => writer.finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::lcd::BLACK_WHITE_PALLET;
+    use crate::ppu::tile::ColorId;
+
+    #[test]
+    fn test_record_avi() {
+        let path = std::env::temp_dir().join("gmboy_test_video.avi");
+        let mut recorder = VideoRecorder::new(&path, 48000, &BLACK_WHITE_PALLET).unwrap();
+        let mut buffer = vec![Pixel::new(BLACK_WHITE_PALLET[0], ColorId::Lightest); 160 * 144];
+        buffer[160 * 143] = Pixel::new(BLACK_WHITE_PALLET[3], ColorId::Darkest);
+
+        for _ in 0..3 {
+            if let VideoWriter::Avi(writer) = &mut recorder.writer {
+                writer.write_samples(&[0.5; 800 * 2]);
+            }
+
+            recorder.capture_frame(&buffer).unwrap();
+        }
+
+        assert_eq!(recorder.get_frames_count(), 3);
+        recorder.finish().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let read_u32 =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(read_u32(4) as usize, bytes.len() - 8);
+        assert_eq!(read_u32(48), 3); // total frames
+        assert_eq!(read_u32(140), 3); // video length
+        assert_eq!(read_u32(264), 800 * 3); // audio length in sample frames
+        assert_eq!(&bytes[336..340], b"movi");
+        assert_eq!(&bytes[340..344], b"00db");
+        assert_eq!(read_u32(344), 160 * 144 * 3);
+        // the first row is the bottom one
+        let (r, g, b, _a) = BLACK_WHITE_PALLET[3].as_rgba();
+        assert_eq!(&bytes[348..351], &[b, g, r]);
+
+        let movi_end = 332 + 4 + read_u32(332) as usize;
+        assert_eq!(&bytes[movi_end..movi_end + 4], b"idx1");
+        assert_eq!(read_u32(movi_end + 4), 6 * 16);
+        assert_eq!(bytes.len(), movi_end + 8 + 6 * 16);
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            VideoFormat::from_path(Path::new("a/clip.GIF")),
+            Ok(VideoFormat::Gif)
+        );
+        assert!(VideoFormat::from_path(Path::new("clip.mp4")).is_err());
+    }
+}
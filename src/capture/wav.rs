@@ -0,0 +1,118 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const HEADER_SIZE: u32 = 44;
+const BITS_PER_SAMPLE: u16 = 16;
+
+/// Streams 16-bit PCM samples into a WAV file. Sizes in the header are patched on `finish`.
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    channels: u16,
+    data_size: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, channels: u16, sample_rate: u32) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut obj = Self {
+            writer: BufWriter::new(file),
+            channels,
+            data_size: 0,
+        };
+        obj.write_header(sample_rate).map_err(|e| e.to_string())?;
+
+        Ok(obj)
+    }
+
+    /// Writes interleaved samples in the -1.0..1.0 range, values outside are clipped.
+    pub fn write_samples(&mut self, samples: &[f32]) -> Result<(), String> {
+        for sample in samples {
+            self.writer
+                .write_all(&into_pcm16(*sample).to_le_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+
+        self.data_size += samples.len() as u32 * (BITS_PER_SAMPLE / 8) as u32;
+
+        Ok(())
+    }
+
+    pub fn get_frames_count(&self) -> u32 {
+        self.data_size / (self.channels as u32 * (BITS_PER_SAMPLE / 8) as u32)
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.patch_sizes().map_err(|e| e.to_string())
+    }
+
+    fn write_header(&mut self, sample_rate: u32) -> std::io::Result<()> {
+        let block_align = self.channels * BITS_PER_SAMPLE / 8;
+        let w = &mut self.writer;
+
+        w.write_all(b"RIFF")?;
+        w.write_all(&(HEADER_SIZE - 8).to_le_bytes())?;
+        w.write_all(b"WAVE")?;
+        w.write_all(b"fmt ")?;
+        w.write_all(&16u32.to_le_bytes())?;
+        w.write_all(&1u16.to_le_bytes())?; // PCM
+        w.write_all(&self.channels.to_le_bytes())?;
+        w.write_all(&sample_rate.to_le_bytes())?;
+        w.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        w.write_all(&block_align.to_le_bytes())?;
+        w.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+        w.write_all(b"data")?;
+        w.write_all(&0u32.to_le_bytes())
+    }
+
+    fn patch_sizes(&mut self) -> std::io::Result<()> {
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_all(&(HEADER_SIZE - 8 + self.data_size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&self.data_size.to_le_bytes())?;
+        self.writer.flush()
+    }
+}
+
+pub fn into_pcm16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_wav_header_and_data() {
+        let path = std::env::temp_dir().join("gmboy_test_wav_header.wav");
+        let mut writer = WavWriter::create(&path, 2, 48000).unwrap();
+        writer.write_samples(&[0.0, 1.0, -1.0, 2.0]).unwrap();
+        assert_eq!(writer.get_frames_count(), 2);
+        writer.finish().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(u16::from_le_bytes(bytes[22..24].try_into().unwrap()), 2);
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 48000);
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 8);
+        assert_eq!(
+            i16::from_le_bytes(bytes[46..48].try_into().unwrap()),
+            i16::MAX
+        );
+        assert_eq!(
+            i16::from_le_bytes(bytes[48..50].try_into().unwrap()),
+            -i16::MAX
+        );
+        assert_eq!(
+            i16::from_le_bytes(bytes[50..52].try_into().unwrap()),
+            i16::MAX
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use crate::cart::camera_image::{get_tile_offset, ImageSource, StillImage, CAPTURE_ADDRESS};
+use crate::cart::mbc::{Mbc, MbcData};
+use crate::{RAM_ADDRESS_START, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use serde::{Deserialize, Serialize};
+
+pub const CAMERA_WIDTH: usize = 128;
+pub const CAMERA_HEIGHT: usize = 112;
+
+/// RAM bank value which maps the sensor registers to 0xA000 instead.
+const REGISTERS_BANK: u8 = 0x10;
+/// 0xA000 - 0xA035, mirrored every 0x80 bytes.
+const REGISTERS_COUNT: usize = 0x36;
+const DITHER_MATRIX_START: usize = 0x06;
+const EDGE_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+
+/// Game Boy Camera mapper with the M64282FP sensor. Captures take images from the source,
+/// run them through exposure, gain, edge enhancement and the dither matrix and store them
+/// as tiles in SRAM.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PocketCamera {
+    data: MbcData,
+    is_registers_mapped: bool,
+    #[serde(with = "crate::big_array")]
+    registers: [u8; REGISTERS_COUNT],
+    /// T-cycles left of the running capture.
+    capture_cycles: usize,
+    #[serde(skip)]
+    source: Box<dyn ImageSource>,
+}
+
+impl PocketCamera {
+    pub fn new(inner: MbcData) -> Self {
+        Self {
+            data: inner,
+            is_registers_mapped: false,
+            registers: [0; REGISTERS_COUNT],
+            capture_cycles: 0,
+            source: Box::new(StillImage::gray()),
+        }
+    }
+
+    pub fn set_image_source(&mut self, source: Box<dyn ImageSource>) {
+        self.source = source;
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture_cycles > 0
+    }
+
+    pub fn tick(&mut self, t_cycles: usize) {
+        if self.capture_cycles == 0 {
+            return;
+        }
+
+        self.capture_cycles = self.capture_cycles.saturating_sub(t_cycles);
+
+        if self.capture_cycles == 0 {
+            self.finish_capture();
+        }
+    }
+
+    fn get_exposure(&self) -> u16 {
+        u16::from_be_bytes([self.registers[2], self.registers[3]])
+    }
+
+    /// Readout takes longer without the N bit, exposure is in steps of 16 M-cycles.
+    fn get_capture_cycles(&self) -> usize {
+        let n_cycles = if self.registers[1] & 0x80 != 0 {
+            0
+        } else {
+            512
+        };
+
+        4 * (32446 + n_cycles + 16 * self.get_exposure() as usize)
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        let register = (address & 0x7F) as usize;
+
+        match register {
+            0 => {
+                if value & 1 != 0 && !self.is_capturing() {
+                    self.capture_cycles = self.get_capture_cycles();
+                }
+
+                self.registers[0] = (value & 0b110) | self.is_capturing() as u8;
+            }
+            1..REGISTERS_COUNT => self.registers[register] = value,
+            _ => {}
+        }
+    }
+
+    fn finish_capture(&mut self) {
+        self.registers[0] &= !1;
+        let frame = self.source.next_frame();
+        let pixels = self.process(&frame);
+
+        for (i, value) in pixels.into_iter().enumerate() {
+            let (x, y) = (i % CAMERA_WIDTH, i / CAMERA_WIDTH);
+            let offset = CAPTURE_ADDRESS + get_tile_offset(x, y);
+            let id = self.dither(x, y, value);
+            let bit = 7 - (x % 8) as u8;
+
+            if let Some(bytes) = self.data.ram_bytes.get_mut(offset..offset + 2) {
+                bytes[0] = (bytes[0] & !(1 << bit)) | ((id & 1) << bit);
+                bytes[1] = (bytes[1] & !(1 << bit)) | ((id >> 1) << bit);
+            }
+        }
+    }
+
+    /// Approximates the sensor output: brightness scaled by exposure and gain, then edge
+    /// enhanced. The voltage references of 0xA004 - 0xA005 are not modeled.
+    fn process(&self, frame: &[u8]) -> Vec<u8> {
+        // gain is about 14 dB at 0 and rises close to 1 dB a step
+        let gain = 10f32.powf(((self.registers[1] & 0x1F) as f32) / 20.0);
+        let scale = gain * self.get_exposure() as f32 / 0x1000 as f32;
+        let values: Vec<f32> = frame.iter().map(|v| *v as f32 * scale).collect();
+        let get = |x: isize, y: isize| {
+            let x = x.clamp(0, CAMERA_WIDTH as isize - 1) as usize;
+            let y = y.clamp(0, CAMERA_HEIGHT as isize - 1) as usize;
+            values[y * CAMERA_WIDTH + x]
+        };
+
+        let (horizontal, vertical) = match (self.registers[1] >> 5) & 0b11 {
+            0 => (false, false),
+            1 => (true, false),
+            2 => (false, true),
+            _ => (true, true),
+        };
+        let ratio = EDGE_RATIOS[((self.registers[4] >> 4) & 0b111) as usize];
+        let is_inverted = self.registers[4] & 0x08 != 0;
+
+        (0..CAMERA_WIDTH * CAMERA_HEIGHT)
+            .map(|i| {
+                let (x, y) = ((i % CAMERA_WIDTH) as isize, (i / CAMERA_WIDTH) as isize);
+                let value = get(x, y);
+                let mut edge = 0.0;
+
+                if horizontal {
+                    edge += 2.0 * value - get(x - 1, y) - get(x + 1, y);
+                }
+
+                if vertical {
+                    edge += 2.0 * value - get(x, y - 1) - get(x, y + 1);
+                }
+
+                let value = (value + ratio * edge).clamp(0.0, 255.0) as u8;
+
+                if is_inverted {
+                    255 - value
+                } else {
+                    value
+                }
+            })
+            .collect()
+    }
+
+    /// Color id from the 3 thresholds of the matrix cell, 3 (black) below the first.
+    fn dither(&self, x: usize, y: usize, value: u8) -> u8 {
+        let cell = DITHER_MATRIX_START + 3 * ((y % 4) * 4 + x % 4);
+        let thresholds = &self.registers[cell..cell + 3];
+
+        match thresholds.iter().position(|threshold| value < *threshold) {
+            Some(level) => 3 - level as u8,
+            None => 0,
+        }
+    }
+
+    fn get_ram_offset(&self, address: u16) -> usize {
+        let offset =
+            self.data.ram_bank as usize * RAM_BANK_SIZE + (address as usize - RAM_ADDRESS_START);
+
+        offset % self.data.ram_bytes.len()
+    }
+}
+
+impl Mbc for PocketCamera {
+    fn read_rom(&self, rom_bytes: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            0x4000..=0x7FFF => self.data.rom_bank as usize,
+            _ => return 0xFF,
+        };
+        let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+
+        rom_bytes[offset % rom_bytes.len()]
+    }
+
+    fn write_rom(&mut self, _rom_bytes: &mut Vec<u8>, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.data.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.data.rom_bank = (value & 0x3F) as u16,
+            0x4000..=0x5FFF => {
+                self.is_registers_mapped = value & REGISTERS_BANK != 0;
+
+                if !self.is_registers_mapped {
+                    self.data.ram_bank = value & 0x0F;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if self.is_registers_mapped {
+            // only the capture register can be read
+            return match address & 0x7F {
+                0 => self.registers[0],
+                _ => 0x00,
+            };
+        }
+
+        // the sensor owns the RAM while capturing
+        if self.is_capturing() || self.data.ram_bytes.is_empty() {
+            return 0x00;
+        }
+
+        self.data.ram_bytes[self.get_ram_offset(address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.is_registers_mapped {
+            self.write_register(address, value);
+            return;
+        }
+
+        if !self.data.ram_enabled || self.is_capturing() || self.data.ram_bytes.is_empty() {
+            return;
+        }
+
+        let offset = self.get_ram_offset(address);
+        self.data.ram_bytes[offset] = value;
+    }
+
+    fn load_ram(&mut self, ram_data: Vec<u8>) {
+        self.data.ram_bytes = ram_data;
+    }
+
+    fn ram_bytes(&self) -> &[u8] {
+        &self.data.ram_bytes
+    }
+
+    fn ram_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data.ram_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart::camera_image::{decode_photo, PHOTO_SIZE};
+    use crate::cart::header::RamSize;
+
+    /// Left half black, right half white.
+    fn create_camera() -> (PocketCamera, Vec<u8>) {
+        let pixels = (0..CAMERA_WIDTH * CAMERA_HEIGHT)
+            .map(|i| if i % CAMERA_WIDTH < 64 { 0 } else { 255 })
+            .collect();
+        let mut camera = PocketCamera::new(MbcData::new(RamSize::Ram128KiB));
+        camera.set_image_source(Box::new(StillImage::new(pixels).unwrap()));
+
+        (camera, vec![0; 0x8000])
+    }
+
+    #[test]
+    fn test_capture() {
+        let (mut camera, mut rom) = create_camera();
+        camera.write_rom(&mut rom, 0x0000, 0x0A);
+        camera.write_rom(&mut rom, 0x4000, REGISTERS_BANK);
+        // exposure 0x1000 with no gain leaves the image as is
+        camera.write_ram(0xA002, 0x10);
+        camera.write_ram(0xA003, 0x00);
+        camera.write_ram(0xA001, 0x80);
+
+        for cell in 0..16 {
+            let address = 0xA000 + (DITHER_MATRIX_START + cell * 3) as u16;
+            camera.write_ram(address, 0x40);
+            camera.write_ram(address + 1, 0x80);
+            camera.write_ram(address + 2, 0xC0);
+        }
+
+        camera.write_ram(0xA000, 0x01);
+        assert_eq!(camera.read_ram(0xA000) & 1, 1);
+        // mirrored every 0x80 bytes
+        assert_eq!(camera.read_ram(0xA080) & 1, 1);
+
+        let cycles = camera.get_capture_cycles();
+        assert_eq!(cycles, 4 * (32446 + 16 * 0x1000));
+        camera.tick(cycles - 4);
+        assert!(camera.is_capturing());
+        camera.write_rom(&mut rom, 0x4000, 0);
+        assert_eq!(camera.read_ram(0xA100), 0x00);
+
+        camera.tick(4);
+        assert!(!camera.is_capturing());
+        camera.write_rom(&mut rom, 0x4000, REGISTERS_BANK);
+        assert_eq!(camera.read_ram(0xA000) & 1, 0);
+
+        let ram = camera.ram_bytes();
+        let ids = decode_photo(&ram[CAPTURE_ADDRESS..CAPTURE_ADDRESS + PHOTO_SIZE]);
+        assert_eq!(ids[0], 3);
+        assert_eq!(ids[CAMERA_WIDTH - 1], 0);
+        assert_eq!(ids[CAMERA_WIDTH * CAMERA_HEIGHT - 1], 0);
+    }
+
+    #[test]
+    fn test_edge_enhancement() {
+        let (mut camera, _) = create_camera();
+        camera.registers[2] = 0x10;
+        // 2D edges, ratio 1
+        camera.registers[1] = 0x60;
+        camera.registers[4] = 0x20;
+        let frame = (0..CAMERA_WIDTH * CAMERA_HEIGHT)
+            .map(|i| if i % CAMERA_WIDTH < 64 { 0x40 } else { 0x80 })
+            .collect::<Vec<u8>>();
+
+        let pixels = camera.process(&frame);
+
+        // darker and brighter on each side of the edge
+        assert_eq!(pixels[62], 0x40);
+        assert!(pixels[63] < 0x40);
+        assert!(pixels[64] > 0x80);
+        assert_eq!(pixels[65], 0x80);
+    }
+
+    #[test]
+    fn test_ram_banking() {
+        let (mut camera, mut rom) = create_camera();
+        camera.write_rom(&mut rom, 0x0000, 0x0A);
+        camera.write_rom(&mut rom, 0x4000, 0x0F);
+        camera.write_ram(0xBFFF, 0x42);
+
+        assert_eq!(camera.ram_bytes()[128 * 1024 - 1], 0x42);
+        camera.write_rom(&mut rom, 0x4000, REGISTERS_BANK);
+        assert_eq!(camera.read_ram(0xBFFF), 0x00);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::cart::camera::{CAMERA_HEIGHT, CAMERA_WIDTH};
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Size of an image in tile data, as captured and as stored in a photo slot.
+pub const PHOTO_SIZE: usize = CAMERA_WIDTH * CAMERA_HEIGHT / 4;
+/// Offset of the last captured image in SRAM.
+pub const CAPTURE_ADDRESS: usize = 0x0100;
+pub const PHOTO_SLOTS: usize = 30;
+/// Slot order of the camera ROM, 0xFF marks an empty or deleted slot.
+const PHOTO_INDEX_ADDRESS: usize = 0x11B2;
+/// Photos are stored two per 8 KiB bank from bank 1.
+const PHOTO_SLOT_START: usize = 0x2000;
+const PHOTO_SLOT_SIZE: usize = 0x1000;
+
+/// What the camera sensor sees, 128x112 brightness values per frame, 0 is black.
+pub trait ImageSource: fmt::Debug + Send {
+    fn next_frame(&mut self) -> Vec<u8>;
+    fn box_clone(&self) -> Box<dyn ImageSource>;
+}
+
+impl Clone for Box<dyn ImageSource> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl Default for Box<dyn ImageSource> {
+    fn default() -> Self {
+        Box::new(StillImage::gray())
+    }
+}
+
+/// The same image on every capture.
+#[derive(Debug, Clone)]
+pub struct StillImage {
+    pixels: Vec<u8>,
+}
+
+impl StillImage {
+    pub fn new(pixels: Vec<u8>) -> Result<Self, String> {
+        if pixels.len() != CAMERA_WIDTH * CAMERA_HEIGHT {
+            return Err(format!("Invalid camera image size: {}", pixels.len()));
+        }
+
+        Ok(Self { pixels })
+    }
+
+    /// Flat gray, used when no image is given.
+    pub fn gray() -> Self {
+        Self {
+            pixels: vec![0x80; CAMERA_WIDTH * CAMERA_HEIGHT],
+        }
+    }
+
+    pub fn from_png(path: &Path) -> Result<Self, String> {
+        Self::new(read_png(path)?)
+    }
+}
+
+impl ImageSource for StillImage {
+    fn next_frame(&mut self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+
+    fn box_clone(&self) -> Box<dyn ImageSource> {
+        Box::new(self.clone())
+    }
+}
+
+/// Images shown one per capture, starting over after the last.
+#[derive(Debug, Clone)]
+pub struct ImageSequence {
+    frames: Vec<Vec<u8>>,
+    index: usize,
+}
+
+impl ImageSequence {
+    pub fn from_pngs(paths: &[PathBuf]) -> Result<Self, String> {
+        if paths.is_empty() {
+            return Err("No camera images".into());
+        }
+
+        let frames = paths
+            .iter()
+            .map(|path| read_png(path))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { frames, index: 0 })
+    }
+}
+
+impl ImageSource for ImageSequence {
+    fn next_frame(&mut self) -> Vec<u8> {
+        let frame = self.frames[self.index].clone();
+        self.index = (self.index + 1) % self.frames.len();
+
+        frame
+    }
+
+    fn box_clone(&self) -> Box<dyn ImageSource> {
+        Box::new(self.clone())
+    }
+}
+
+/// A still image for one PNG, a sequence for several or for a directory of them.
+pub fn create_image_source(paths: &[PathBuf]) -> Result<Box<dyn ImageSource>, String> {
+    let paths = match paths {
+        [path] if path.is_dir() => {
+            let mut paths: Vec<PathBuf> = fs::read_dir(path)
+                .map_err(|e| e.to_string())?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+                .collect();
+            paths.sort();
+            paths
+        }
+        [path] => return Ok(Box::new(StillImage::from_png(path)?)),
+        _ => paths.to_vec(),
+    };
+
+    Ok(Box::new(ImageSequence::from_pngs(&paths)?))
+}
+
+/// Reads a PNG as brightness values scaled to the sensor size.
+pub fn read_png(path: &Path) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut decoder = Decoder::new(file);
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+    let channels = info.color_type.samples();
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    let luma = |x: usize, y: usize| {
+        let pixel = &buffer[(y * width + x) * channels..];
+
+        match info.color_type {
+            ColorType::Rgb | ColorType::Rgba => {
+                ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000)
+                    as u8
+            }
+            _ => pixel[0],
+        }
+    };
+
+    let mut pixels = Vec::with_capacity(CAMERA_WIDTH * CAMERA_HEIGHT);
+
+    for y in 0..CAMERA_HEIGHT {
+        for x in 0..CAMERA_WIDTH {
+            pixels.push(luma(x * width / CAMERA_WIDTH, y * height / CAMERA_HEIGHT));
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Color ids (0 white - 3 black) of a 128x112 image stored as 16x14 tiles.
+pub fn decode_photo(tiles: &[u8]) -> Vec<u8> {
+    let mut ids = vec![0; CAMERA_WIDTH * CAMERA_HEIGHT];
+
+    for (i, id) in ids.iter_mut().enumerate() {
+        let (x, y) = (i % CAMERA_WIDTH, i / CAMERA_WIDTH);
+        let offset = get_tile_offset(x, y);
+        let bit = 7 - (x % 8);
+        let low = (tiles[offset] >> bit) & 1;
+        let high = (tiles[offset + 1] >> bit) & 1;
+        *id = (high << 1) | low;
+    }
+
+    ids
+}
+
+/// Offset of the byte pair holding the pixel in 2bpp tile data.
+pub fn get_tile_offset(x: usize, y: usize) -> usize {
+    let tile = (y / 8) * (CAMERA_WIDTH / 8) + x / 8;
+
+    tile * 16 + (y % 8) * 2
+}
+
+pub fn save_photo(ids: &[u8], path: &Path) -> Result<(), String> {
+    let data: Vec<u8> = ids.iter().map(|id| 255 - id * 85).collect();
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = Encoder::new(
+        BufWriter::new(file),
+        CAMERA_WIDTH as u32,
+        CAMERA_HEIGHT as u32,
+    );
+    encoder.set_color(ColorType::Grayscale);
+    encoder.set_depth(BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&data).map_err(|e| e.to_string())?;
+
+    writer.finish().map_err(|e| e.to_string())
+}
+
+/// Saves the photos of the used slots and the last capture as PNGs to the directory.
+pub fn extract_photos(ram: &[u8], dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if ram.len() < PHOTO_SLOT_START + PHOTO_SLOTS * PHOTO_SLOT_SIZE {
+        return Err(format!("Not a camera SRAM: {} bytes", ram.len()));
+    }
+
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let mut paths = Vec::new();
+
+    let mut save = |name: String, tiles: &[u8]| -> Result<(), String> {
+        let path = dir.join(name);
+        save_photo(&decode_photo(tiles), &path)?;
+        paths.push(path);
+
+        Ok(())
+    };
+
+    save(
+        "capture.png".into(),
+        &ram[CAPTURE_ADDRESS..CAPTURE_ADDRESS + PHOTO_SIZE],
+    )?;
+
+    for slot in 0..PHOTO_SLOTS {
+        if ram[PHOTO_INDEX_ADDRESS + slot] == 0xFF {
+            continue;
+        }
+
+        let start = PHOTO_SLOT_START + slot * PHOTO_SLOT_SIZE;
+        save(
+            format!("photo_{:02}.png", slot + 1),
+            &ram[start..start + PHOTO_SIZE],
+        )?;
+    }
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence() {
+        let dir = std::env::temp_dir().join("gmboy_camera_sequence");
+        fs::create_dir_all(&dir).unwrap();
+        let mut paths = Vec::new();
+
+        for (i, value) in [0, 255].iter().enumerate() {
+            let ids = vec![if *value == 0 { 3 } else { 0 }; CAMERA_WIDTH * CAMERA_HEIGHT];
+            let path = dir.join(format!("{}.png", i));
+            save_photo(&ids, &path).unwrap();
+            paths.push(path);
+        }
+
+        let mut source = create_image_source(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(source.next_frame()[0], 0);
+        assert_eq!(source.next_frame()[0], 255);
+        assert_eq!(source.next_frame()[0], 0);
+
+        let mut still = create_image_source(&paths[1..]).unwrap();
+        assert_eq!(still.next_frame(), still.next_frame());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_photos() {
+        let mut ram = vec![0; 128 * 1024];
+        ram[PHOTO_INDEX_ADDRESS..PHOTO_INDEX_ADDRESS + PHOTO_SLOTS].fill(0xFF);
+        ram[PHOTO_INDEX_ADDRESS + 2] = 0;
+        // top left pixel of the third slot black
+        let start = PHOTO_SLOT_START + 2 * PHOTO_SLOT_SIZE;
+        ram[start] = 0x80;
+        ram[start + 1] = 0x80;
+
+        let dir = std::env::temp_dir().join("gmboy_camera_photos");
+        let paths = extract_photos(&ram, &dir).unwrap();
+
+        assert_eq!(paths.len(), 2);
+        assert!(paths[1].ends_with("photo_03.png"));
+        let pixels = read_png(&paths[1]).unwrap();
+        assert_eq!(&pixels[..2], &[0, 255]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,8 @@
+use crate::cart::camera_image::ImageSource;
 use crate::cart::header::{CartHeader, CartType, RamSize, RomSize};
+use crate::cart::ir::IrPort;
 use crate::cart::mbc::{Mbc, MbcVariant};
+use crate::gamedb::CartOverride;
 
 pub const RAM_ADDRESS_START: usize = 0xA000;
 pub const RAM_SIZE: usize = 0x4000;
@@ -11,6 +14,17 @@ pub const MASK_MSB: u16 = 0xF000;
 pub struct Cart {
     pub data: CartData,
     pub mbc: Option<MbcVariant>,
+    /// Applied on every ROM read, used by Game Genie style cheats.
+    pub rom_patches: Vec<RomPatch>,
+}
+
+/// Replaces the byte read at `address` (0x0000 - 0x7FFF). With `compare` set, only when the
+/// original byte matches it, which is how a patch is limited to one of the switchable banks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomPatch {
+    pub address: u16,
+    pub value: u8,
+    pub compare: Option<u8>,
 }
 
 impl Cart {
@@ -20,10 +34,46 @@ impl Cart {
         Ok(Self {
             mbc: MbcVariant::new(&data),
             data,
+            rom_patches: Vec::new(),
+        })
+    }
+
+    /// Uses the cart type and RAM size of the override where it has them.
+    pub fn with_override(rom_bytes: Vec<u8>, cart_override: &CartOverride) -> Result<Cart, String> {
+        let data = CartData::new(rom_bytes);
+        let cart_type = match cart_override.cart_type {
+            Some(cart_type) => cart_type,
+            None => data.get_cart_type()?,
+        };
+        let ram_size = match cart_override.ram_size {
+            Some(ram_size) => ram_size,
+            None => data.get_ram_size()?,
+        };
+
+        Ok(Self {
+            mbc: MbcVariant::with_type(&data, cart_type, ram_size),
+            data,
+            rom_patches: Vec::new(),
         })
     }
 
     pub fn read(&self, address: u16) -> u8 {
+        let value = self.read_unpatched(address);
+
+        if self.rom_patches.is_empty() || address > 0x7FFF {
+            return value;
+        }
+
+        for patch in self.rom_patches.iter() {
+            if patch.address == address && patch.compare.is_none_or(|c| c == value) {
+                return patch.value;
+            }
+        }
+
+        value
+    }
+
+    fn read_unpatched(&self, address: u16) -> u8 {
         if let Some(mbc) = &self.mbc {
             match (address & MASK_MSB) >> 12 {
                 0x0..=0x7 => mbc.read_rom(&self.data.bytes, address),
@@ -46,6 +96,38 @@ impl Cart {
             self.data.bytes[address as usize] = value;
         }
     }
+
+    pub fn set_ir_port(&mut self, port: Box<dyn IrPort>) -> Result<(), String> {
+        match self.mbc.as_mut() {
+            Some(mbc) => mbc.set_ir_port(port),
+            None => Err("The cart has no IR port".into()),
+        }
+    }
+
+    pub fn set_image_source(&mut self, source: Box<dyn ImageSource>) -> Result<(), String> {
+        match self.mbc.as_mut() {
+            Some(mbc) => mbc.set_image_source(source),
+            None => Err("The cart has no camera".into()),
+        }
+    }
+
+    pub fn tick(&mut self, t_cycles: usize) {
+        if let Some(mbc) = self.mbc.as_mut() {
+            mbc.tick(t_cycles);
+        }
+    }
+
+    /// Raw external RAM of all banks. Empty when the cart has no mapper RAM.
+    pub fn ram_bytes(&self) -> &[u8] {
+        self.mbc.as_ref().map(|mbc| mbc.ram_bytes()).unwrap_or(&[])
+    }
+
+    pub fn ram_bytes_mut(&mut self) -> &mut [u8] {
+        match self.mbc.as_mut() {
+            Some(mbc) => mbc.ram_bytes_mut(),
+            None => &mut [],
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -74,6 +156,10 @@ impl CartData {
         CartHeader::parse_ram_size(&self.bytes)
     }
 
+    pub fn get_global_checksum(&self) -> u16 {
+        CartHeader::get_global_checksum(&self.bytes)
+    }
+
     pub fn get_rom_version(&self) -> u8 {
         CartHeader::get_rom_version(&self.bytes)
     }
@@ -85,19 +171,6 @@ impl CartData {
     }
 
     pub fn calc_checksum(&self) -> u8 {
-        let end = 0x014C;
-
-        if self.bytes.len() < end {
-            return 0;
-        }
-
-        let start = 0x0134;
-        let mut checksum: u8 = 0;
-
-        for &byte in &self.bytes[start..=end] {
-            checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
-        }
-
-        checksum
+        CartHeader::calc_header_checksum(&self.bytes)
     }
 }
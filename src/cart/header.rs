@@ -1,3 +1,13 @@
+/// Compared by the boot ROM with the one in the header, which locks up on a mismatch.
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Cart bytes past the end of the header.
+pub const HEADER_END: usize = 0x0150;
+
 #[derive(Debug, Clone)]
 pub struct CartHeader {
     /// 0x0100-0x0103: Execution start point
@@ -35,7 +45,7 @@ pub struct CartHeader {
 
 impl CartHeader {
     pub fn new(rom_bytes: &[u8]) -> Result<Self, String> {
-        if rom_bytes.len() < 0x50 {
+        if rom_bytes.len() < HEADER_END {
             return Err("Insufficient data for cart header".into());
         }
 
@@ -58,7 +68,7 @@ impl CartHeader {
             old_licensee_code: rom_bytes[0x014B].into(),
             mask_rom_version: Self::get_rom_version(rom_bytes),
             header_checksum: Self::get_header_checksum(rom_bytes),
-            global_checksum: u16::from_be_bytes(rom_bytes[0x014E..0x0150].try_into().unwrap()),
+            global_checksum: Self::get_global_checksum(rom_bytes),
         })
     }
 
@@ -86,6 +96,28 @@ impl CartHeader {
         rom_bytes[0x014D]
     }
 
+    /// Checksum of the header bytes 0x0134-0x014C, as the boot ROM computes it.
+    pub fn calc_header_checksum(rom_bytes: &[u8]) -> u8 {
+        let end = 0x014C;
+
+        if rom_bytes.len() <= end {
+            return 0;
+        }
+
+        let start = 0x0134;
+        let mut checksum: u8 = 0;
+
+        for &byte in &rom_bytes[start..=end] {
+            checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
+        }
+
+        checksum
+    }
+
+    pub fn get_global_checksum(rom_bytes: &[u8]) -> u16 {
+        u16::from_be_bytes(rom_bytes[0x014E..0x0150].try_into().unwrap())
+    }
+
     pub fn get_rom_version(rom_bytes: &[u8]) -> u8 {
         rom_bytes[0x014C]
     }
@@ -130,6 +162,17 @@ impl TryFrom<u8> for RomSize {
     }
 }
 
+impl RomSize {
+    pub fn bytes_size(&self) -> usize {
+        match self {
+            RomSize::Rom1_1MiB => 72 * 16 * 1024,
+            RomSize::Rom1_2MiB => 80 * 16 * 1024,
+            RomSize::Rom1_5MiB => 96 * 16 * 1024,
+            size => (32 * 1024) << (*size as u8),
+        }
+    }
+}
+
 impl RamSize {
     pub fn _number_of_banks(&self) -> usize {
         match self {
@@ -674,6 +717,56 @@ pub enum CartType {
     HuC1RamBattery = 0xFF,
 }
 
+impl CartType {
+    /// Whether the header RAM size should declare external RAM. MBC2 RAM is built in.
+    pub fn has_ram(&self) -> bool {
+        matches!(
+            self,
+            CartType::Mbc1Ram
+                | CartType::Mbc1RamBattery
+                | CartType::RomRam
+                | CartType::RomRamBattery
+                | CartType::Mmm01Ram
+                | CartType::Mmm01RamBattery
+                | CartType::Mbc3TimerRamBattery
+                | CartType::Mbc3Ram
+                | CartType::Mbc3RamBattery
+                | CartType::Mbc5Ram
+                | CartType::Mbc5RamBattery
+                | CartType::Mbc5RumbleRam
+                | CartType::Mbc5RumbleRamBattery
+                | CartType::PocketCamera
+                | CartType::HuC3
+                | CartType::HuC1RamBattery
+        )
+    }
+
+    /// Largest ROM the mapper can address, `None` when not known.
+    pub fn get_max_rom_size(&self) -> Option<usize> {
+        const MIB: usize = 1024 * 1024;
+
+        match self {
+            CartType::RomOnly | CartType::RomRam | CartType::RomRamBattery => Some(32 * 1024),
+            CartType::Mbc1 | CartType::Mbc1Ram | CartType::Mbc1RamBattery => Some(2 * MIB),
+            CartType::Mbc2 | CartType::Mbc2Battery => Some(256 * 1024),
+            // MBC30 doubles the MBC3 range
+            CartType::Mbc3TimerBattery
+            | CartType::Mbc3TimerRamBattery
+            | CartType::Mbc3
+            | CartType::Mbc3Ram
+            | CartType::Mbc3RamBattery => Some(4 * MIB),
+            CartType::Mbc5
+            | CartType::Mbc5Ram
+            | CartType::Mbc5RamBattery
+            | CartType::Mbc5Rumble
+            | CartType::Mbc5RumbleRam
+            | CartType::Mbc5RumbleRamBattery => Some(8 * MIB),
+            CartType::PocketCamera => Some(MIB),
+            _ => None,
+        }
+    }
+}
+
 impl TryFrom<u8> for CartType {
     type Error = String;
 
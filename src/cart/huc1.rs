@@ -0,0 +1,145 @@
+use crate::cart::ir::{read_ir_register, IrDisconnected, IrPort};
+use crate::cart::mbc::{Mbc, MbcData};
+use crate::{RAM_ADDRESS_START, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use serde::{Deserialize, Serialize};
+
+/// Written to 0x0000 - 0x1FFF to map the IR register instead of RAM.
+const IR_MODE: u8 = 0x0E;
+
+/// Hudson mapper of Pokémon Card GB. Like MBC1 without the banking modes, with an IR port.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HuC1 {
+    data: MbcData,
+    is_ir_mode: bool,
+    #[serde(skip)]
+    ir: Box<dyn IrPort>,
+}
+
+impl HuC1 {
+    pub fn new(inner: MbcData) -> Self {
+        Self {
+            data: inner,
+            is_ir_mode: false,
+            ir: Box::new(IrDisconnected),
+        }
+    }
+
+    pub fn set_ir_port(&mut self, port: Box<dyn IrPort>) {
+        self.ir = port;
+    }
+}
+
+impl Mbc for HuC1 {
+    fn read_rom(&self, rom_bytes: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            0x4000..=0x7FFF => self.data.rom_bank as usize,
+            _ => return 0xFF,
+        };
+        let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+
+        rom_bytes[offset % rom_bytes.len()]
+    }
+
+    fn write_rom(&mut self, _rom_bytes: &mut Vec<u8>, address: u16, value: u8) {
+        match address {
+            // RAM or IR select, RAM has no enable
+            0x0000..=0x1FFF => self.is_ir_mode = value & 0x0F == IR_MODE,
+            0x2000..=0x3FFF => self.data.rom_bank = (value & 0x3F).max(1) as u16,
+            0x4000..=0x5FFF => self.data.ram_bank = value & 0b11,
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if self.is_ir_mode {
+            return read_ir_register(self.ir.as_ref());
+        }
+
+        if self.data.ram_bytes.is_empty() {
+            return 0xFF;
+        }
+
+        let offset =
+            self.data.ram_bank as usize * RAM_BANK_SIZE + (address as usize - RAM_ADDRESS_START);
+
+        self.data.ram_bytes[offset % self.data.ram_bytes.len()]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.is_ir_mode {
+            self.ir.set_led(value & 1 != 0);
+            return;
+        }
+
+        if self.data.ram_bytes.is_empty() {
+            return;
+        }
+
+        let offset =
+            self.data.ram_bank as usize * RAM_BANK_SIZE + (address as usize - RAM_ADDRESS_START);
+        let len = self.data.ram_bytes.len();
+
+        self.data.ram_bytes[offset % len] = value;
+    }
+
+    fn load_ram(&mut self, ram_data: Vec<u8>) {
+        self.data.ram_bytes = ram_data;
+    }
+
+    fn ram_bytes(&self) -> &[u8] {
+        &self.data.ram_bytes
+    }
+
+    fn ram_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data.ram_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart::header::RamSize;
+    use crate::cart::ir::IrLink;
+
+    #[test]
+    fn test_banking() {
+        let mut rom: Vec<u8> = (0..64 * ROM_BANK_SIZE)
+            .map(|i| (i / ROM_BANK_SIZE) as u8)
+            .collect();
+        let mut mbc = HuC1::new(MbcData::new(RamSize::Ram32KiB));
+
+        mbc.write_rom(&mut rom, 0x2000, 0x3F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x3F);
+        mbc.write_rom(&mut rom, 0x2000, 0);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+        mbc.write_rom(&mut rom, 0x4000, 2);
+        mbc.write_ram(0xA000, 0x42);
+        mbc.write_rom(&mut rom, 0x4000, 0);
+        assert_eq!(mbc.read_ram(0xA000), 0);
+        mbc.write_rom(&mut rom, 0x4000, 2);
+        assert_eq!(mbc.read_ram(0xA000), 0x42);
+    }
+
+    #[test]
+    fn test_ir_link() {
+        let mut rom = vec![0; 0x8000];
+        let (a, b) = IrLink::pair();
+        let mut sender = HuC1::new(MbcData::new(RamSize::Ram8KiB));
+        let mut receiver = HuC1::new(MbcData::new(RamSize::Ram8KiB));
+        sender.set_ir_port(Box::new(a));
+        receiver.set_ir_port(Box::new(b));
+
+        sender.write_rom(&mut rom, 0x0000, IR_MODE);
+        receiver.write_rom(&mut rom, 0x0000, IR_MODE);
+        assert_eq!(receiver.read_ram(0xA000), 0xC0);
+
+        sender.write_ram(0xA000, 0x01);
+        assert_eq!(receiver.read_ram(0xA000), 0xC1);
+
+        // RAM is mapped back and kept apart from the IR register
+        receiver.write_rom(&mut rom, 0x0000, 0x0A);
+        assert_eq!(receiver.read_ram(0xA000), 0x00);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::cart::ir::{read_ir_register, IrDisconnected, IrPort};
+use crate::cart::mbc::{Mbc, MbcData};
+use crate::{RAM_ADDRESS_START, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+
+/// What 0xA000 - 0xBFFF maps to, written to 0x0000 - 0x1FFF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Mode {
+    RamReadOnly,
+    Ram,
+    CommandWrite,
+    CommandRead,
+    Semaphore,
+    Ir,
+    Unmapped,
+}
+
+impl From<u8> for Mode {
+    fn from(value: u8) -> Self {
+        match value & 0x0F {
+            0x0 => Mode::RamReadOnly,
+            0xA => Mode::Ram,
+            0xB => Mode::CommandWrite,
+            0xC => Mode::CommandRead,
+            0xD => Mode::Semaphore,
+            0xE => Mode::Ir,
+            _ => Mode::Unmapped,
+        }
+    }
+}
+
+/// Hudson mapper of the Robopon games. The RTC is reached through 4-bit commands working
+/// on 256 nibbles of memory, the time is latched into and set from its first 6 nibbles.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HuC3 {
+    data: MbcData,
+    mode: Mode,
+    rtc_memory: Vec<u8>,
+    access_address: u8,
+    last_command: u8,
+    result: u8,
+    /// Unix time at which the clock read day 0, minute 0.
+    rtc_base: u64,
+    #[serde(skip)]
+    ir: Box<dyn IrPort>,
+}
+
+impl HuC3 {
+    pub fn new(inner: MbcData) -> Self {
+        Self {
+            data: inner,
+            mode: Mode::RamReadOnly,
+            rtc_memory: vec![0; 256],
+            access_address: 0,
+            last_command: 0,
+            result: 0,
+            rtc_base: get_unix_time(),
+            ir: Box::new(IrDisconnected),
+        }
+    }
+
+    pub fn set_ir_port(&mut self, port: Box<dyn IrPort>) {
+        self.ir = port;
+    }
+
+    fn execute(&mut self, value: u8) {
+        let command = (value >> 4) & 0b111;
+        let arg = value & 0x0F;
+        self.last_command = command;
+
+        match command {
+            // read and increment
+            0x1 => {
+                self.result = self.rtc_memory[self.access_address as usize];
+                self.access_address = self.access_address.wrapping_add(1);
+            }
+            // write and increment
+            0x3 => {
+                self.rtc_memory[self.access_address as usize] = arg;
+                self.access_address = self.access_address.wrapping_add(1);
+            }
+            0x4 => self.access_address = (self.access_address & 0xF0) | arg,
+            0x5 => self.access_address = (self.access_address & 0x0F) | (arg << 4),
+            0x6 => match arg {
+                0x0 => self.latch_rtc(),
+                0x1 => self.set_rtc(),
+                // status, always ready
+                0x2 => self.result = 1,
+                // FIXME: the tone generator (0xE) is not emulated
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// Copies the time to nibbles 0x00-0x02 (minute of the day) and 0x03-0x05 (day).
+    fn latch_rtc(&mut self) {
+        let minutes = get_unix_time().saturating_sub(self.rtc_base) / 60;
+        let minute = minutes % MINUTES_PER_DAY;
+        let day = (minutes / MINUTES_PER_DAY) & 0xFFF;
+
+        for i in 0..3 {
+            self.rtc_memory[i] = ((minute >> (i * 4)) & 0xF) as u8;
+            self.rtc_memory[i + 3] = ((day >> (i * 4)) & 0xF) as u8;
+        }
+    }
+
+    fn set_rtc(&mut self) {
+        let mut minute = 0;
+        let mut day = 0;
+
+        for i in 0..3 {
+            minute |= (self.rtc_memory[i] as u64) << (i * 4);
+            day |= (self.rtc_memory[i + 3] as u64) << (i * 4);
+        }
+
+        let elapsed = (day * MINUTES_PER_DAY + minute) * 60;
+        self.rtc_base = get_unix_time().saturating_sub(elapsed);
+    }
+
+    fn get_ram_offset(&self, address: u16) -> usize {
+        let offset =
+            self.data.ram_bank as usize * RAM_BANK_SIZE + (address as usize - RAM_ADDRESS_START);
+
+        offset % self.data.ram_bytes.len()
+    }
+}
+
+fn get_unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl Mbc for HuC3 {
+    fn read_rom(&self, rom_bytes: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            0x4000..=0x7FFF => self.data.rom_bank as usize,
+            _ => return 0xFF,
+        };
+        let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+
+        rom_bytes[offset % rom_bytes.len()]
+    }
+
+    fn write_rom(&mut self, _rom_bytes: &mut Vec<u8>, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.mode = value.into(),
+            0x2000..=0x3FFF => self.data.rom_bank = (value & 0x7F) as u16,
+            0x4000..=0x5FFF => self.data.ram_bank = value & 0b11,
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.mode {
+            Mode::RamReadOnly | Mode::Ram if !self.data.ram_bytes.is_empty() => {
+                self.data.ram_bytes[self.get_ram_offset(address)]
+            }
+            // the command is echoed above its 4-bit result
+            Mode::CommandRead => (self.last_command << 4) | self.result,
+            // commands run at once, so the RTC is always ready
+            Mode::Semaphore => 0x01,
+            Mode::Ir => read_ir_register(self.ir.as_ref()),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        match self.mode {
+            Mode::Ram if !self.data.ram_bytes.is_empty() => {
+                let offset = self.get_ram_offset(address);
+                self.data.ram_bytes[offset] = value;
+            }
+            Mode::CommandWrite => self.execute(value),
+            Mode::Ir => self.ir.set_led(value & 1 != 0),
+            _ => {}
+        }
+    }
+
+    fn load_ram(&mut self, ram_data: Vec<u8>) {
+        self.data.ram_bytes = ram_data;
+    }
+
+    fn ram_bytes(&self) -> &[u8] {
+        &self.data.ram_bytes
+    }
+
+    fn ram_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data.ram_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart::header::RamSize;
+    use crate::cart::ir::IrLoopback;
+
+    fn command(mbc: &mut HuC3, rom: &mut Vec<u8>, command: u8, arg: u8) -> u8 {
+        mbc.write_rom(rom, 0x0000, 0x0B);
+        mbc.write_ram(0xA000, (command << 4) | arg);
+        mbc.write_rom(rom, 0x0000, 0x0C);
+
+        mbc.read_ram(0xA000) & 0x0F
+    }
+
+    #[test]
+    fn test_ram_modes() {
+        let mut rom = vec![0; 0x8000];
+        let mut mbc = HuC3::new(MbcData::new(RamSize::Ram32KiB));
+
+        mbc.write_rom(&mut rom, 0x0000, 0x0A);
+        mbc.write_ram(0xA000, 0x42);
+        mbc.write_rom(&mut rom, 0x0000, 0x00);
+        mbc.write_ram(0xA000, 0x24);
+        assert_eq!(mbc.read_ram(0xA000), 0x42);
+
+        mbc.write_rom(&mut rom, 0x0000, 0x0D);
+        assert_eq!(mbc.read_ram(0xA000), 0x01);
+    }
+
+    #[test]
+    fn test_rtc() {
+        let mut rom = vec![0; 0x8000];
+        let mut mbc = HuC3::new(MbcData::new(RamSize::Ram32KiB));
+
+        // write minute 0x123 and day 0x045 from address 0, then set the clock
+        command(&mut mbc, &mut rom, 0x4, 0);
+        command(&mut mbc, &mut rom, 0x5, 0);
+        for nibble in [0x3, 0x2, 0x1, 0x5, 0x4, 0x0] {
+            command(&mut mbc, &mut rom, 0x3, nibble);
+        }
+        command(&mut mbc, &mut rom, 0x6, 0x1);
+
+        // clear, then latch it back
+        command(&mut mbc, &mut rom, 0x4, 0);
+        for _ in 0..6 {
+            command(&mut mbc, &mut rom, 0x3, 0);
+        }
+        command(&mut mbc, &mut rom, 0x6, 0x0);
+
+        command(&mut mbc, &mut rom, 0x4, 0);
+        let nibbles: Vec<u8> = (0..6)
+            .map(|_| command(&mut mbc, &mut rom, 0x1, 0))
+            .collect();
+        assert_eq!(nibbles, [0x3, 0x2, 0x1, 0x5, 0x4, 0x0]);
+        assert_eq!(mbc.read_ram(0xA000) >> 4, 0x1);
+        assert_eq!(command(&mut mbc, &mut rom, 0x6, 0x2), 1);
+    }
+
+    #[test]
+    fn test_ir_loopback() {
+        let mut rom = vec![0; 0x8000];
+        let mut mbc = HuC3::new(MbcData::new(RamSize::NoRam));
+        mbc.set_ir_port(Box::new(IrLoopback::default()));
+
+        mbc.write_rom(&mut rom, 0x0000, 0x0E);
+        assert_eq!(mbc.read_ram(0xA000), 0xC0);
+        mbc.write_ram(0xA000, 0x01);
+        assert_eq!(mbc.read_ram(0xA000), 0xC1);
+    }
+}
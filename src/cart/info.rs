@@ -0,0 +1,487 @@
+use crate::cart::header::{
+    CartHeader, CartType, DestinationCode, NewLicenseeCode, OldLicenseeCode, RamSize, RomSize,
+    HEADER_END, NINTENDO_LOGO,
+};
+use crate::cart::CartData;
+use crate::gamedb::{find_override, Dat, DatMatch};
+use serde::Serialize;
+use std::fmt;
+
+/// Old licensee code telling that the new one at 0x0144-0x0145 is used instead.
+const USE_NEW_LICENSEE: u8 = 0x33;
+const SGB_SUPPORTED: u8 = 0x03;
+
+/// Every header field of a ROM with the results of its consistency checks.
+#[derive(Debug, Clone, Serialize)]
+pub struct CartInfo {
+    pub file: String,
+    pub file_size: usize,
+    pub title: String,
+    pub manufacturer_code: Option<String>,
+    pub cgb_flag: u8,
+    pub cgb_support: String,
+    pub sgb_flag: u8,
+    pub is_sgb_supported: bool,
+    pub old_licensee_code: u8,
+    /// The new two character code when the old one is 0x33.
+    pub licensee_code: String,
+    pub licensee: String,
+    pub cart_type_code: u8,
+    pub cart_type: Option<String>,
+    pub rom_size_code: u8,
+    pub rom_size: Option<usize>,
+    pub ram_size_code: u8,
+    pub ram_size: Option<usize>,
+    pub destination_code: u8,
+    pub destination: Option<String>,
+    pub rom_version: u8,
+    pub is_logo_valid: bool,
+    pub header_checksum: u8,
+    pub calculated_header_checksum: u8,
+    pub global_checksum: u16,
+    pub calculated_global_checksum: u16,
+    /// Set by `identify`.
+    pub dat_match: Option<DatMatch>,
+    /// Header values replaced by the built-in override table.
+    pub cart_override: Option<String>,
+    /// Problems that keep the ROM from booting or running correctly.
+    pub errors: Vec<String>,
+    /// Oddities real hardware doesn't care about, like the global checksum.
+    pub warnings: Vec<String>,
+}
+
+impl CartInfo {
+    pub fn new(file: &str, rom_bytes: &[u8]) -> Result<Self, String> {
+        if rom_bytes.len() < HEADER_END {
+            return Err(format!(
+                "File is too small for a cart header: {} bytes",
+                rom_bytes.len()
+            ));
+        }
+
+        let cgb_flag = rom_bytes[0x0143];
+        let (licensee_code, licensee) = get_licensee(rom_bytes);
+        let data = CartData::new(rom_bytes.to_vec());
+
+        let mut info = Self {
+            file: file.to_string(),
+            file_size: rom_bytes.len(),
+            title: get_title(rom_bytes),
+            manufacturer_code: get_manufacturer_code(rom_bytes),
+            cgb_flag,
+            cgb_support: get_cgb_support(cgb_flag).to_string(),
+            sgb_flag: rom_bytes[0x0146],
+            is_sgb_supported: rom_bytes[0x0146] == SGB_SUPPORTED,
+            old_licensee_code: rom_bytes[0x014B],
+            licensee_code,
+            licensee,
+            cart_type_code: rom_bytes[0x0147],
+            cart_type: None,
+            rom_size_code: rom_bytes[0x0148],
+            rom_size: None,
+            ram_size_code: rom_bytes[0x0149],
+            ram_size: None,
+            destination_code: rom_bytes[0x014A],
+            destination: DestinationCode::try_from(rom_bytes[0x014A])
+                .ok()
+                .map(|code| format!("{:?}", code)),
+            rom_version: CartHeader::get_rom_version(rom_bytes),
+            is_logo_valid: rom_bytes[0x0104..0x0134] == NINTENDO_LOGO,
+            header_checksum: CartHeader::get_header_checksum(rom_bytes),
+            calculated_header_checksum: data.calc_checksum(),
+            global_checksum: CartHeader::get_global_checksum(rom_bytes),
+            calculated_global_checksum: calc_global_checksum(rom_bytes),
+            dat_match: None,
+            cart_override: find_override(rom_bytes).map(|o| o.get_description()),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        let cart_type = data.get_cart_type().ok();
+        let rom_size = data.get_rom_size().ok();
+        let ram_size = data.get_ram_size().ok();
+        info.cart_type = cart_type.map(|t| format!("{:?}", t));
+        info.rom_size = rom_size.map(|size| size.bytes_size());
+        info.ram_size = ram_size.map(|size| size.bytes_size());
+        info.check(cart_type, rom_size, ram_size);
+
+        Ok(info)
+    }
+
+    /// Looks the ROM up in the DAT, a bad dump is an error and an overdump a warning.
+    pub fn identify(&mut self, dat: &Dat, rom_bytes: &[u8]) {
+        let Some(dat_match) = dat.identify(rom_bytes) else {
+            self.warnings.push(format!("Not found in {}", dat.name));
+            return;
+        };
+
+        if dat_match.is_bad_dump() {
+            self.errors.push(format!("Bad dump of {}", dat_match.game));
+        }
+
+        if dat_match.is_overdump() {
+            self.warnings.push(format!(
+                "Overdump of {}: {} bytes, {} in the DAT",
+                dat_match.game, self.file_size, dat_match.size
+            ));
+        }
+
+        self.dat_match = Some(dat_match);
+    }
+
+    /// The boot ROM accepts it and the header describes a cart that can be emulated.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn check(
+        &mut self,
+        cart_type: Option<CartType>,
+        rom_size: Option<RomSize>,
+        ram_size: Option<RamSize>,
+    ) {
+        if !self.is_logo_valid {
+            self.errors.push("Nintendo logo mismatch".into());
+        }
+
+        if self.header_checksum != self.calculated_header_checksum {
+            self.errors.push(format!(
+                "Header checksum mismatch: {:02X}, expected {:02X}",
+                self.header_checksum, self.calculated_header_checksum
+            ));
+        }
+
+        if self.global_checksum != self.calculated_global_checksum {
+            self.warnings.push(format!(
+                "Global checksum mismatch: {:04X}, expected {:04X}",
+                self.global_checksum, self.calculated_global_checksum
+            ));
+        }
+
+        if self.destination.is_none() {
+            self.warnings.push(format!(
+                "Unknown destination code: {:02X}",
+                self.destination_code
+            ));
+        }
+
+        if self.is_sgb_supported && self.old_licensee_code != USE_NEW_LICENSEE {
+            self.warnings.push(format!(
+                "SGB flag is ignored with old licensee code {:02X}",
+                self.old_licensee_code
+            ));
+        }
+
+        match rom_size {
+            Some(size) if self.file_size < size.bytes_size() => self.errors.push(format!(
+                "File is smaller than the ROM size: {} of {} bytes",
+                self.file_size,
+                size.bytes_size()
+            )),
+            Some(size) if self.file_size > size.bytes_size() => self.warnings.push(format!(
+                "File is larger than the ROM size: {} of {} bytes",
+                self.file_size,
+                size.bytes_size()
+            )),
+            Some(_) => {}
+            None => self
+                .errors
+                .push(format!("Unknown ROM size code: {:02X}", self.rom_size_code)),
+        }
+
+        match ram_size {
+            Some(RamSize::Unused) => self.warnings.push("Unused RAM size code: 01".into()),
+            Some(_) => {}
+            None => self
+                .errors
+                .push(format!("Unknown RAM size code: {:02X}", self.ram_size_code)),
+        }
+
+        let Some(cart_type) = cart_type else {
+            self.errors
+                .push(format!("Unknown cart type: {:02X}", self.cart_type_code));
+            return;
+        };
+
+        if let (Some(ram_size), Some(cart_type_name)) = (ram_size, &self.cart_type) {
+            let declares_ram = ram_size.bytes_size() > 0;
+
+            if cart_type.has_ram() && !declares_ram {
+                self.warnings
+                    .push(format!("{} cart without RAM size", cart_type_name));
+            } else if !cart_type.has_ram() && declares_ram {
+                self.warnings.push(format!(
+                    "{} cart declares {} bytes of RAM",
+                    cart_type_name,
+                    ram_size.bytes_size()
+                ));
+            }
+        }
+
+        if let (Some(max), Some(rom_size)) = (cart_type.get_max_rom_size(), rom_size) {
+            if rom_size.bytes_size() > max {
+                self.warnings.push(format!(
+                    "{:?} cart can't address {} bytes of ROM",
+                    cart_type,
+                    rom_size.bytes_size()
+                ));
+            }
+        }
+    }
+}
+
+impl fmt::Display for CartInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ok = |is_ok: bool| if is_ok { "OK" } else { "BAD" };
+        let or_unknown = |value: &Option<String>| value.clone().unwrap_or("Unknown".into());
+        let size = |size: Option<usize>| size.map(|s| format!("{} KiB", s / 1024));
+
+        writeln!(f, "{}", self.file)?;
+        writeln!(f, "\t File Size       : {} bytes", self.file_size)?;
+        writeln!(f, "\t Title           : {}", self.title)?;
+        writeln!(
+            f,
+            "\t Manufacturer    : {}",
+            self.manufacturer_code.as_deref().unwrap_or("-")
+        )?;
+        writeln!(
+            f,
+            "\t Licensee        : {} ({})",
+            self.licensee, self.licensee_code
+        )?;
+        writeln!(
+            f,
+            "\t CGB Flag        : {:02X} ({})",
+            self.cgb_flag, self.cgb_support
+        )?;
+        writeln!(
+            f,
+            "\t SGB Flag        : {:02X} ({})",
+            self.sgb_flag,
+            if self.is_sgb_supported {
+                "supported"
+            } else {
+                "not supported"
+            }
+        )?;
+        writeln!(
+            f,
+            "\t Type            : {:02X} ({})",
+            self.cart_type_code,
+            or_unknown(&self.cart_type)
+        )?;
+        writeln!(
+            f,
+            "\t ROM Size        : {:02X} ({})",
+            self.rom_size_code,
+            or_unknown(&size(self.rom_size))
+        )?;
+        writeln!(
+            f,
+            "\t RAM Size        : {:02X} ({})",
+            self.ram_size_code,
+            or_unknown(&size(self.ram_size))
+        )?;
+        writeln!(
+            f,
+            "\t Destination     : {:02X} ({})",
+            self.destination_code,
+            or_unknown(&self.destination)
+        )?;
+        writeln!(f, "\t ROM Version     : {:02X}", self.rom_version)?;
+        writeln!(f, "\t Nintendo Logo   : {}", ok(self.is_logo_valid))?;
+        writeln!(
+            f,
+            "\t Header Checksum : {:02X} {}",
+            self.header_checksum,
+            ok(self.header_checksum == self.calculated_header_checksum)
+        )?;
+        writeln!(
+            f,
+            "\t Global Checksum : {:04X} {}",
+            self.global_checksum,
+            ok(self.global_checksum == self.calculated_global_checksum)
+        )?;
+
+        if let Some(dat_match) = &self.dat_match {
+            writeln!(
+                f,
+                "\t DAT Name        : {} ({:?})",
+                dat_match.game, dat_match.status
+            )?;
+        }
+
+        if let Some(cart_override) = &self.cart_override {
+            writeln!(f, "\t Override        : {}", cart_override)?;
+        }
+
+        for error in self.errors.iter() {
+            writeln!(f, "\t ERROR: {}", error)?;
+        }
+
+        for warning in self.warnings.iter() {
+            writeln!(f, "\t WARNING: {}", warning)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Sum of every byte except the checksum itself.
+pub fn calc_global_checksum(rom_bytes: &[u8]) -> u16 {
+    rom_bytes
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != 0x014E && *i != 0x014F)
+        .fold(0u16, |sum, (_, &byte)| sum.wrapping_add(byte as u16))
+}
+
+fn has_cgb_flag(cgb_flag: u8) -> bool {
+    cgb_flag & 0x80 != 0
+}
+
+fn get_cgb_support(cgb_flag: u8) -> &'static str {
+    match cgb_flag {
+        0x80 => "CGB enhanced",
+        0xC0 => "CGB only",
+        flag if has_cgb_flag(flag) => "CGB, unusual flag",
+        _ => "DMG",
+    }
+}
+
+/// CGB carts give up the last title bytes to the manufacturer code and the CGB flag.
+fn get_title(rom_bytes: &[u8]) -> String {
+    let end = if get_manufacturer_code(rom_bytes).is_some() {
+        0x013F
+    } else if has_cgb_flag(rom_bytes[0x0143]) {
+        0x0143
+    } else {
+        0x0144
+    };
+    let title = String::from_utf8_lossy(&rom_bytes[0x0134..end]);
+
+    title.trim_end_matches('\0').trim_end().to_string()
+}
+
+fn get_manufacturer_code(rom_bytes: &[u8]) -> Option<String> {
+    let code = &rom_bytes[0x013F..0x0143];
+    let is_code = code
+        .iter()
+        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit());
+
+    (has_cgb_flag(rom_bytes[0x0143]) && is_code).then(|| String::from_utf8_lossy(code).to_string())
+}
+
+fn get_licensee(rom_bytes: &[u8]) -> (String, String) {
+    let old = rom_bytes[0x014B];
+
+    if old == USE_NEW_LICENSEE {
+        let code = &rom_bytes[0x0144..0x0146];
+
+        return (
+            String::from_utf8_lossy(code).to_string(),
+            format!("{:?}", NewLicenseeCode::from(code)),
+        );
+    }
+
+    (
+        format!("{:02X}", old),
+        format!("{:?}", OldLicenseeCode::from(old)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_rom(cart_type: u8, rom_size: u8, ram_size: u8, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; len];
+        bytes[0x0104..0x0134].copy_from_slice(&NINTENDO_LOGO);
+        bytes[0x0134..0x0138].copy_from_slice(b"TEST");
+        bytes[0x0147] = cart_type;
+        bytes[0x0148] = rom_size;
+        bytes[0x0149] = ram_size;
+        bytes[0x014B] = USE_NEW_LICENSEE;
+        bytes[0x0144..0x0146].copy_from_slice(b"01");
+        bytes[0x014D] = CartData::new(bytes.clone()).calc_checksum();
+        let global = calc_global_checksum(&bytes).to_be_bytes();
+        bytes[0x014E..0x0150].copy_from_slice(&global);
+
+        bytes
+    }
+
+    #[test]
+    fn test_valid_rom() {
+        let info = CartInfo::new("test.gb", &create_rom(0x03, 0x01, 0x02, 0x10000)).unwrap();
+
+        assert!(info.is_valid(), "{:?}", info.errors);
+        assert!(info.warnings.is_empty(), "{:?}", info.warnings);
+        assert_eq!(info.title, "TEST");
+        assert_eq!(info.licensee_code, "01");
+        assert_eq!(info.rom_size, Some(0x10000));
+        assert_eq!(info.ram_size, Some(8 * 1024));
+        assert_eq!(info.cart_type.as_deref(), Some("Mbc1RamBattery"));
+    }
+
+    #[test]
+    fn test_invalid_rom() {
+        let mut bytes = create_rom(0x00, 0x01, 0x00, 0x8000);
+        bytes[0x0104] = 0;
+        bytes[0x0134] = b'X';
+
+        let info = CartInfo::new("test.gb", &bytes).unwrap();
+
+        assert!(!info.is_valid());
+        assert!(!info.is_logo_valid);
+        assert_ne!(info.header_checksum, info.calculated_header_checksum);
+        assert_ne!(info.global_checksum, info.calculated_global_checksum);
+        assert!(info.errors.iter().any(|e| e.contains("smaller")));
+        assert!(info.warnings.iter().any(|e| e.contains("can't address")));
+    }
+
+    #[test]
+    fn test_inconsistent_cart_type() {
+        let info = CartInfo::new("test.gb", &create_rom(0x01, 0x00, 0x03, 0x8000)).unwrap();
+        assert!(info.is_valid());
+        assert!(info.warnings.iter().any(|e| e.contains("declares")));
+
+        let info = CartInfo::new("test.gb", &create_rom(0x13, 0x00, 0x00, 0x8000)).unwrap();
+        assert!(info.warnings.iter().any(|e| e.contains("without RAM")));
+
+        let info = CartInfo::new("test.gb", &create_rom(0x42, 0x00, 0x00, 0x8000)).unwrap();
+        assert!(!info.is_valid());
+    }
+
+    #[test]
+    fn test_identify() {
+        let rom = create_rom(0x00, 0x00, 0x00, 0x8000);
+        let xml = format!(
+            r#"<datafile><header><name>GB</name></header><game name="Test (World)">
+            <rom name="Test (World).gb" size="32768" crc="{:08X}"/></game></datafile>"#,
+            crc32fast::hash(&rom)
+        );
+        let dat = Dat::parse(&xml).unwrap();
+
+        let mut info = CartInfo::new("test.gb", &rom).unwrap();
+        info.identify(&dat, &rom);
+        assert_eq!(info.dat_match.as_ref().unwrap().game, "Test (World)");
+        assert!(info.warnings.is_empty(), "{:?}", info.warnings);
+
+        let mut overdump = rom.clone();
+        overdump.extend_from_slice(&rom);
+        let mut info = CartInfo::new("test.gb", &overdump).unwrap();
+        info.identify(&dat, &overdump);
+        assert!(info.warnings.iter().any(|w| w.starts_with("Overdump")));
+        assert!(info.to_string().contains("Test (World)"));
+    }
+
+    #[test]
+    fn test_json() {
+        let info = CartInfo::new("test.gb", &create_rom(0x00, 0x00, 0x00, 0x8000)).unwrap();
+        let json: serde_json::Value = serde_json::to_value(&info).unwrap();
+
+        assert_eq!(json["title"], "TEST");
+        assert_eq!(json["is_logo_valid"], true);
+        assert_eq!(json["errors"].as_array().unwrap().len(), 0);
+    }
+}
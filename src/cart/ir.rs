@@ -0,0 +1,128 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// IR LED and receiver of a cart, HuC1 and HuC3 have one.
+pub trait IrPort: fmt::Debug + Send {
+    fn set_led(&mut self, is_on: bool);
+    /// Whether light from the other side reaches the receiver.
+    fn is_receiving(&self) -> bool;
+    fn box_clone(&self) -> Box<dyn IrPort>;
+}
+
+impl Clone for Box<dyn IrPort> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl Default for Box<dyn IrPort> {
+    fn default() -> Self {
+        Box::new(IrDisconnected)
+    }
+}
+
+/// Nothing in front of the port, no light is ever received.
+#[derive(Debug, Clone, Default)]
+pub struct IrDisconnected;
+
+impl IrPort for IrDisconnected {
+    fn set_led(&mut self, _is_on: bool) {}
+
+    fn is_receiving(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn IrPort> {
+        Box::new(self.clone())
+    }
+}
+
+/// The LED reflected back into the receiver, like a mirror in front of the port.
+#[derive(Debug, Clone, Default)]
+pub struct IrLoopback {
+    is_led_on: bool,
+}
+
+impl IrPort for IrLoopback {
+    fn set_led(&mut self, is_on: bool) {
+        self.is_led_on = is_on;
+    }
+
+    fn is_receiving(&self) -> bool {
+        self.is_led_on
+    }
+
+    fn box_clone(&self) -> Box<dyn IrPort> {
+        Box::new(self.clone())
+    }
+}
+
+/// One end of two ports facing each other, the emulators may run on different threads.
+#[derive(Debug, Clone)]
+pub struct IrLink {
+    led: Arc<AtomicBool>,
+    other_led: Arc<AtomicBool>,
+}
+
+impl IrLink {
+    pub fn pair() -> (IrLink, IrLink) {
+        let a = Arc::new(AtomicBool::new(false));
+        let b = Arc::new(AtomicBool::new(false));
+
+        (
+            IrLink {
+                led: a.clone(),
+                other_led: b.clone(),
+            },
+            IrLink {
+                led: b,
+                other_led: a,
+            },
+        )
+    }
+}
+
+impl IrPort for IrLink {
+    fn set_led(&mut self, is_on: bool) {
+        self.led.store(is_on, Ordering::Relaxed);
+    }
+
+    fn is_receiving(&self) -> bool {
+        self.other_led.load(Ordering::Relaxed)
+    }
+
+    fn box_clone(&self) -> Box<dyn IrPort> {
+        Box::new(self.clone())
+    }
+}
+
+/// Value read from the IR register of HuC carts.
+pub fn read_ir_register(port: &dyn IrPort) -> u8 {
+    0xC0 | port.is_receiving() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link() {
+        let (mut a, b) = IrLink::pair();
+        assert!(!b.is_receiving());
+
+        a.set_led(true);
+        assert!(b.is_receiving());
+        assert!(!a.is_receiving());
+        assert_eq!(read_ir_register(&b), 0xC1);
+    }
+
+    #[test]
+    fn test_loopback() {
+        let mut port: Box<dyn IrPort> = Box::new(IrLoopback::default());
+        port.set_led(true);
+
+        assert!(port.is_receiving());
+        assert!(!IrDisconnected.is_receiving());
+    }
+}
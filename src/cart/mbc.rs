@@ -1,6 +1,13 @@
-use crate::cart::header::{CartType, RamSize};
-use crate::cart::mbc1::Mbc1;
+use crate::cart::camera::PocketCamera;
+use crate::cart::camera_image::ImageSource;
+use crate::cart::header::{CartHeader, CartType, RamSize};
+use crate::cart::huc1::HuC1;
+use crate::cart::huc3::HuC3;
+use crate::cart::ir::IrPort;
+use crate::cart::mbc1::{self, Mbc1};
+use crate::cart::mmm01::{self, Mmm01};
 use crate::{CartData, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use serde::{Deserialize, Serialize};
 
 pub trait Mbc {
     fn read_rom(&self, rom_bytes: &[u8], address: u16) -> u8;
@@ -8,27 +15,68 @@ pub trait Mbc {
     fn read_ram(&self, address: u16) -> u8;
     fn write_ram(&mut self, address: u16, value: u8);
     fn load_ram(&mut self, ram_bytes: Vec<u8>);
+    fn ram_bytes(&self) -> &[u8];
+    fn ram_bytes_mut(&mut self) -> &mut [u8];
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MbcVariant {
     Mbc1(Mbc1),
+    Mmm01(Mmm01),
+    HuC1(HuC1),
+    HuC3(HuC3),
+    PocketCamera(PocketCamera),
 }
 
 impl MbcVariant {
     pub fn new(cart_data: &CartData) -> Option<MbcVariant> {
-        match cart_data.get_cart_type().unwrap() {
+        if let Some(menu) = mmm01::find_menu(&cart_data.bytes) {
+            let menu_header = &cart_data.bytes[menu..];
+
+            return Self::with_type(
+                cart_data,
+                CartHeader::parse_cart_type(menu_header).unwrap(),
+                CartHeader::parse_ram_size(menu_header).unwrap_or(RamSize::NoRam),
+            );
+        }
+
+        Self::with_type(
+            cart_data,
+            cart_data.get_cart_type().unwrap(),
+            cart_data.get_ram_size().unwrap(),
+        )
+    }
+
+    /// Creates the mapper for the given type instead of the one in the header.
+    pub fn with_type(
+        cart_data: &CartData,
+        cart_type: CartType,
+        ram_size: RamSize,
+    ) -> Option<MbcVariant> {
+        match cart_type {
             CartType::RomOnly => None,
-            CartType::Mbc1 | CartType::Mbc1Ram | CartType::Mbc1RamBattery => Some(
-                MbcVariant::Mbc1(Mbc1::new(MbcData::new(cart_data.get_ram_size().unwrap()))),
-            ),
+            CartType::Mbc1 | CartType::Mbc1Ram | CartType::Mbc1RamBattery => {
+                let data = MbcData::new(ram_size);
+
+                Some(MbcVariant::Mbc1(if mbc1::is_multicart(&cart_data.bytes) {
+                    Mbc1::with_multicart(data)
+                } else {
+                    Mbc1::new(data)
+                }))
+            }
+            CartType::Mmm01 | CartType::Mmm01Ram | CartType::Mmm01RamBattery => {
+                Some(MbcVariant::Mmm01(Mmm01::new(MbcData::new(ram_size))))
+            }
+            CartType::HuC1RamBattery => Some(MbcVariant::HuC1(HuC1::new(MbcData::new(ram_size)))),
+            CartType::HuC3 => Some(MbcVariant::HuC3(HuC3::new(MbcData::new(ram_size)))),
+            // the photos need all of it whatever the header says
+            CartType::PocketCamera => Some(MbcVariant::PocketCamera(PocketCamera::new(
+                MbcData::new(RamSize::Ram128KiB),
+            ))),
             CartType::Mbc2
             | CartType::Mbc2Battery
             | CartType::RomRam
             | CartType::RomRamBattery
-            | CartType::Mmm01
-            | CartType::Mmm01Ram
-            | CartType::Mmm01RamBattery
             | CartType::Mbc3TimerBattery
             | CartType::Mbc3TimerRamBattery
             | CartType::Mbc3
@@ -40,10 +88,34 @@ impl MbcVariant {
             | CartType::Mbc5Rumble
             | CartType::Mbc5RumbleRam
             | CartType::Mbc5RumbleRamBattery
-            | CartType::PocketCamera
-            | CartType::BandaiTama5
-            | CartType::HuC3
-            | CartType::HuC1RamBattery => unimplemented!(),
+            | CartType::BandaiTama5 => unimplemented!(),
+        }
+    }
+
+    /// Puts the port in front of the IR LED and receiver, for mappers that have them.
+    pub fn set_ir_port(&mut self, port: Box<dyn IrPort>) -> Result<(), String> {
+        match self {
+            MbcVariant::HuC1(c) => c.set_ir_port(port),
+            MbcVariant::HuC3(c) => c.set_ir_port(port),
+            _ => return Err("The cart has no IR port".into()),
+        }
+
+        Ok(())
+    }
+
+    pub fn set_image_source(&mut self, source: Box<dyn ImageSource>) -> Result<(), String> {
+        match self {
+            MbcVariant::PocketCamera(c) => c.set_image_source(source),
+            _ => return Err("The cart has no camera".into()),
+        }
+
+        Ok(())
+    }
+
+    /// Advances mappers that keep time on their own, like the camera capture.
+    pub fn tick(&mut self, t_cycles: usize) {
+        if let MbcVariant::PocketCamera(c) = self {
+            c.tick(t_cycles);
         }
     }
 }
@@ -52,35 +124,75 @@ impl Mbc for MbcVariant {
     fn read_rom(&self, rom_bytes: &[u8], address: u16) -> u8 {
         match self {
             MbcVariant::Mbc1(c) => c.read_rom(rom_bytes, address),
+            MbcVariant::Mmm01(c) => c.read_rom(rom_bytes, address),
+            MbcVariant::HuC1(c) => c.read_rom(rom_bytes, address),
+            MbcVariant::HuC3(c) => c.read_rom(rom_bytes, address),
+            MbcVariant::PocketCamera(c) => c.read_rom(rom_bytes, address),
         }
     }
 
     fn write_rom(&mut self, rom_bytes: &mut Vec<u8>, address: u16, value: u8) {
         match self {
             MbcVariant::Mbc1(c) => c.write_rom(rom_bytes, address, value),
+            MbcVariant::Mmm01(c) => c.write_rom(rom_bytes, address, value),
+            MbcVariant::HuC1(c) => c.write_rom(rom_bytes, address, value),
+            MbcVariant::HuC3(c) => c.write_rom(rom_bytes, address, value),
+            MbcVariant::PocketCamera(c) => c.write_rom(rom_bytes, address, value),
         }
     }
 
     fn read_ram(&self, address: u16) -> u8 {
         match self {
             MbcVariant::Mbc1(c) => c.read_ram(address),
+            MbcVariant::Mmm01(c) => c.read_ram(address),
+            MbcVariant::HuC1(c) => c.read_ram(address),
+            MbcVariant::HuC3(c) => c.read_ram(address),
+            MbcVariant::PocketCamera(c) => c.read_ram(address),
         }
     }
 
     fn write_ram(&mut self, address: u16, value: u8) {
         match self {
             MbcVariant::Mbc1(c) => c.write_ram(address, value),
+            MbcVariant::Mmm01(c) => c.write_ram(address, value),
+            MbcVariant::HuC1(c) => c.write_ram(address, value),
+            MbcVariant::HuC3(c) => c.write_ram(address, value),
+            MbcVariant::PocketCamera(c) => c.write_ram(address, value),
         }
     }
 
     fn load_ram(&mut self, ram_bytes: Vec<u8>) {
         match self {
             MbcVariant::Mbc1(c) => c.load_ram(ram_bytes),
+            MbcVariant::Mmm01(c) => c.load_ram(ram_bytes),
+            MbcVariant::HuC1(c) => c.load_ram(ram_bytes),
+            MbcVariant::HuC3(c) => c.load_ram(ram_bytes),
+            MbcVariant::PocketCamera(c) => c.load_ram(ram_bytes),
+        }
+    }
+
+    fn ram_bytes(&self) -> &[u8] {
+        match self {
+            MbcVariant::Mbc1(c) => c.ram_bytes(),
+            MbcVariant::Mmm01(c) => c.ram_bytes(),
+            MbcVariant::HuC1(c) => c.ram_bytes(),
+            MbcVariant::HuC3(c) => c.ram_bytes(),
+            MbcVariant::PocketCamera(c) => c.ram_bytes(),
+        }
+    }
+
+    fn ram_bytes_mut(&mut self) -> &mut [u8] {
+        match self {
+            MbcVariant::Mbc1(c) => c.ram_bytes_mut(),
+            MbcVariant::Mmm01(c) => c.ram_bytes_mut(),
+            MbcVariant::HuC1(c) => c.ram_bytes_mut(),
+            MbcVariant::HuC3(c) => c.ram_bytes_mut(),
+            MbcVariant::PocketCamera(c) => c.ram_bytes_mut(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MbcData {
     pub ram_bytes: Vec<u8>,
     pub rom_bank: u16,
@@ -1,16 +1,27 @@
+use crate::cart::header::NINTENDO_LOGO;
 use crate::cart::mbc::{Mbc, MbcData};
-use crate::{MASK_MSB, RAM_ADDRESS_START};
+use crate::{RAM_ADDRESS_START, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+/// Multicarts repeat the logo in the header of each 256 KiB game, the second one at bank 0x10.
+const MULTICART_LOGO_ADDRESS: usize = 0x10 * ROM_BANK_SIZE + 0x0104;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum Mode {
     RomBanking,
     RamBanking,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Mbc1 {
     data: MbcData,
     mode: Mode,
+    /// 0x2000 - 0x3FFF, lower 5 bits of the ROM bank.
+    bank1: u8,
+    /// 0x4000 - 0x5FFF, upper bits of the ROM bank or the RAM bank.
+    bank2: u8,
+    /// MBC1M wires only 4 bits of `bank1`, so `bank2` selects one of the 256 KiB games.
+    is_multicart: bool,
 }
 
 impl Mbc1 {
@@ -18,79 +29,190 @@ impl Mbc1 {
         Self {
             data: inner,
             mode: Mode::RomBanking,
+            bank1: 1,
+            bank2: 0,
+            is_multicart: false,
+        }
+    }
+
+    pub fn with_multicart(inner: MbcData) -> Self {
+        Self {
+            is_multicart: true,
+            ..Self::new(inner)
+        }
+    }
+
+    fn get_bank2_shift(&self) -> u32 {
+        if self.is_multicart {
+            4
+        } else {
+            5
+        }
+    }
+
+    /// Bank at 0x0000 - 0x3FFF, switchable in RAM banking mode only.
+    fn get_rom_bank0(&self) -> usize {
+        match self.mode {
+            Mode::RomBanking => 0,
+            Mode::RamBanking => (self.bank2 as usize) << self.get_bank2_shift(),
+        }
+    }
+
+    /// Bank at 0x4000 - 0x7FFF.
+    fn get_rom_bank(&self) -> usize {
+        let bank1 = if self.is_multicart {
+            self.bank1 & 0x0F
+        } else {
+            self.bank1
+        };
+
+        ((self.bank2 as usize) << self.get_bank2_shift()) | bank1 as usize
+    }
+
+    fn get_ram_bank(&self) -> usize {
+        match self.mode {
+            Mode::RomBanking => 0,
+            Mode::RamBanking => self.bank2 as usize,
         }
     }
 }
 
+/// MBC1M carts have the same header type as MBC1 ones, they are told apart by the logo.
+pub fn is_multicart(rom_bytes: &[u8]) -> bool {
+    let end = MULTICART_LOGO_ADDRESS + NINTENDO_LOGO.len();
+
+    rom_bytes.len() >= end && rom_bytes[MULTICART_LOGO_ADDRESS..end] == NINTENDO_LOGO
+}
+
 impl Mbc for Mbc1 {
     fn read_rom(&self, rom_bytes: &[u8], address: u16) -> u8 {
-        match (address & MASK_MSB) >> 12 {
-            // 0x0000 - 0x3FFF (Bank 00)
-            0x0..=0x3 => rom_bytes[address as usize],
-            // 0x4000 - 0x7FFF (Bank 01-7F)
-            0x4..=0x7 => {
-                let offset = self.data.rom_offset * self.data.rom_bank as usize;
-                rom_bytes[(address as usize - self.data.rom_offset) + offset]
-            }
-            _ => 0xFF,
-        }
+        let bank = match address {
+            0x0000..=0x3FFF => self.get_rom_bank0(),
+            0x4000..=0x7FFF => self.get_rom_bank(),
+            _ => return 0xFF,
+        };
+        let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+
+        // banks past the end wrap around as the upper address lines are not connected
+        rom_bytes[offset % rom_bytes.len()]
     }
 
-    fn write_rom(&mut self, rom_data: &mut Vec<u8>, address: u16, value: u8) {
-        match (address & MASK_MSB) >> 12 {
-            // 0x0000 - 0x1FFF (RAM enable)
-            0x0 | 0x1 => self.data.ram_enabled = value == 0x0A,
-            // 0x2000 - 0x3FFF (ROM bank number)
-            0x2 | 0x3 => {
-                // Specify the lower 5 bits
-                let bank_number = if value == 0 { 1 } else { value };
-                self.data.rom_bank =
-                    (self.data.rom_bank & 0b0110_0000) | (bank_number & 0b0001_1111) as u16;
+    fn write_rom(&mut self, _rom_bytes: &mut Vec<u8>, address: u16, value: u8) {
+        match address {
+            // RAM enable
+            0x0000..=0x1FFF => self.data.ram_enabled = value & 0x0F == 0x0A,
+            // ROM bank number, 0 is treated as 1
+            0x2000..=0x3FFF => self.bank1 = (value & 0x1F).max(1),
+            // RAM bank number — or — upper bits of ROM bank number
+            0x4000..=0x5FFF => self.bank2 = value & 0b11,
+            // Banking mode select
+            0x6000..=0x7FFF => {
+                self.mode = if value & 1 == 0 {
+                    Mode::RomBanking
+                } else {
+                    Mode::RamBanking
+                }
             }
-            // 0x4000 - 0x5FFF (RAM bank number — or — upper bits of ROM bank number)
-            0x4 | 0x5 => match self.mode {
-                Mode::RamBanking => self.data.ram_bank = value,
-                Mode::RomBanking => self.data.rom_bank |= ((value & 0b0000_0011) << 5) as u16,
-            },
-            // 0x6000 - 0x7FFF (Banking mode select)
-            0x6 | 0x7 => match value {
-                0 => self.mode = Mode::RomBanking,
-                1 => self.mode = Mode::RamBanking,
-                _ => {}
-            },
             _ => (),
         }
 
-        let max_banks = (rom_data.len() / self.data.rom_offset).max(1);
-
-        if self.data.rom_bank as usize >= max_banks {
-            self.data.rom_bank = (self.data.rom_bank as usize % max_banks) as u16;
-        }
+        self.data.rom_bank = self.get_rom_bank() as u16;
+        self.data.ram_bank = self.get_ram_bank() as u8;
     }
 
     fn read_ram(&self, address: u16) -> u8 {
-        if !self.data.ram_enabled {
+        if !self.data.ram_enabled || self.data.ram_bytes.is_empty() {
             return 0xFF;
         }
 
-        let offset = self.data.ram_offset * self.data.ram_bank as usize;
+        let offset = self.get_ram_bank() * RAM_BANK_SIZE + (address as usize - RAM_ADDRESS_START);
 
-        self.data.ram_bytes[(address as usize - RAM_ADDRESS_START) + offset]
+        self.data.ram_bytes[offset % self.data.ram_bytes.len()]
     }
 
     fn write_ram(&mut self, address: u16, value: u8) {
-        if !self.data.ram_enabled {
+        if !self.data.ram_enabled || self.data.ram_bytes.is_empty() {
             return;
         }
 
-        let ram_offset = self.data.ram_offset;
-        let ram_bank = self.data.ram_bank;
-        let offset = ram_offset * ram_bank as usize;
+        let offset = self.get_ram_bank() * RAM_BANK_SIZE + (address as usize - RAM_ADDRESS_START);
+        let len = self.data.ram_bytes.len();
 
-        self.data.ram_bytes[(address as usize - RAM_ADDRESS_START) + offset] = value;
+        self.data.ram_bytes[offset % len] = value;
     }
 
     fn load_ram(&mut self, ram_data: Vec<u8>) {
         self.data.ram_bytes = ram_data;
     }
+
+    fn ram_bytes(&self) -> &[u8] {
+        &self.data.ram_bytes
+    }
+
+    fn ram_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data.ram_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart::header::RamSize;
+
+    /// 1 MiB ROM with the bank number in the first byte of each bank.
+    fn create_rom(is_multicart: bool) -> Vec<u8> {
+        let mut rom: Vec<u8> = (0..64 * ROM_BANK_SIZE)
+            .map(|i| (i / ROM_BANK_SIZE) as u8)
+            .collect();
+        rom[0x0104..0x0134].copy_from_slice(&NINTENDO_LOGO);
+
+        if is_multicart {
+            rom[MULTICART_LOGO_ADDRESS..MULTICART_LOGO_ADDRESS + 48]
+                .copy_from_slice(&NINTENDO_LOGO);
+        }
+
+        rom
+    }
+
+    #[test]
+    fn test_banking() {
+        let mut rom = create_rom(false);
+        let mut mbc = Mbc1::new(MbcData::new(RamSize::Ram32KiB));
+        assert!(!is_multicart(&rom));
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+        mbc.write_rom(&mut rom, 0x2000, 0x12);
+        mbc.write_rom(&mut rom, 0x4000, 1);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x32);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0);
+
+        mbc.write_rom(&mut rom, 0x6000, 1);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x20);
+
+        mbc.write_rom(&mut rom, 0x2000, 0);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x21);
+    }
+
+    #[test]
+    fn test_multicart_banking() {
+        let mut rom = create_rom(true);
+        let mut mbc = Mbc1::with_multicart(MbcData::new(RamSize::NoRam));
+        assert!(is_multicart(&rom));
+
+        // the menu selects the second game and switches its bank 0 in
+        mbc.write_rom(&mut rom, 0x4000, 1);
+        mbc.write_rom(&mut rom, 0x6000, 1);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x10);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x11);
+
+        // bit 4 of the bank is not connected
+        mbc.write_rom(&mut rom, 0x2000, 0x12);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x12);
+        mbc.write_rom(&mut rom, 0x2000, 0x10);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x10);
+
+        mbc.write_rom(&mut rom, 0x4000, 3);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x30);
+        assert_eq!(mbc.read_rom(&rom, 0xFFFF), 0xFF);
+    }
 }
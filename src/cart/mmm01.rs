@@ -0,0 +1,283 @@
+use crate::cart::header::{CartHeader, CartType, NINTENDO_LOGO};
+use crate::cart::mbc::{Mbc, MbcData};
+use crate::{RAM_ADDRESS_START, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use serde::{Deserialize, Serialize};
+
+/// The menu is the last 32 KiB of the ROM, its header is the one with the MMM01 type.
+const MENU_SIZE: usize = 2 * ROM_BANK_SIZE;
+
+/// Compilation mapper. It starts unmapped with the menu at 0x0000 - 0x7FFF, the menu sets
+/// up the banks of the selected game and locks them, after which the game sees an MBC1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mmm01 {
+    data: MbcData,
+    is_mapped: bool,
+    /// RB0-RB8.
+    rom_bank: u16,
+    /// Bits of RB1-RB4 the game can't change once mapped.
+    rom_bank_mask: u8,
+    /// RA0-RA3.
+    ram_bank: u8,
+    /// Bits of RA0-RA1 the game can't change once mapped.
+    ram_bank_mask: u8,
+    is_mode_locked: bool,
+    /// MBC1 banking mode, only picks the RAM bank.
+    is_ram_banking: bool,
+}
+
+impl Mmm01 {
+    pub fn new(inner: MbcData) -> Self {
+        Self {
+            data: inner,
+            is_mapped: false,
+            rom_bank: 0,
+            rom_bank_mask: 0,
+            ram_bank: 0,
+            ram_bank_mask: 0,
+            is_mode_locked: false,
+            is_ram_banking: false,
+        }
+    }
+
+    /// Bits of the bank the game can switch, RB0 always is.
+    fn get_writable_rom_bits(&self) -> u16 {
+        0x1F & !((self.rom_bank_mask as u16) << 1)
+    }
+
+    fn get_rom_bank(&self, address: u16) -> usize {
+        if !self.is_mapped {
+            // all bank lines but A14 are pulled high, selecting the menu
+            return 0x1FE | (address as usize >> 14);
+        }
+
+        let base = self.rom_bank & !self.get_writable_rom_bits();
+
+        if address < 0x4000 {
+            return base as usize;
+        }
+
+        // like MBC1, bank 0 of the game can't be selected in the upper area
+        let bank = self.rom_bank & self.get_writable_rom_bits();
+
+        (base | bank.max(1)) as usize
+    }
+
+    /// Like MBC1 in ROM banking mode, the bits the game can switch read as 0.
+    fn get_ram_bank(&self) -> usize {
+        if self.is_ram_banking {
+            self.ram_bank as usize
+        } else {
+            (self.ram_bank & !(0b11 & !self.ram_bank_mask)) as usize
+        }
+    }
+
+    /// Sets the bits selected by `bits`, leaving the masked ones once mapped.
+    fn set_bits(&self, current: u16, value: u16, bits: u16, mask: u16) -> u16 {
+        let bits = if self.is_mapped { bits & !mask } else { bits };
+
+        (current & !bits) | (value & bits)
+    }
+}
+
+/// Offset of the menu in the ROM when the cart is an MMM01 compilation. The header at the
+/// start belongs to the first game and has its type. The menu header must be a valid one,
+/// with the logo and checksum, so stray bytes in a plain ROM aren't taken for it.
+pub fn find_menu(rom_bytes: &[u8]) -> Option<usize> {
+    let offset = rom_bytes.len().checked_sub(MENU_SIZE)?;
+    let menu = &rom_bytes[offset..];
+    let cart_type = CartType::try_from(menu[0x0147]).ok()?;
+
+    let is_mmm01 = matches!(
+        cart_type,
+        CartType::Mmm01 | CartType::Mmm01Ram | CartType::Mmm01RamBattery
+    );
+    let is_valid = menu[0x0104..0x0104 + NINTENDO_LOGO.len()] == NINTENDO_LOGO
+        && CartHeader::get_header_checksum(menu) == CartHeader::calc_header_checksum(menu);
+
+    (is_mmm01 && is_valid).then_some(offset)
+}
+
+impl Mbc for Mmm01 {
+    fn read_rom(&self, rom_bytes: &[u8], address: u16) -> u8 {
+        if address > 0x7FFF {
+            return 0xFF;
+        }
+
+        let banks = rom_bytes.len() / ROM_BANK_SIZE;
+        let bank = self.get_rom_bank(address) % banks.max(1);
+
+        rom_bytes[bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))]
+    }
+
+    fn write_rom(&mut self, _rom_bytes: &mut Vec<u8>, address: u16, value: u8) {
+        let value16 = value as u16;
+
+        match address {
+            0x0000..=0x1FFF => {
+                self.data.ram_enabled = value & 0x0F == 0x0A;
+
+                if !self.is_mapped {
+                    self.ram_bank_mask = (value >> 4) & 0b11;
+                    self.is_mapped = value & 0x40 != 0;
+                }
+            }
+            0x2000..=0x3FFF => {
+                let mask = (self.rom_bank_mask as u16) << 1;
+                self.rom_bank = self.set_bits(self.rom_bank, value16, 0x1F, mask);
+                // RB5-RB6
+                self.rom_bank = self.set_bits(self.rom_bank, value16, 0x60, 0x60);
+            }
+            0x4000..=0x5FFF => {
+                let mask = self.ram_bank_mask as u16;
+                let ram_bank = self.set_bits(self.ram_bank as u16, value16, 0b11, mask);
+                // RA2-RA3
+                self.ram_bank = self.set_bits(ram_bank, value16, 0b1100, 0b1100) as u8;
+
+                if !self.is_mapped {
+                    // RB7-RB8
+                    self.rom_bank = (self.rom_bank & 0x7F) | ((value16 & 0x30) << 3);
+                    self.is_mode_locked = value & 0x40 != 0;
+                }
+            }
+            0x6000..=0x7FFF => {
+                if !self.is_mode_locked {
+                    self.is_ram_banking = value & 1 != 0;
+                }
+
+                // FIXME: the multiplexer of bit 6 is not emulated
+                if !self.is_mapped {
+                    self.rom_bank_mask = (value >> 2) & 0x0F;
+                }
+            }
+            _ => (),
+        }
+
+        self.data.rom_bank = self.get_rom_bank(0x4000) as u16;
+        self.data.ram_bank = self.get_ram_bank() as u8;
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.data.ram_enabled || self.data.ram_bytes.is_empty() {
+            return 0xFF;
+        }
+
+        let offset = self.get_ram_bank() * RAM_BANK_SIZE + (address as usize - RAM_ADDRESS_START);
+
+        self.data.ram_bytes[offset % self.data.ram_bytes.len()]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.data.ram_enabled || self.data.ram_bytes.is_empty() {
+            return;
+        }
+
+        let offset = self.get_ram_bank() * RAM_BANK_SIZE + (address as usize - RAM_ADDRESS_START);
+        let len = self.data.ram_bytes.len();
+
+        self.data.ram_bytes[offset % len] = value;
+    }
+
+    fn load_ram(&mut self, ram_data: Vec<u8>) {
+        self.data.ram_bytes = ram_data;
+    }
+
+    fn ram_bytes(&self) -> &[u8] {
+        &self.data.ram_bytes
+    }
+
+    fn ram_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data.ram_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart::header::RamSize;
+    use crate::cart::mbc::MbcVariant;
+    use crate::cart::Cart;
+
+    /// 256 KiB compilation with the bank number in the first byte of each bank.
+    fn create_rom() -> Vec<u8> {
+        let mut rom: Vec<u8> = (0..16 * ROM_BANK_SIZE)
+            .map(|i| (i / ROM_BANK_SIZE) as u8)
+            .collect();
+        // the first game is an MBC1 one, the menu has the MMM01 header
+        rom[0x0147] = CartType::Mbc1 as u8;
+        set_header(&mut rom[14 * ROM_BANK_SIZE..], CartType::Mmm01);
+
+        rom
+    }
+
+    fn set_header(bytes: &mut [u8], cart_type: CartType) {
+        bytes[0x0104..0x0104 + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+        bytes[0x0147] = cart_type as u8;
+        bytes[0x014D] = CartHeader::calc_header_checksum(bytes);
+    }
+
+    #[test]
+    fn test_find_menu() {
+        let rom = create_rom();
+        assert_eq!(find_menu(&rom), Some(14 * ROM_BANK_SIZE));
+        assert_eq!(find_menu(&rom[..0x8000]), None);
+
+        // no logo or a bad checksum, the byte is just data of a plain ROM
+        let mut bad_checksum = rom.clone();
+        bad_checksum[14 * ROM_BANK_SIZE + 0x014D] ^= 0xFF;
+        assert_eq!(find_menu(&bad_checksum), None);
+
+        let mut no_logo = rom.clone();
+        no_logo[14 * ROM_BANK_SIZE + 0x0104] = 0;
+        assert_eq!(find_menu(&no_logo), None);
+
+        let cart = Cart::new(rom).unwrap();
+        assert!(matches!(cart.mbc, Some(MbcVariant::Mmm01(_))));
+    }
+
+    #[test]
+    fn test_find_menu_mbc1() {
+        let mut rom = vec![0; 16 * ROM_BANK_SIZE];
+        set_header(&mut rom, CartType::Mbc1);
+        // an MMM01 type byte in the data where the menu header would be
+        rom[14 * ROM_BANK_SIZE + 0x0147] = CartType::Mmm01 as u8;
+
+        assert_eq!(find_menu(&rom), None);
+
+        let cart = Cart::new(rom).unwrap();
+        assert!(matches!(cart.mbc, Some(MbcVariant::Mbc1(_))));
+    }
+
+    #[test]
+    fn test_lock_sequence() {
+        let mut rom = create_rom();
+        let mut mbc = Mmm01::new(MbcData::new(RamSize::NoRam));
+
+        // unmapped, the menu is in both areas
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 14);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 15);
+
+        // the menu maps the 64 KiB game at bank 4, RB2-RB4 are masked
+        mbc.write_rom(&mut rom, 0x2000, 0x04);
+        mbc.write_rom(&mut rom, 0x6000, 0b0011_1000);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 14);
+        mbc.write_rom(&mut rom, 0x0000, 0x40);
+
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 4);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 5);
+
+        // the game switches its own banks only
+        mbc.write_rom(&mut rom, 0x2000, 0x02);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 6);
+        mbc.write_rom(&mut rom, 0x2000, 0x1F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 7);
+        mbc.write_rom(&mut rom, 0x2000, 0x04);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 5);
+
+        // locked, the menu can't be mapped back
+        mbc.write_rom(&mut rom, 0x0000, 0x00);
+        mbc.write_rom(&mut rom, 0x4000, 0x30);
+        mbc.write_rom(&mut rom, 0x6000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 4);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 5);
+    }
+}
@@ -1,6 +1,13 @@
+pub mod camera;
+pub mod camera_image;
 pub mod cart;
 pub mod header;
+pub mod huc1;
+pub mod huc3;
+pub mod info;
+pub mod ir;
 pub mod mbc;
 pub mod mbc1;
+pub mod mmm01;
 
 pub use cart::*;
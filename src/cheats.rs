@@ -0,0 +1,366 @@
+use crate::bus::Bus;
+use crate::cart::{CartData, RomPatch};
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const ROM_END: u16 = 0x7FFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatCode {
+    /// `TTVVLLHH`: writes VV to HHLL every frame. Address is stored little-endian,
+    /// TT is the code type (01 for the usual RAM write, 8X/9X select a CGB WRAM bank).
+    GameShark {
+        code_type: u8,
+        value: u8,
+        address: u16,
+    },
+    /// `VVA-AAA-CCC` or `VVA-AAA`: replaces ROM reads, optionally only when the original
+    /// byte equals the compare value.
+    GameGenie {
+        address: u16,
+        value: u8,
+        compare: Option<u8>,
+    },
+    /// `AAAA:VV`: ROM addresses are patched like Game Genie, others are written every frame.
+    Raw { address: u16, value: u8 },
+}
+
+impl CheatCode {
+    pub fn parse(code: &str) -> Result<CheatCode, String> {
+        let code = code.trim();
+
+        if let Some((address, value)) = code.split_once(':') {
+            return Ok(CheatCode::Raw {
+                address: parse_hex(address.trim(), 4)? as u16,
+                value: parse_hex(value.trim(), 2)? as u8,
+            });
+        }
+
+        if code.contains('-') {
+            return parse_game_genie(code);
+        }
+
+        if code.len() == 8 {
+            let bytes = parse_hex(code, 8)?;
+
+            return Ok(CheatCode::GameShark {
+                code_type: (bytes >> 24) as u8,
+                value: (bytes >> 16) as u8,
+                address: (bytes as u16).swap_bytes(),
+            });
+        }
+
+        Err(format!("Unknown cheat code format: {}", code))
+    }
+
+    pub fn rom_patch(&self) -> Option<RomPatch> {
+        match *self {
+            CheatCode::GameGenie {
+                address,
+                value,
+                compare,
+            } => Some(RomPatch {
+                address,
+                value,
+                compare,
+            }),
+            CheatCode::Raw { address, value } if address <= ROM_END => Some(RomPatch {
+                address,
+                value,
+                compare: None,
+            }),
+            CheatCode::GameShark { .. } | CheatCode::Raw { .. } => None,
+        }
+    }
+
+    /// Returns address and value to write every frame.
+    pub fn ram_write(&self) -> Option<(u16, u8)> {
+        match *self {
+            CheatCode::GameShark { address, value, .. } => Some((address, value)),
+            CheatCode::Raw { address, value } if address > ROM_END => Some((address, value)),
+            CheatCode::GameGenie { .. } | CheatCode::Raw { .. } => None,
+        }
+    }
+}
+
+/// Decodes `ABC-DEF-GHI`: value is AB, address is (F ^ 0xF)CDE and compare is GI rotated
+/// right by 2 and xored with 0xBA. H is a check digit and is ignored.
+fn parse_game_genie(code: &str) -> Result<CheatCode, String> {
+    let digits: String = code.chars().filter(|c| *c != '-').collect();
+
+    if digits.len() != 6 && digits.len() != 9 {
+        return Err(format!("Game Genie code must have 6 or 9 digits: {}", code));
+    }
+
+    let nibbles = digits
+        .chars()
+        .map(|c| {
+            c.to_digit(16)
+                .map(|d| d as u16)
+                .ok_or(format!("Invalid hex digit in cheat code: {}", code))
+        })
+        .collect::<Result<Vec<u16>, String>>()?;
+
+    let value = (nibbles[0] << 4 | nibbles[1]) as u8;
+    let address = ((nibbles[5] ^ 0xF) << 12) | nibbles[2] << 8 | nibbles[3] << 4 | nibbles[4];
+    let compare = if nibbles.len() == 9 {
+        let byte = (nibbles[6] << 4 | nibbles[8]) as u8;
+        Some(byte.rotate_right(2) ^ 0xBA)
+    } else {
+        None
+    };
+
+    Ok(CheatCode::GameGenie {
+        address,
+        value,
+        compare,
+    })
+}
+
+fn parse_hex(text: &str, max_digits: usize) -> Result<u32, String> {
+    if text.is_empty() || text.len() > max_digits {
+        return Err(format!(
+            "Expected up to {} hex digits: {}",
+            max_digits, text
+        ));
+    }
+
+    u32::from_str_radix(text, 16).map_err(|e| format!("Invalid hex value {}: {}", text, e))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cheat {
+    pub name: String,
+    /// Multiple codes can be separated with `+`, e.g. `010F3DC0+01633EC0`
+    pub code: String,
+    pub enabled: bool,
+}
+
+impl Cheat {
+    pub fn parse_codes(&self) -> Result<Vec<CheatCode>, String> {
+        self.code.split('+').map(CheatCode::parse).collect()
+    }
+}
+
+/// Cheats of a single ROM, stored in `save/cheats/<TITLE>_<GLOBAL CHECKSUM>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cheats {
+    pub enabled: bool,
+    pub cheats: Vec<Cheat>,
+    #[serde(skip)]
+    codes: Vec<CheatCode>,
+}
+
+impl Default for Cheats {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cheats: Vec::new(),
+            codes: Vec::new(),
+        }
+    }
+}
+
+impl Cheats {
+    pub fn new(cheats: Vec<Cheat>) -> Result<Self, String> {
+        let mut obj = Self {
+            cheats,
+            ..Default::default()
+        };
+        obj.parse()?;
+
+        Ok(obj)
+    }
+
+    pub fn path(cart_data: &CartData) -> PathBuf {
+        let title: String = cart_data
+            .get_title()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let file_name = format!("{}_{:04X}.json", title, cart_data.get_global_checksum());
+
+        Config::save_dir().join("cheats").join(file_name)
+    }
+
+    /// Loads cheats for the cart, empty if there is no cheat file yet.
+    pub fn load(cart_data: &CartData) -> Result<Self, String> {
+        let path = Cheats::path(cart_data);
+
+        if !path.exists() {
+            return Ok(Cheats::default());
+        }
+
+        let data = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let mut cheats: Cheats = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+        cheats.parse()?;
+
+        Ok(cheats)
+    }
+
+    pub fn save(&self, cart_data: &CartData) -> Result<(), String> {
+        let path = Cheats::path(cart_data);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    fn parse(&mut self) -> Result<(), String> {
+        self.codes.clear();
+
+        for cheat in self.cheats.iter().filter(|c| c.enabled) {
+            let codes = cheat
+                .parse_codes()
+                .map_err(|e| format!("Cheat '{}': {}", cheat.name, e))?;
+            self.codes.extend(codes);
+        }
+
+        Ok(())
+    }
+
+    pub fn toggle(&mut self, bus: &mut Bus) {
+        self.enabled = !self.enabled;
+        self.install(bus);
+    }
+
+    pub fn set_cheat_enabled(&mut self, idx: usize, enabled: bool) -> Result<(), String> {
+        let cheat = self.cheats.get_mut(idx).ok_or("Cheat not found")?;
+        cheat.enabled = enabled;
+
+        self.parse()
+    }
+
+    /// Replaces cart ROM patches with the Game Genie/raw ROM codes of enabled cheats.
+    pub fn install(&self, bus: &mut Bus) {
+        bus.cart.rom_patches.clear();
+
+        if self.enabled {
+            bus.cart
+                .rom_patches
+                .extend(self.codes.iter().filter_map(|c| c.rom_patch()));
+        }
+    }
+
+    /// Applies GameShark/raw RAM writes, should be called once per frame.
+    pub fn apply(&self, bus: &mut Bus) {
+        if !self.enabled {
+            return;
+        }
+
+        for (address, value) in self.codes.iter().filter_map(|c| c.ram_write()) {
+            bus.write(address, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart::Cart;
+
+    #[test]
+    fn test_parse_game_shark() {
+        assert_eq!(
+            CheatCode::parse("010F3DC0").unwrap(),
+            CheatCode::GameShark {
+                code_type: 0x01,
+                value: 0x0F,
+                address: 0xC03D
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_game_genie() {
+        assert_eq!(
+            CheatCode::parse("00A-17B-C49").unwrap(),
+            CheatCode::GameGenie {
+                address: 0x4A17,
+                value: 0x00,
+                compare: Some(0xC8)
+            }
+        );
+        assert_eq!(
+            CheatCode::parse("3EF-15F").unwrap(),
+            CheatCode::GameGenie {
+                address: 0x0F15,
+                value: 0x3E,
+                compare: None
+            }
+        );
+        assert!(CheatCode::parse("00A-17B-C4").is_err());
+        assert!(CheatCode::parse("00A-17B-CXX").is_err());
+    }
+
+    #[test]
+    fn test_parse_raw() {
+        assert_eq!(
+            CheatCode::parse("C0A0:63").unwrap(),
+            CheatCode::Raw {
+                address: 0xC0A0,
+                value: 0x63
+            }
+        );
+        assert!(CheatCode::parse("C0A0:163").is_err());
+    }
+
+    #[test]
+    fn test_rom_patch_compare() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x4A17] = 0xC8;
+        rom[0x0150] = 0x11;
+        let mut bus = Bus::new(Cart::new(rom).unwrap());
+        let cheats = Cheats::new(vec![
+            Cheat {
+                name: "genie".to_string(),
+                code: "00A-17B-C49".to_string(),
+                enabled: true,
+            },
+            Cheat {
+                name: "raw".to_string(),
+                code: "0150:22".to_string(),
+                enabled: true,
+            },
+        ])
+        .unwrap();
+
+        cheats.install(&mut bus);
+        assert_eq!(bus.read(0x4A17), 0x00);
+        assert_eq!(bus.read(0x0150), 0x22);
+
+        bus.cart.data.bytes[0x4A17] = 0x01; // other bank content
+        assert_eq!(bus.read(0x4A17), 0x01);
+    }
+
+    #[test]
+    fn test_ram_writes_and_toggle() {
+        let mut bus = Bus::new(Cart::new(vec![0; 0x8000]).unwrap());
+        let mut cheats = Cheats::new(vec![Cheat {
+            name: "lives".to_string(),
+            code: "0109A0C0+C0A1:05".to_string(),
+            enabled: true,
+        }])
+        .unwrap();
+
+        cheats.apply(&mut bus);
+        assert_eq!(bus.read(0xC0A0), 0x09);
+        assert_eq!(bus.read(0xC0A1), 0x05);
+
+        bus.write(0xC0A0, 0);
+        cheats.toggle(&mut bus);
+        cheats.apply(&mut bus);
+        assert_eq!(bus.read(0xC0A0), 0);
+
+        cheats.toggle(&mut bus);
+        cheats.set_cheat_enabled(0, false).unwrap();
+        cheats.apply(&mut bus);
+        assert_eq!(bus.read(0xC0A0), 0);
+    }
+}
@@ -1,14 +1,35 @@
+use crate::apu::SAMPLING_FREQ;
+use crate::auxiliary::joypad::JoypadButton;
+use crate::capture::screenshot::ScreenshotFormat;
+use crate::capture::video_recorder::VideoFormat;
+use crate::ui::display::DisplayMode;
+use crate::ui::filters::FilterConfig;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 use std::{env, fs, io};
 
+pub const MAX_RECENT_CARTS: usize = 10;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub last_cart_path: Option<String>,
     pub emulation: EmulationConfig,
     pub graphics: GraphicsConfig,
+    #[serde(default)]
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub input: InputConfig,
+    /// Most recent first.
+    #[serde(default)]
+    pub recent_carts: Vec<String>,
+    /// Where the ROM browser of the menu starts.
+    #[serde(default)]
+    pub roms_dir: Option<String>,
+    /// No-Intro DAT used to show the canonical names of loaded carts.
+    #[serde(default)]
+    pub dat_path: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -27,6 +48,145 @@ pub struct GraphicsConfig {
     pub is_fullscreen: bool,
     pub show_fps: bool,
     pub text_scale: usize,
+    /// Format of F12 screenshots, `Scaled` follows the current window scale.
+    #[serde(default)]
+    pub screenshot_format: ScreenshotFormat,
+    /// Format of F7 video recordings.
+    #[serde(default)]
+    pub video_format: VideoFormat,
+    #[serde(default)]
+    pub filters: FilterConfig,
+    #[serde(default)]
+    pub display_mode: DisplayMode,
+    /// 256x224 PNG drawn around the screen.
+    #[serde(default)]
+    pub border_path: Option<String>,
+    /// Last size of the main window, `scale` is used when not set.
+    #[serde(default)]
+    pub window_size: Option<(u32, u32)>,
+    /// Last position of the main window, it is centered when not set.
+    #[serde(default)]
+    pub window_position: Option<(i32, i32)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AudioConfig {
+    /// Output rate in Hz, the APU is resampled to it.
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: u32,
+    /// Also write a WAV per APU channel when recording.
+    #[serde(default)]
+    pub record_channels: bool,
+    /// Paces emulation by the audio queue instead of the frame limit at normal speed.
+    #[serde(default = "default_sync_to_audio")]
+    pub sync_to_audio: bool,
+    /// Audio queued ahead of the device the sync aims for.
+    #[serde(default = "default_latency_ms")]
+    pub latency_ms: u32,
+    /// Output volume from 0.0 to 1.0.
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: default_sample_rate(),
+            record_channels: false,
+            sync_to_audio: default_sync_to_audio(),
+            latency_ms: default_latency_ms(),
+            volume: default_volume(),
+        }
+    }
+}
+
+fn default_sample_rate() -> u32 {
+    SAMPLING_FREQ as u32
+}
+
+fn default_sync_to_audio() -> bool {
+    true
+}
+
+fn default_latency_ms() -> u32 {
+    60
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+/// SDL key names bound to the joypad buttons.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InputConfig {
+    pub up: String,
+    pub down: String,
+    pub left: String,
+    pub right: String,
+    pub a: String,
+    pub b: String,
+    pub start: String,
+    pub select: String,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            up: "Up".to_string(),
+            down: "Down".to_string(),
+            left: "Left".to_string(),
+            right: "Right".to_string(),
+            a: "X".to_string(),
+            b: "Z".to_string(),
+            start: "Return".to_string(),
+            select: "Backspace".to_string(),
+        }
+    }
+}
+
+impl InputConfig {
+    pub fn get_key(&self, button: JoypadButton) -> &str {
+        match button {
+            JoypadButton::Up => &self.up,
+            JoypadButton::Down => &self.down,
+            JoypadButton::Left => &self.left,
+            JoypadButton::Right => &self.right,
+            JoypadButton::A => &self.a,
+            JoypadButton::B => &self.b,
+            JoypadButton::Start => &self.start,
+            JoypadButton::Select => &self.select,
+        }
+    }
+
+    /// Binds the key, a button which had it before gets the previous key of this one.
+    pub fn set_key(&mut self, button: JoypadButton, key: &str) {
+        let previous = self.get_key(button).to_string();
+
+        if let Some(other) = self.find_button(key) {
+            *self.get_key_mut(other) = previous;
+        }
+
+        *self.get_key_mut(button) = key.to_string();
+    }
+
+    pub fn find_button(&self, key: &str) -> Option<JoypadButton> {
+        JoypadButton::ALL
+            .into_iter()
+            .find(|button| self.get_key(*button) == key)
+    }
+
+    fn get_key_mut(&mut self, button: JoypadButton) -> &mut String {
+        match button {
+            JoypadButton::Up => &mut self.up,
+            JoypadButton::Down => &mut self.down,
+            JoypadButton::Left => &mut self.left,
+            JoypadButton::Right => &mut self.right,
+            JoypadButton::A => &mut self.a,
+            JoypadButton::B => &mut self.b,
+            JoypadButton::Start => &mut self.start,
+            JoypadButton::Select => &mut self.select,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -36,6 +196,13 @@ pub struct Pallet {
 }
 
 impl Config {
+    /// Moves the path to the front of the recent carts.
+    pub fn add_recent_cart(&mut self, path: &str) {
+        self.recent_carts.retain(|recent| recent != path);
+        self.recent_carts.insert(0, path.to_string());
+        self.recent_carts.truncate(MAX_RECENT_CARTS);
+    }
+
     pub fn from_file(path: &str) -> io::Result<Self> {
         let data = fs::read_to_string(path)?;
         let config: Self = serde_json::from_str(&data)?;
@@ -53,12 +220,17 @@ impl Config {
     }
 
     pub fn default_path() -> PathBuf {
+        Config::save_dir().join("config.json")
+    }
+
+    /// The `save` folder next to the binary.
+    pub fn save_dir() -> PathBuf {
         // Get the directory where the binary is running from
         let exe_path = env::current_exe().expect("Failed to get executable path");
         let exe_dir = exe_path
             .parent()
             .expect("Failed to get executable directory");
 
-        exe_dir.join("save/config.json")
+        exe_dir.join("save")
     }
 }
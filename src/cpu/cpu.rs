@@ -1,8 +1,9 @@
 use crate::bus::Bus;
-use crate::cpu::instructions::{AddressMode, ExecutableInstruction, Instruction};
-use crate::cpu::instructions::{FetchedData, RegisterType};
+use crate::cpu::instructions::dispatch::DispatchTable;
+use crate::cpu::instructions::{FetchedData, Instruction, RegisterType};
 use crate::cpu::Registers;
 use crate::LittleEndianBytes;
+use serde::{Deserialize, Serialize};
 
 pub const CPU_CLOCK_SPEED: u32 = 4194304;
 
@@ -34,8 +35,10 @@ impl CpuCallback for CounterCpuCallback {
     fn debug(&mut self, _cpu: &mut Cpu, _ctx: Option<DebugCtx>) {}
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cpu {
+    /// Not in save states, see `clone_without_bus`.
+    #[serde(skip)]
     pub bus: Bus,
     pub registers: Registers,
     pub enabling_ime: bool,
@@ -97,7 +100,7 @@ impl Cpu {
         callback.m_cycles(1, &mut self.bus);
     }
 
-    pub fn step(&mut self, callback: &mut impl CpuCallback) -> Result<(), String> {
+    pub fn step<C: CpuCallback>(&mut self, callback: &mut C) -> Result<(), String> {
         #[cfg(debug_assertions)]
         callback.debug(self, None);
 
@@ -115,33 +118,9 @@ impl Cpu {
             return Ok(());
         }
 
-        #[cfg(debug_assertions)]
-        let pc = self.registers.pc;
-
         self.current_opcode = self.fetch_data(callback);
-
-        let Some(instruction) = Instruction::get_by_opcode(self.current_opcode) else {
-            return Err(format!(
-                "Unknown instruction OPCODE: {:X}",
-                self.current_opcode,
-            ));
-        };
-
-        let fetched_data = AddressMode::fetch_data(self, instruction.get_address_mode(), callback);
-
-        #[cfg(debug_assertions)]
-        let inst_ctx = DebugCtx {
-            pc,
-            instruction: instruction.to_owned(),
-            opcode: self.current_opcode,
-            fetched_data: fetched_data.clone(),
-        };
-        #[cfg(debug_assertions)]
-        callback.debug(self, Some(inst_ctx));
-        callback.update_serial(self);
-
         let prev_enabling_ime = self.enabling_ime;
-        instruction.execute(self, callback, fetched_data);
+        DispatchTable::<C>::get_base()[self.current_opcode as usize](self, callback)?;
 
         if self.enabling_ime && prev_enabling_ime {
             // execute after next instruction when flag is changed
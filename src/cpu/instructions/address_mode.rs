@@ -6,6 +6,7 @@ impl AddressMode {
         self == AddressMode::LH_SPi8
     }
 
+    #[inline(always)]
     pub fn fetch_data(
         cpu: &mut Cpu,
         address_mode: AddressMode,
@@ -0,0 +1,158 @@
+use crate::cpu::instructions::misc::prefix::PrefixInstruction;
+use crate::cpu::instructions::INSTRUCTIONS_BY_OPCODES;
+use crate::cpu::instructions::{AddressMode, ExecutableInstruction, Instruction};
+use crate::cpu::{Cpu, CpuCallback};
+use std::marker::PhantomData;
+
+/// Executes an instruction after its opcode has been fetched.
+pub type Handler<C> = fn(&mut Cpu, &mut C) -> Result<(), String>;
+
+macro_rules! handlers {
+    ($handler:ident) => {
+        handlers!(
+            $handler;
+            0x00 0x01 0x02 0x03 0x04 0x05 0x06 0x07 0x08 0x09 0x0A 0x0B 0x0C 0x0D 0x0E 0x0F
+            0x10 0x11 0x12 0x13 0x14 0x15 0x16 0x17 0x18 0x19 0x1A 0x1B 0x1C 0x1D 0x1E 0x1F
+            0x20 0x21 0x22 0x23 0x24 0x25 0x26 0x27 0x28 0x29 0x2A 0x2B 0x2C 0x2D 0x2E 0x2F
+            0x30 0x31 0x32 0x33 0x34 0x35 0x36 0x37 0x38 0x39 0x3A 0x3B 0x3C 0x3D 0x3E 0x3F
+            0x40 0x41 0x42 0x43 0x44 0x45 0x46 0x47 0x48 0x49 0x4A 0x4B 0x4C 0x4D 0x4E 0x4F
+            0x50 0x51 0x52 0x53 0x54 0x55 0x56 0x57 0x58 0x59 0x5A 0x5B 0x5C 0x5D 0x5E 0x5F
+            0x60 0x61 0x62 0x63 0x64 0x65 0x66 0x67 0x68 0x69 0x6A 0x6B 0x6C 0x6D 0x6E 0x6F
+            0x70 0x71 0x72 0x73 0x74 0x75 0x76 0x77 0x78 0x79 0x7A 0x7B 0x7C 0x7D 0x7E 0x7F
+            0x80 0x81 0x82 0x83 0x84 0x85 0x86 0x87 0x88 0x89 0x8A 0x8B 0x8C 0x8D 0x8E 0x8F
+            0x90 0x91 0x92 0x93 0x94 0x95 0x96 0x97 0x98 0x99 0x9A 0x9B 0x9C 0x9D 0x9E 0x9F
+            0xA0 0xA1 0xA2 0xA3 0xA4 0xA5 0xA6 0xA7 0xA8 0xA9 0xAA 0xAB 0xAC 0xAD 0xAE 0xAF
+            0xB0 0xB1 0xB2 0xB3 0xB4 0xB5 0xB6 0xB7 0xB8 0xB9 0xBA 0xBB 0xBC 0xBD 0xBE 0xBF
+            0xC0 0xC1 0xC2 0xC3 0xC4 0xC5 0xC6 0xC7 0xC8 0xC9 0xCA 0xCB 0xCC 0xCD 0xCE 0xCF
+            0xD0 0xD1 0xD2 0xD3 0xD4 0xD5 0xD6 0xD7 0xD8 0xD9 0xDA 0xDB 0xDC 0xDD 0xDE 0xDF
+            0xE0 0xE1 0xE2 0xE3 0xE4 0xE5 0xE6 0xE7 0xE8 0xE9 0xEA 0xEB 0xEC 0xED 0xEE 0xEF
+            0xF0 0xF1 0xF2 0xF3 0xF4 0xF5 0xF6 0xF7 0xF8 0xF9 0xFA 0xFB 0xFC 0xFD 0xFE 0xFF
+        )
+    };
+    ($handler:ident; $($opcode:literal)*) => {
+        [$($handler::<C, $opcode>),*]
+    };
+}
+
+/// Handlers monomorphized per opcode and callback. The instruction is a constant in each of
+/// them, so decoding its address mode and type is folded away at compile time.
+pub struct DispatchTable<C>(PhantomData<C>);
+
+impl<C: CpuCallback> DispatchTable<C> {
+    pub const BASE: [Handler<C>; 0x100] = handlers!(execute_opcode);
+    /// Opcodes following the 0xCB prefix.
+    pub const CB: [Handler<C>; 0x100] = handlers!(execute_cb_opcode);
+
+    /// Promoted to a static, indexing the constant directly would copy the whole table.
+    pub fn get_base<'a>() -> &'a [Handler<C>; 0x100] {
+        &Self::BASE
+    }
+
+    pub fn get_cb<'a>() -> &'a [Handler<C>; 0x100] {
+        &Self::CB
+    }
+}
+
+fn execute_opcode<C: CpuCallback, const OPCODE: u8>(
+    cpu: &mut Cpu,
+    callback: &mut C,
+) -> Result<(), String> {
+    let instruction = const { INSTRUCTIONS_BY_OPCODES[OPCODE as usize] };
+
+    if let Instruction::Unknown(_) = instruction {
+        return Err(format!("Unknown instruction OPCODE: {:X}", OPCODE));
+    }
+
+    // the opcode is already fetched
+    #[cfg(debug_assertions)]
+    let pc = cpu.registers.pc.wrapping_sub(1);
+    let fetched_data = AddressMode::fetch_data(cpu, instruction.get_address_mode(), callback);
+
+    #[cfg(debug_assertions)]
+    callback.debug(
+        cpu,
+        Some(crate::cpu::DebugCtx {
+            pc,
+            instruction,
+            opcode: OPCODE,
+            fetched_data: fetched_data.clone(),
+        }),
+    );
+    callback.update_serial(cpu);
+
+    if let Instruction::Prefix(_) = instruction {
+        return DispatchTable::<C>::get_cb()[fetched_data.value as usize](cpu, callback);
+    }
+
+    instruction.execute(cpu, callback, fetched_data);
+
+    Ok(())
+}
+
+fn execute_cb_opcode<C: CpuCallback, const OPCODE: u8>(
+    cpu: &mut Cpu,
+    callback: &mut C,
+) -> Result<(), String> {
+    PrefixInstruction::execute_cb(cpu, callback, OPCODE);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::Bus;
+    use crate::cpu::CounterCpuCallback;
+
+    fn new_cpu(opcode: u8) -> Cpu {
+        let mut cpu = Cpu::new(Bus::with_bytes(vec![0x5A; 0x10000]));
+        cpu.registers.pc = 0x100;
+        cpu.registers.sp = 0xC000;
+        cpu.bus.write(0x100, opcode);
+
+        cpu
+    }
+
+    #[test]
+    fn test_dispatch_matches_decoding() {
+        for (opcode, instruction) in INSTRUCTIONS_BY_OPCODES.iter().enumerate() {
+            if let Instruction::Unknown(_) = instruction {
+                continue;
+            }
+
+            let mut expected_cpu = new_cpu(opcode as u8);
+            let mut expected = CounterCpuCallback::default();
+            expected_cpu.fetch_data(&mut expected);
+            let fetched_data = AddressMode::fetch_data(
+                &mut expected_cpu,
+                instruction.get_address_mode(),
+                &mut expected,
+            );
+            instruction.execute(&mut expected_cpu, &mut expected, fetched_data);
+
+            let mut cpu = new_cpu(opcode as u8);
+            let mut actual = CounterCpuCallback::default();
+            cpu.fetch_data(&mut actual);
+            DispatchTable::get_base()[opcode](&mut cpu, &mut actual).unwrap();
+
+            assert_eq!(
+                format!("{:?}", expected_cpu.registers),
+                format!("{:?}", cpu.registers),
+                "{:02X}",
+                opcode
+            );
+            assert_eq!(
+                expected.m_cycles_count, actual.m_cycles_count,
+                "{:02X}",
+                opcode
+            );
+        }
+    }
+
+    #[test]
+    fn test_dispatch_unknown() {
+        let mut cpu = new_cpu(0xD3);
+
+        assert!(cpu.step(&mut CounterCpuCallback::default()).is_err());
+    }
+}
@@ -84,6 +84,7 @@ pub enum Instruction {
 }
 
 impl ExecutableInstruction for Instruction {
+    #[inline(always)]
     fn execute(&self, cpu: &mut Cpu, callback: &mut impl CpuCallback, fetched_data: FetchedData) {
         match self {
             Instruction::Unknown(opcode) => {
@@ -126,6 +127,7 @@ impl ExecutableInstruction for Instruction {
         }
     }
 
+    #[inline(always)]
     fn get_address_mode(&self) -> AddressMode {
         match self {
             Instruction::Unknown(opcode) => panic!(
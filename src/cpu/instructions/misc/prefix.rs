@@ -28,8 +28,19 @@ pub fn decode_reg(reg: u16) -> Option<RegisterType> {
 
 impl ExecutableInstruction for PrefixInstruction {
     fn execute(&self, cpu: &mut Cpu, callback: &mut impl CpuCallback, fetched_data: FetchedData) {
-        let op = fetched_data.value;
-        let reg = decode_reg(op & 0b111);
+        PrefixInstruction::execute_cb(cpu, callback, fetched_data.value as u8);
+    }
+
+    fn get_address_mode(&self) -> AddressMode {
+        AddressMode::D8
+    }
+}
+
+impl PrefixInstruction {
+    /// Executes the opcode following the prefix.
+    #[inline(always)]
+    pub fn execute_cb(cpu: &mut Cpu, callback: &mut impl CpuCallback, op: u8) {
+        let reg = decode_reg(op as u16 & 0b111);
 
         let Some(reg) = reg else {
             return;
@@ -174,11 +185,5 @@ impl ExecutableInstruction for PrefixInstruction {
                 unimplemented!();
             }
         }
-
-        callback.m_cycles(1, &mut cpu.bus);
-    }
-
-    fn get_address_mode(&self) -> AddressMode {
-        AddressMode::D8
     }
 }
@@ -2,6 +2,7 @@ pub mod address_mode;
 mod arithmetic;
 mod bitwise;
 pub mod condition_type;
+pub mod dispatch;
 pub mod instruction;
 mod interrupt;
 mod jump;
@@ -1,5 +1,6 @@
 use crate::cpu::instructions::RegisterType;
 use crate::{get_bit_flag, set_bit};
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
 const ZERO_FLAG_BYTE_POSITION: u8 = 7;
@@ -7,7 +8,7 @@ const SUBTRACT_FLAG_BYTE_POSITION: u8 = 6;
 const HALF_CARRY_FLAG_BYTE_POSITION: u8 = 5;
 const CARRY_FLAG_BYTE_POSITION: u8 = 4;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Registers {
     pub a: u8,
     pub flags: Flags,
@@ -21,7 +22,7 @@ pub struct Registers {
     pub pc: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Flags {
     pub byte: u8,
 }
@@ -1,19 +1,33 @@
+use crate::apu::Apu;
+use crate::archive::{is_archive_path, list_archive_roms, read_rom_file};
 use crate::auxiliary::clock::Clock;
 use crate::auxiliary::joypad::Joypad;
 use crate::bus::Bus;
-use crate::cart::Cart;
+use crate::capture::audio_recorder::AudioRecorder;
+use crate::capture::get_capture_path;
+use crate::capture::screenshot::{save_screenshot, ScreenshotFormat};
+use crate::capture::video_recorder::VideoRecorder;
+use crate::cart::camera_image::create_image_source;
+use crate::cart::{Cart, CartData};
+use crate::cheats::Cheats;
 use crate::config::Config;
 use crate::cpu::{Cpu, CpuCallback, DebugCtx};
 use crate::debugger::{CpuLogType, Debugger};
+use crate::gamedb::{find_override, Dat};
 use crate::mbc::MbcVariant;
+use crate::patch::{apply_patch_file, find_patch};
 use crate::ppu::Ppu;
 use crate::ui::events::{UiEvent, UiEventHandler};
+use crate::ui::menu::{Menu, MenuAction};
+use crate::ui::ui::into_pallet;
 use crate::ui::Ui;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::{fs, thread};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct EmuSaveState {
     pub clock: Clock,
     pub cpu_without_bus: Cpu,
@@ -21,6 +35,40 @@ pub struct EmuSaveState {
     pub cart_mbc: Option<MbcVariant>,
 }
 
+impl EmuSaveState {
+    /// Slot file of the cart, the ROM hash keeps patched ROMs apart.
+    pub fn path(cart_data: &CartData, slot: usize) -> PathBuf {
+        let title: String = cart_data
+            .get_title()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let hash = crc32fast::hash(&cart_data.bytes);
+        let file_name = format!("{}_{:08X}_{}.state", title, hash, slot);
+
+        Config::save_dir().join("states").join(file_name)
+    }
+
+    pub fn save_file(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let bytes = bincode::serde::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn load_file(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        let (state, _) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+            .map_err(|e| format!("Invalid save state {}: {}", path.display(), e))?;
+
+        Ok(state)
+    }
+}
+
 pub struct Emu {
     pub clock: Clock,
     pub debugger: Option<Debugger>,
@@ -35,6 +83,23 @@ pub struct EmuCtx {
     pub prev_frame: usize,
     pub last_fps_timestamp: Duration,
     pub rewind_buffer: VecDeque<EmuSaveState>,
+    pub cheats: Cheats,
+    pub audio_recorder: Option<AudioRecorder>,
+    pub video_recorder: Option<VideoRecorder>,
+    /// Saves the next complete frame to PNG.
+    pub is_screenshot_requested: bool,
+    pub menu: Menu,
+    /// Menu actions which need the CPU or the UI, applied by the run loop.
+    pub menu_actions: Vec<MenuAction>,
+    /// Cart path and the patches passed with it, other carts use patches found next to them.
+    pub patches: Option<(String, Vec<PathBuf>)>,
+    /// Loaded from `config.dat_path`.
+    pub dat: Option<Dat>,
+    /// Image source of carts with a camera, a gray image without it.
+    pub camera_images: Vec<PathBuf>,
+    /// Audio recording path and `record_channels` from the command line, the recording
+    /// starts with the first cart.
+    pub pending_audio_recording: Option<(PathBuf, bool)>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -56,15 +121,108 @@ pub enum RunMode {
 
 impl EmuCtx {
     pub fn new(config: Config) -> EmuCtx {
+        let dat = config.dat_path.as_ref().and_then(|path| {
+            Dat::from_file(path)
+                .map_err(|e| eprintln!("Failed to load DAT {}: {}", path, e))
+                .ok()
+        });
+
         Self {
             state: EmuState::WaitCart,
             config,
             prev_frame: 0,
             last_fps_timestamp: Default::default(),
             rewind_buffer: Default::default(),
+            cheats: Default::default(),
+            audio_recorder: None,
+            video_recorder: None,
+            is_screenshot_requested: false,
+            menu: Default::default(),
+            menu_actions: Vec::new(),
+            patches: None,
+            dat,
+            camera_images: Vec::new(),
+            pending_audio_recording: None,
         }
     }
 
+    /// Carts without a camera ignore the images.
+    pub fn apply_camera_images(&self, cart: &mut Cart) -> Result<(), String> {
+        if !self.camera_images.is_empty() {
+            let source = create_image_source(&self.camera_images)?;
+            let _ = cart.set_image_source(source);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_patches(&self, cart_path: &str) -> &[PathBuf] {
+        match &self.patches {
+            Some((path, patches)) if path == cart_path => patches,
+            _ => &[],
+        }
+    }
+
+    /// Records the channels to their own files too when `record_channels` is set.
+    pub fn start_audio_recording(
+        &mut self,
+        path: &Path,
+        record_channels: bool,
+        apu: &mut Apu,
+    ) -> Result<(), String> {
+        let recorder = AudioRecorder::new(path, record_channels, apu)?;
+        println!("Recording audio to {}", path.display());
+        self.audio_recorder = Some(recorder);
+
+        Ok(())
+    }
+
+    pub fn stop_audio_recording(&mut self, bus: &mut Bus) -> Result<(), String> {
+        if let Some(recorder) = self.audio_recorder.take() {
+            let path = recorder.get_path().to_path_buf();
+            recorder.finish(&mut bus.io.apu)?;
+            println!("Audio saved to {}", path.display());
+        }
+
+        Ok(())
+    }
+
+    pub fn start_video_recording(&mut self, path: &Path) -> Result<(), String> {
+        let graphics = &self.config.graphics;
+        let palette = into_pallet(&graphics.pallets[graphics.selected_pallet_idx].hex_colors);
+        let recorder = VideoRecorder::new(path, self.config.audio.sample_rate, &palette)?;
+        println!("Recording video to {}", path.display());
+        self.video_recorder = Some(recorder);
+
+        Ok(())
+    }
+
+    pub fn stop_video_recording(&mut self) -> Result<(), String> {
+        if let Some(recorder) = self.video_recorder.take() {
+            let path = recorder.get_path().to_path_buf();
+            let frames = recorder.get_frames_count();
+            recorder.finish()?;
+            println!("Video saved to {} ({} frames)", path.display(), frames);
+        }
+
+        Ok(())
+    }
+
+    pub fn save_screenshot(&mut self, bus: &Bus, ppu: &Ppu) -> Result<(), String> {
+        let path = get_capture_path(bus, "screenshots", "png");
+        let format = match self.config.graphics.screenshot_format {
+            ScreenshotFormat::Scaled(_) => {
+                ScreenshotFormat::Scaled(self.config.graphics.scale as u32)
+            }
+            format => format,
+        };
+
+        save_screenshot(&ppu.pipeline.buffer, &path, format)?;
+        println!("Screenshot saved to {}", path.display());
+
+        Ok(())
+    }
+
     pub fn reset(&mut self) {
         self.prev_frame = 0;
         self.last_fps_timestamp = Default::default();
@@ -101,10 +259,16 @@ impl CpuCallback for Emu {
 }
 
 impl UiEventHandler for EmuCtx {
-    fn on_event(&mut self, _bus: &mut Bus, event: UiEvent) {
+    fn on_event(&mut self, bus: &mut Bus, event: UiEvent) {
         match event {
             UiEvent::Quit => self.state = EmuState::Quit,
-            UiEvent::DropFile(path) => self.state = EmuState::LoadCart(path),
+            UiEvent::DropFile(path) => match list_archive_roms(&path) {
+                // let the menu ask which one to load
+                Ok(roms) if is_archive_path(Path::new(&path)) && roms.len() > 1 => {
+                    self.menu.open_archive(&path, roms)
+                }
+                _ => self.state = EmuState::LoadCart(path),
+            },
             UiEvent::Pause => {
                 if self.state == EmuState::Paused {
                     self.state = EmuState::Running(RunMode::Normal);
@@ -119,8 +283,65 @@ impl UiEventHandler for EmuCtx {
             }
             UiEvent::ConfigChanged(config) => self.config.graphics = config,
             UiEvent::Mode(mode) => self.state = EmuState::Running(mode),
+            UiEvent::ToggleCheats => {
+                self.cheats.toggle(bus);
+                println!("Cheats enabled: {}", self.cheats.enabled);
+            }
+            UiEvent::ToggleAudioRecording => {
+                let result = if self.audio_recorder.is_some() {
+                    self.stop_audio_recording(bus)
+                } else {
+                    let path = get_capture_path(bus, "recordings", "wav");
+                    let record_channels = self.config.audio.record_channels;
+                    self.start_audio_recording(&path, record_channels, &mut bus.io.apu)
+                };
+
+                if let Err(err) = result {
+                    eprintln!("Audio recording failed: {}", err);
+                }
+            }
+            UiEvent::ToggleVideoRecording => {
+                let result = if self.video_recorder.is_some() {
+                    self.stop_video_recording()
+                } else {
+                    let extension = self.config.graphics.video_format.get_extension();
+                    let path = get_capture_path(bus, "recordings", extension);
+                    self.start_video_recording(&path)
+                };
+
+                if let Err(err) = result {
+                    eprintln!("Video recording failed: {}", err);
+                }
+            }
+            UiEvent::Screenshot => self.is_screenshot_requested = true,
+            UiEvent::ToggleMenu => {
+                if self.menu.is_open {
+                    self.menu.close();
+                } else {
+                    bus.io.joypad.release_all();
+                    self.menu.used_slots = std::array::from_fn(|slot| {
+                        EmuSaveState::path(&bus.cart.data, slot).exists()
+                    });
+                    self.menu.cheats = self
+                        .cheats
+                        .cheats
+                        .iter()
+                        .map(|cheat| (cheat.name.clone(), cheat.enabled))
+                        .collect();
+                    self.menu.open();
+                }
+            }
+            UiEvent::MenuKey(key) => match self.menu.handle_key(&key, &mut self.config) {
+                MenuAction::None | MenuAction::Close => (),
+                MenuAction::LoadCart(path) => self.state = EmuState::LoadCart(path),
+                action => self.menu_actions.push(action),
+            },
         }
     }
+
+    fn is_menu_open(&self) -> bool {
+        self.menu.is_open
+    }
 }
 
 impl Emu {
@@ -130,7 +351,12 @@ impl Emu {
         Ok(Self {
             clock: Clock::with_ppu(ppu),
             debugger: Some(Debugger::new(CpuLogType::None, false)),
-            ui: Ui::new(config.graphics.clone(), false)?,
+            ui: Ui::new(
+                config.graphics.clone(),
+                &config.audio,
+                config.input.clone(),
+                false,
+            )?,
             ctx: EmuCtx::new(config),
         })
     }
@@ -147,6 +373,15 @@ impl Emu {
         let mut cpu = Cpu::new(Bus::with_bytes(vec![]));
 
         loop {
+            if self.ctx.menu.is_open {
+                let view = self.ctx.menu.get_view(&self.ctx.config);
+                self.ui.draw_menu(&view);
+                self.ui.handle_events(&mut cpu.bus, &mut self.ctx);
+                self.apply_menu_actions(&mut cpu)?;
+                thread::sleep(Duration::from_millis(16));
+                continue;
+            }
+
             if self.ctx.state == EmuState::Paused || self.ctx.state == EmuState::WaitCart {
                 self.ui.draw_text("DROP FILE");
                 self.ui.handle_events(&mut cpu.bus, &mut self.ctx);
@@ -155,21 +390,43 @@ impl Emu {
             }
 
             if self.ctx.state == EmuState::Quit {
+                self.ctx.stop_audio_recording(&mut cpu.bus)?;
+                self.ctx.stop_video_recording()?;
                 self.ctx.config.save().map_err(|e| e.to_string())?;
                 break;
             }
 
             if let EmuState::LoadCart(path) = &self.ctx.state {
-                let cart = read_cart(path).map_err(|e| e.to_string())?;
+                let mut cart =
+                    read_cart(path, self.ctx.get_patches(path)).map_err(|e| e.to_string())?;
+
+                self.ctx.apply_camera_images(&mut cart)?;
 
                 let mut bus = Bus::new(cart);
+                bus.io.apu.set_sample_rate(self.ctx.config.audio.sample_rate);
+                self.ctx.cheats = Cheats::load(&bus.cart.data).unwrap_or_else(|e| {
+                    eprintln!("Failed to load cheats: {}", e);
+                    Cheats::default()
+                });
+                self.ctx.cheats.install(&mut bus);
+                self.ui
+                    .set_title(&get_window_title(&bus.cart, self.ctx.dat.as_ref()));
                 //bus.io.apu.buffer = self.ui.audio_buffer.clone();
                 bus.io.lcd.set_pallet(self.ui.curr_palette);
                 cpu = Cpu::new(bus);
 
                 self.ctx.config.last_cart_path = Some(path.to_owned());
+                self.ctx.config.add_recent_cart(path);
                 self.ctx.state = EmuState::Running(RunMode::Normal);
                 self.ctx.reset();
+
+                let apu = &mut cpu.bus.io.apu;
+
+                if let Some((path, record_channels)) = self.ctx.pending_audio_recording.take() {
+                    self.ctx.start_audio_recording(&path, record_channels, apu)?;
+                } else if let Some(recorder) = self.ctx.audio_recorder.as_mut() {
+                    recorder.attach(apu);
+                }
             }
 
             if let EmuState::Running(RunMode::Rewind) = &self.ctx.state {
@@ -189,8 +446,12 @@ impl Emu {
 
             let ppu = self.clock.ppu.as_mut().unwrap();
 
+            let sync_to_audio = self.ctx.config.audio.sync_to_audio
+                && self.ctx.state == EmuState::Running(RunMode::Normal);
+
             if let EmuState::Running(mode) = &self.ctx.state {
                 match mode {
+                    RunMode::Normal if sync_to_audio => ppu.clear_fps_limit(),
                     RunMode::Normal => ppu.set_fps_limit(self.ctx.config.graphics.fps_limit),
                     RunMode::Slow => ppu.set_fps_limit(
                         self.ctx.config.graphics.fps_limit * self.ctx.config.emulation.slow_speed
@@ -205,10 +466,37 @@ impl Emu {
             }
 
             if self.ctx.prev_frame != ppu.current_frame {
+                self.ctx.cheats.apply(&mut cpu.bus);
                 self.ui.draw(ppu, &cpu.bus);
+
+                if let Some(recorder) = self.ctx.video_recorder.as_mut() {
+                    if let Err(err) = recorder.capture_frame(&ppu.pipeline.buffer) {
+                        eprintln!("Video recording failed: {}", err);
+
+                        if let Err(err) = self.ctx.stop_video_recording() {
+                            eprintln!("Failed to save video: {}", err);
+                        }
+                    }
+                }
+
+                if self.ctx.is_screenshot_requested {
+                    self.ctx.is_screenshot_requested = false;
+
+                    if let Err(err) = self.ctx.save_screenshot(&cpu.bus, ppu) {
+                        eprintln!("Screenshot failed: {}", err);
+                    }
+                }
             }
 
-            self.ui.audio.play(&mut cpu.bus.io.apu)?;
+            if let Some(recorder) = self.ctx.audio_recorder.as_mut() {
+                recorder.capture(&mut cpu.bus.io.apu)?;
+            }
+
+            if let Some(recorder) = self.ctx.video_recorder.as_mut() {
+                recorder.capture_audio(&cpu.bus.io.apu);
+            }
+
+            self.ui.audio.play(&mut cpu.bus.io.apu, sync_to_audio)?;
 
             self.ctx.prev_frame = ppu.current_frame;
 
@@ -224,6 +512,59 @@ impl Emu {
         Ok(())
     }
 
+    fn apply_menu_actions(&mut self, cpu: &mut Cpu) -> Result<(), String> {
+        for action in std::mem::take(&mut self.ctx.menu_actions) {
+            match action {
+                MenuAction::SaveState(slot) => {
+                    let path = EmuSaveState::path(&cpu.bus.cart.data, slot);
+
+                    match self.save_state(cpu).save_file(&path) {
+                        Ok(()) => println!("State saved to {}", path.display()),
+                        Err(err) => eprintln!("Failed to save state: {}", err),
+                    }
+                }
+                MenuAction::LoadState(slot) => {
+                    let path = EmuSaveState::path(&cpu.bus.cart.data, slot);
+
+                    match EmuSaveState::load_file(&path) {
+                        Ok(state) => {
+                            self.load_state(cpu, state);
+                            // image sources are not in save states
+                            self.ctx.apply_camera_images(&mut cpu.bus.cart)?;
+                        }
+                        Err(err) => eprintln!("Failed to load state: {}", err),
+                    }
+                }
+                MenuAction::ToggleCheat(idx) => {
+                    let Some((_, enabled)) = self.ctx.menu.cheats.get(idx) else {
+                        continue;
+                    };
+                    let result = self
+                        .ctx
+                        .cheats
+                        .set_cheat_enabled(idx, *enabled)
+                        .and_then(|()| self.ctx.cheats.save(&cpu.bus.cart.data));
+                    self.ctx.cheats.install(&mut cpu.bus);
+
+                    if let Err(err) = result {
+                        eprintln!("Failed to update cheats: {}", err);
+                    }
+                }
+                MenuAction::GraphicsChanged => {
+                    let config = self.ctx.config.graphics.clone();
+                    self.ui.apply_config(&mut cpu.bus, config)?;
+                    // window geometry follows the scale
+                    self.ctx.config.graphics = self.ui.config.clone();
+                }
+                MenuAction::AudioChanged => self.ui.audio.set_volume(self.ctx.config.audio.volume),
+                MenuAction::InputChanged => self.ui.input = self.ctx.config.input.clone(),
+                MenuAction::None | MenuAction::Close | MenuAction::LoadCart(_) => (),
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn save_state(&self, cpu: &Cpu) -> EmuSaveState {
         EmuSaveState {
             clock: self.clock.clone(),
@@ -239,23 +580,62 @@ impl Emu {
         state_cpu.bus.io.joypad = Joypad::default(); // reset controls
         state_cpu.bus.cart.mbc = save_state.cart_mbc; // reconstruct cart
         state_cpu.bus.cart.data = cpu.bus.cart.data.clone();
+        state_cpu.bus.cart.rom_patches = cpu.bus.cart.rom_patches.clone();
 
         *cpu = state_cpu;
         self.clock = save_state.clock;
+
+        if let Some(recorder) = self.ctx.audio_recorder.as_mut() {
+            recorder.attach(&mut cpu.bus.io.apu);
+        }
+
         self.ctx.reset();
     }
 }
 
-pub fn read_cart(file: &str) -> Result<Cart, String> {
-    let bytes = read_bytes(file).map_err(|e| e.to_string())?;
-    let cart = Cart::new(bytes).map_err(|e| e.to_string())?;
-    _ = print_cart(&cart).map_err(|e| println!("Failed to print cart: {}", e));
+/// Reads a ROM file or a ROM packed in an archive, see `read_rom_file`, and applies the
+/// patches in memory. Without patches given, the one named after the ROM is applied.
+pub fn read_cart(file: &str, patches: &[PathBuf]) -> Result<Cart, String> {
+    let mut rom = read_rom_file(file)?;
+    let patches = if patches.is_empty() {
+        find_patch(file, &rom).into_iter().collect()
+    } else {
+        patches.to_vec()
+    };
+
+    for path in patches {
+        apply_patch_file(&mut rom, &path)?;
+    }
+
+    let cart = match find_override(&rom.bytes) {
+        Some(cart_override) => {
+            println!("Override: {}", cart_override.get_description());
+            Cart::with_override(rom.bytes, cart_override)?
+        }
+        None => Cart::new(rom.bytes)?,
+    };
+    _ = print_cart(&cart, &rom.name).map_err(|e| println!("Failed to print cart: {}", e));
 
     Ok(cart)
 }
 
-fn print_cart(cart: &Cart) -> Result<(), String> {
+/// The DAT name of the cart, or the header title when it isn't found.
+fn get_window_title(cart: &Cart, dat: Option<&Dat>) -> String {
+    let name = dat
+        .and_then(|dat| dat.identify(&cart.data.bytes))
+        .map(|dat_match| {
+            println!("DAT Name: {} ({:?})", dat_match.game, dat_match.status);
+            dat_match.game
+        })
+        .or_else(|| cart.data.get_title().ok())
+        .unwrap_or_default();
+
+    format!("GMBoy - {}", name)
+}
+
+fn print_cart(cart: &Cart, file_name: &str) -> Result<(), String> {
     println!("Cart Loaded:");
+    println!("\t File           : {}", file_name);
     println!("\t Title          : {}", cart.data.get_title()?);
     println!("\t Type           : {:?}", cart.data.get_cart_type()?);
     println!("\t ROM Size       : {:?}", cart.data.get_rom_size()?);
@@ -273,3 +653,54 @@ pub fn read_bytes(file_path: &str) -> Result<Vec<u8>, String> {
 
     fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_state_file() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0147] = 0x03; // MBC1+RAM+BATTERY
+        rom[0x0149] = 0x02;
+        let mut cpu = Cpu::new(Bus::new(Cart::new(rom).unwrap()));
+        cpu.bus.write(0x0000, 0x0A);
+        cpu.bus.write(0xA000, 0x42);
+        cpu.bus.write(0xC123, 0x24);
+        let state = EmuSaveState {
+            clock: Clock::with_ppu(Ppu::default()),
+            cpu_without_bus: cpu.clone_without_bus(),
+            bus_without_cart: cpu.bus.clone_without_cart(),
+            cart_mbc: cpu.bus.cart.mbc.clone(),
+        };
+
+        let path = std::env::temp_dir().join("gmboy_test_save_state.state");
+        state.save_file(&path).unwrap();
+        let loaded = EmuSaveState::load_file(&path).unwrap();
+        fs::write(&path, [0xFF; 4]).unwrap();
+        assert!(EmuSaveState::load_file(&path).is_err());
+        fs::remove_file(&path).unwrap();
+
+        let mut bus = loaded.bus_without_cart;
+        bus.cart.data = cpu.bus.cart.data.clone();
+        bus.cart.mbc = loaded.cart_mbc;
+        assert_eq!(bus.read(0xC123), 0x24);
+        assert_eq!(bus.read(0xA000), 0x42);
+        assert!(loaded.clock.ppu.is_some());
+    }
+
+    #[test]
+    fn test_save_state_path() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0134..0x0139].copy_from_slice(b"TE ST");
+        let data = CartData::new(rom.clone());
+        rom[0x0200] = 1;
+        let patched = CartData::new(rom);
+
+        let path = EmuSaveState::path(&data, 2);
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("TE_ST_") && name.ends_with("_2.state"), "{}", name);
+        assert_ne!(path, EmuSaveState::path(&patched, 2));
+        assert_ne!(path, EmuSaveState::path(&data, 1));
+    }
+}
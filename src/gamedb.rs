@@ -0,0 +1,319 @@
+use crate::cart::header::{CartType, RamSize};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DumpStatus {
+    Good,
+    Verified,
+    /// Known to be damaged, listed so it can be told apart from the good dump.
+    BadDump,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatRom {
+    /// Canonical name of the game, like `Tetris (World) (Rev 1)`.
+    pub game: String,
+    pub name: String,
+    pub size: usize,
+    pub crc32: u32,
+    /// Lowercase hex, not all DATs have it.
+    pub sha1: Option<String>,
+    pub status: DumpStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatMatch {
+    pub game: String,
+    pub rom_name: String,
+    /// Size in the DAT.
+    pub size: usize,
+    pub status: DumpStatus,
+    /// File size when the ROM is a good dump padded or repeated to a larger size.
+    pub overdump_size: Option<usize>,
+}
+
+impl DatMatch {
+    fn new(rom: &DatRom, overdump_size: Option<usize>) -> Self {
+        Self {
+            game: rom.game.clone(),
+            rom_name: rom.name.clone(),
+            size: rom.size,
+            status: rom.status,
+            overdump_size,
+        }
+    }
+
+    pub fn is_bad_dump(&self) -> bool {
+        self.status == DumpStatus::BadDump
+    }
+
+    pub fn is_overdump(&self) -> bool {
+        self.overdump_size.is_some()
+    }
+}
+
+/// ROM database in the No-Intro or Redump XML DAT format.
+#[derive(Debug, Clone, Default)]
+pub struct Dat {
+    pub name: String,
+    pub roms: Vec<DatRom>,
+}
+
+impl Dat {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let xml = fs::read_to_string(path).map_err(|e| format!("Failed to read DAT: {}", e))?;
+
+        Self::parse(&xml)
+    }
+
+    pub fn parse(xml: &str) -> Result<Self, String> {
+        let doc = roxmltree::Document::parse(xml).map_err(|e| format!("Invalid DAT: {}", e))?;
+        let mut dat = Dat::default();
+
+        for node in doc.root_element().children().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "header" => {
+                    dat.name = get_child_text(node, "name").unwrap_or_default();
+                }
+                // older DATs use `machine`
+                "game" | "machine" => {
+                    let game = node.attribute("name").unwrap_or_default();
+
+                    for rom in node.children().filter(|n| n.has_tag_name("rom")) {
+                        if let Some(rom) = parse_rom(game, rom)? {
+                            dat.roms.push(rom);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(dat)
+    }
+
+    /// Looks the ROM up by CRC32 and size, and by SHA-1 when the DAT has it. A ROM with
+    /// no entry is also checked for being an overdump of a smaller one.
+    pub fn identify(&self, bytes: &[u8]) -> Option<DatMatch> {
+        if let Some(rom) = self.find(bytes) {
+            return Some(DatMatch::new(rom, None));
+        }
+
+        let mut sizes: Vec<usize> = self
+            .roms
+            .iter()
+            .map(|rom| rom.size)
+            .filter(|size| *size > 0 && *size < bytes.len())
+            .collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        sizes.dedup();
+
+        sizes.into_iter().find_map(|size| {
+            self.find(&bytes[..size])
+                .map(|rom| DatMatch::new(rom, Some(bytes.len())))
+        })
+    }
+
+    fn find(&self, bytes: &[u8]) -> Option<&DatRom> {
+        let crc32 = crc32fast::hash(bytes);
+        let mut sha1 = None;
+
+        self.roms
+            .iter()
+            .filter(|rom| rom.crc32 == crc32 && rom.size == bytes.len())
+            .find(|rom| match &rom.sha1 {
+                Some(expected) => sha1.get_or_insert_with(|| get_sha1(bytes)) == expected,
+                None => true,
+            })
+    }
+}
+
+/// Lowercase hex SHA-1 as written in DATs.
+pub fn get_sha1(bytes: &[u8]) -> String {
+    Sha1::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn parse_rom(game: &str, node: roxmltree::Node) -> Result<Option<DatRom>, String> {
+    let status = match node.attribute("status") {
+        Some("baddump") => DumpStatus::BadDump,
+        Some("verified") => DumpStatus::Verified,
+        // no checksums to match against
+        Some("nodump") => return Ok(None),
+        _ => DumpStatus::Good,
+    };
+    let name = node.attribute("name").unwrap_or_default();
+    let (Some(size), Some(crc32)) = (node.attribute("size"), node.attribute("crc")) else {
+        return Ok(None);
+    };
+
+    Ok(Some(DatRom {
+        game: game.to_string(),
+        name: name.to_string(),
+        size: size
+            .parse()
+            .map_err(|_| format!("Invalid size of {}: {}", name, size))?,
+        crc32: u32::from_str_radix(crc32, 16)
+            .map_err(|_| format!("Invalid CRC32 of {}: {}", name, crc32))?,
+        sha1: node.attribute("sha1").map(|sha1| sha1.to_lowercase()),
+        status,
+    }))
+}
+
+fn get_child_text(node: roxmltree::Node, name: &str) -> Option<String> {
+    node.children()
+        .find(|n| n.has_tag_name(name))
+        .and_then(|n| n.text())
+        .map(|text| text.trim().to_string())
+}
+
+/// Replaces header values a cart is known to lie about.
+#[derive(Debug, Clone, Copy)]
+pub struct CartOverride {
+    /// CRC32 of the whole ROM.
+    pub crc32: u32,
+    pub name: &'static str,
+    pub cart_type: Option<CartType>,
+    pub ram_size: Option<RamSize>,
+}
+
+impl CartOverride {
+    pub fn get_description(&self) -> String {
+        let mut changes = Vec::new();
+
+        if let Some(cart_type) = self.cart_type {
+            changes.push(format!("type {:?}", cart_type));
+        }
+
+        if let Some(ram_size) = self.ram_size {
+            changes.push(format!("RAM size {:?}", ram_size));
+        }
+
+        format!("{}: {}", self.name, changes.join(", "))
+    }
+}
+
+/// Entries are added once their CRC32 is checked against a No-Intro DAT. Model overrides wait
+/// for CGB support.
+pub const CART_OVERRIDES: &[CartOverride] = &[];
+
+pub fn find_override(bytes: &[u8]) -> Option<&'static CartOverride> {
+    find_override_in(CART_OVERRIDES, bytes)
+}
+
+pub fn find_override_in<'a>(
+    overrides: &'a [CartOverride],
+    bytes: &[u8],
+) -> Option<&'a CartOverride> {
+    if overrides.is_empty() {
+        return None;
+    }
+
+    let crc32 = crc32fast::hash(bytes);
+
+    overrides.iter().find(|o| o.crc32 == crc32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart::Cart;
+    use crate::mbc::MbcVariant;
+
+    fn create_dat(good: &[u8], bad: &[u8]) -> String {
+        format!(
+            r#"<?xml version="1.0"?>
+<datafile>
+    <header><name>Nintendo - Game Boy</name></header>
+    <game name="Good (World)">
+        <description>Good (World)</description>
+        <rom name="Good (World).gb" size="{}" crc="{:08X}" sha1="{}" status="verified"/>
+    </game>
+    <game name="Bad (Japan)">
+        <rom name="Bad (Japan).gb" size="{}" crc="{:08x}" status="baddump"/>
+    </game>
+    <game name="Lost (USA)">
+        <rom name="Lost (USA).gb" size="32768" status="nodump"/>
+    </game>
+</datafile>"#,
+            good.len(),
+            crc32fast::hash(good),
+            get_sha1(good).to_uppercase(),
+            bad.len(),
+            crc32fast::hash(bad),
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        let dat = Dat::parse(&create_dat(&[1; 64], &[2; 64])).unwrap();
+
+        assert_eq!(dat.name, "Nintendo - Game Boy");
+        assert_eq!(dat.roms.len(), 2);
+        assert_eq!(dat.roms[0].game, "Good (World)");
+        assert_eq!(dat.roms[0].status, DumpStatus::Verified);
+        assert_eq!(dat.roms[1].status, DumpStatus::BadDump);
+        assert!(Dat::parse("<datafile>").is_err());
+    }
+
+    #[test]
+    fn test_identify() {
+        let good = vec![1; 64];
+        let bad = vec![2; 64];
+        let dat = Dat::parse(&create_dat(&good, &bad)).unwrap();
+
+        let found = dat.identify(&good).unwrap();
+        assert_eq!(found.game, "Good (World)");
+        assert!(!found.is_bad_dump() && !found.is_overdump());
+
+        assert!(dat.identify(&bad).unwrap().is_bad_dump());
+        assert_eq!(dat.identify(&[3; 64]), None);
+
+        let mut overdump = good.clone();
+        overdump.extend_from_slice(&[0xFF; 64]);
+        let found = dat.identify(&overdump).unwrap();
+        assert_eq!(found.game, "Good (World)");
+        assert_eq!(found.overdump_size, Some(128));
+    }
+
+    #[test]
+    fn test_identify_sha1_mismatch() {
+        let good = vec![1; 64];
+        let mut dat = Dat::parse(&create_dat(&good, &[2; 64])).unwrap();
+        dat.roms[0].sha1 = Some(get_sha1(&[0; 64]));
+
+        assert_eq!(dat.identify(&good), None);
+    }
+
+    #[test]
+    fn test_find_override() {
+        let rom = vec![5; 0x8000];
+        let overrides = [CartOverride {
+            crc32: crc32fast::hash(&rom),
+            name: "Test",
+            cart_type: Some(CartType::Mbc1),
+            ram_size: None,
+        }];
+
+        let found = find_override_in(&overrides, &rom).unwrap();
+        assert_eq!(found.get_description(), "Test: type Mbc1");
+        assert!(find_override_in(&overrides, &[0; 0x8000]).is_none());
+
+        let cart = Cart::with_override(rom, found).unwrap();
+        assert!(matches!(cart.mbc, Some(MbcVariant::Mbc1(_))));
+    }
+
+    #[test]
+    fn test_find_override_builtin() {
+        assert!(find_override(&[0; 0x8000]).is_none());
+
+        for (i, entry) in CART_OVERRIDES.iter().enumerate() {
+            assert!(entry.cart_type.is_some() || entry.ram_size.is_some());
+            assert!(CART_OVERRIDES[..i].iter().all(|o| o.crc32 != entry.crc32));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::auxiliary::clock::Clock;
+use crate::bus::Bus;
+use crate::capture::audio_recorder::AudioRecorder;
+use crate::capture::screenshot::{save_screenshot, ScreenshotFormat};
+use crate::capture::video_recorder::VideoRecorder;
+use crate::cart::Cart;
+use crate::cpu::{Cpu, CpuCallback, DebugCtx};
+use crate::ppu::Ppu;
+use std::path::Path;
+
+pub struct HeadlessCtx {
+    pub clock: Clock,
+}
+
+impl CpuCallback for HeadlessCtx {
+    fn m_cycles(&mut self, m_cycles: usize, bus: &mut Bus) {
+        self.clock.m_cycles(m_cycles, bus);
+    }
+
+    fn update_serial(&mut self, _cpu: &mut Cpu) {}
+
+    fn debug(&mut self, _cpu: &mut Cpu, _ctx: Option<DebugCtx>) {}
+}
+
+/// Runs a cart without window, audio device and frame limit. Audio output is drained every
+/// step so it can be recorded.
+pub struct Headless {
+    pub cpu: Cpu,
+    pub ctx: HeadlessCtx,
+    pub audio_recorder: Option<AudioRecorder>,
+    pub video_recorder: Option<VideoRecorder>,
+}
+
+impl Headless {
+    pub fn new(cart: Cart) -> Self {
+        Self {
+            cpu: Cpu::new(Bus::new(cart)),
+            ctx: HeadlessCtx {
+                clock: Clock::with_ppu(Ppu::default()),
+            },
+            audio_recorder: None,
+            video_recorder: None,
+        }
+    }
+
+    pub fn get_ppu(&self) -> &Ppu {
+        self.ctx.clock.ppu.as_ref().unwrap()
+    }
+
+    pub fn get_frame(&self) -> usize {
+        self.get_ppu().current_frame
+    }
+
+    pub fn step(&mut self) -> Result<(), String> {
+        let frame = self.get_frame();
+        self.cpu.step(&mut self.ctx)?;
+
+        if let Some(recorder) = self.video_recorder.as_mut() {
+            let ppu = self.ctx.clock.ppu.as_ref().unwrap();
+
+            if ppu.current_frame != frame {
+                recorder.capture_frame(&ppu.pipeline.buffer)?;
+            }
+
+            recorder.capture_audio(&self.cpu.bus.io.apu);
+        }
+
+        let apu = &mut self.cpu.bus.io.apu;
+
+        if let Some(recorder) = self.audio_recorder.as_mut() {
+            recorder.capture(apu)?;
+        }
+
+        if apu.output_ready() {
+            apu.take_output();
+        }
+
+        Ok(())
+    }
+
+    pub fn run_frames(&mut self, frames: usize) -> Result<(), String> {
+        let target_frame = self.get_frame() + frames;
+
+        while self.get_frame() < target_frame {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_screenshot(&self, path: &Path, format: ScreenshotFormat) -> Result<(), String> {
+        save_screenshot(&self.get_ppu().pipeline.buffer, path, format)
+    }
+
+    /// Finalizes recordings.
+    pub fn finish(mut self) -> Result<(), String> {
+        if let Some(recorder) = self.audio_recorder.take() {
+            recorder.finish(&mut self.cpu.bus.io.apu)?;
+        }
+
+        if let Some(recorder) = self.video_recorder.take() {
+            recorder.finish()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apu::SAMPLING_FREQ;
+    use crate::emu::read_bytes;
+    use std::fs;
+
+    #[test]
+    fn test_record_audio_headless() {
+        let cart = Cart::new(read_bytes("tests/roms/dmg-acid2.gb").unwrap()).unwrap();
+        let mut headless = Headless::new(cart);
+        let path = std::env::temp_dir().join("gmboy_test_headless.wav");
+        // starts with a partly filled output buffer
+        headless.run_frames(1).unwrap();
+        let apu = &mut headless.cpu.bus.io.apu;
+        headless.audio_recorder = Some(AudioRecorder::new(&path, true, apu).unwrap());
+
+        headless.run_frames(6).unwrap();
+        headless.finish().unwrap();
+
+        let stereo = fs::read(&path).unwrap();
+        let ch1_path = crate::capture::audio_recorder::get_channel_path(&path, 1);
+        let ch1 = fs::read(&ch1_path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        for n in 1..=4 {
+            fs::remove_file(crate::capture::audio_recorder::get_channel_path(&path, n)).unwrap();
+        }
+
+        // ~100 ms of audio, the channels start and end with the stereo output
+        let stereo_frames = (stereo.len() - 44) / 4;
+        let ch1_frames = (ch1.len() - 44) / 2;
+        assert!(ch1_frames > 3500, "{}", ch1_frames);
+        assert_eq!(ch1_frames, stereo_frames);
+    }
+
+    #[test]
+    fn test_record_video_headless() {
+        let cart = Cart::new(read_bytes("tests/roms/dmg-acid2.gb").unwrap()).unwrap();
+        let mut headless = Headless::new(cart);
+        let path = std::env::temp_dir().join("gmboy_test_headless.avi");
+        let palette = headless.cpu.bus.io.lcd.current_pallet;
+        headless.video_recorder =
+            Some(VideoRecorder::new(&path, SAMPLING_FREQ as u32, &palette).unwrap());
+
+        headless.run_frames(10).unwrap();
+        assert_eq!(
+            headless.video_recorder.as_ref().unwrap().get_frames_count(),
+            10
+        );
+        headless.finish().unwrap();
+
+        let avi = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(u32::from_le_bytes(avi[48..52].try_into().unwrap()), 10);
+    }
+}
@@ -1,18 +1,29 @@
 pub mod apu;
+pub mod archive;
+pub mod args;
 pub mod auxiliary;
+pub mod benchmark;
+pub mod big_array;
 pub mod bus;
+pub mod capture;
 pub mod cart;
+pub mod cheats;
 pub mod config;
 pub mod cpu;
 pub mod debugger;
 pub mod emu;
+pub mod gamedb;
+pub mod headless;
+pub mod mem_view;
+pub mod patch;
 pub mod ppu;
+pub mod ram_search;
 pub mod ui;
 
 pub use cart::*;
 pub use cpu::*;
-pub use ppu::*;
 pub use apu::*;
+pub use ppu::*;
 
 pub struct LittleEndianBytes {
     pub low_byte: u8,
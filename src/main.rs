@@ -1,29 +1,154 @@
+use gmboy::args::{Args, USAGE};
+use gmboy::benchmark::{get_benchmark_roms, run_benchmark, DEFAULT_BENCHMARK_FRAMES};
+use gmboy::capture::audio_recorder::AudioRecorder;
+use gmboy::capture::video_recorder::VideoRecorder;
+use gmboy::cart::camera_image::{create_image_source, extract_photos};
 use gmboy::config::Config;
-use gmboy::emu::Emu;
+use gmboy::emu::{read_cart, Emu};
+use gmboy::headless::Headless;
 use std::env;
+use std::time::Duration;
 
 fn main() {
-    let mut args: Vec<String> = env::args().collect();
+    let mut args = Args::parse(env::args().skip(1)).unwrap_or_else(|err| exit_with_usage(&err));
 
-    let cart_path = if args.len() < 2 {
-        env::var("CART_PATH").ok()
-    } else {
-        Some(args.remove(1))
-    };
+    // without a cart the benchmark runs the test ROMs
+    if args.cart_path.is_none() && !args.benchmark {
+        args.cart_path = env::var("CART_PATH").ok();
+    }
+
+    if let Err(err) = args.validate() {
+        exit_with_usage(&err);
+    }
+
+    if args.benchmark {
+        if let Err(err) = run_benchmarks(&args) {
+            eprintln!("Benchmark failed: {}", err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if args.headless {
+        if let Err(err) = run_headless(&args) {
+            eprintln!("Headless run failed: {}", err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
 
     let config_path = Config::default_path();
 
-    let config = if config_path.exists() {
+    let mut config = if config_path.exists() {
         Config::from_file(config_path.to_str().unwrap()).expect("Failed to parse save/config.json")
     } else {
         eprintln!("config.json not found in the save folder");
         std::process::exit(1);
     };
 
+    if let Some(sample_rate) = args.sample_rate {
+        config.audio.sample_rate = sample_rate;
+    }
+
     let mut emu = Emu::new(config).unwrap();
 
-    if let Err(err) = emu.run(cart_path) {
+    if let Some(cart_path) = &args.cart_path {
+        emu.ctx.patches = Some((cart_path.clone(), args.patches.clone()));
+    }
+
+    emu.ctx.camera_images = args.camera_images.clone();
+
+    if let Some(path) = &args.record_audio {
+        let record_channels = args.record_channels || emu.ctx.config.audio.record_channels;
+        emu.ctx.pending_audio_recording = Some((path.clone(), record_channels));
+    }
+
+    if let Some(path) = &args.record_video {
+        if let Err(err) = emu.ctx.start_video_recording(path) {
+            eprintln!("Failed to start video recording: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    if let Err(err) = emu.run(args.cart_path) {
         eprintln!("Emu run failed: {}", err);
         std::process::exit(1);
     }
 }
+
+fn exit_with_usage(err: &str) -> ! {
+    eprintln!("{}\n{}", err, USAGE);
+    std::process::exit(1);
+}
+
+fn run_benchmarks(args: &Args) -> Result<(), String> {
+    let frames = args.frames.unwrap_or(DEFAULT_BENCHMARK_FRAMES);
+    let paths = match &args.cart_path {
+        Some(path) => vec![path.clone()],
+        None => get_benchmark_roms()?,
+    };
+    let mut total_frames = 0;
+    let mut total_elapsed = Duration::ZERO;
+
+    for path in paths {
+        let result = run_benchmark(&path, frames)?;
+        print!("{}", result);
+        total_frames += result.frames;
+        total_elapsed += result.elapsed;
+    }
+
+    println!(
+        "Total: {} frames in {:.3} s, {:.1} fps",
+        total_frames,
+        total_elapsed.as_secs_f64(),
+        total_frames as f64 / total_elapsed.as_secs_f64()
+    );
+
+    Ok(())
+}
+
+fn run_headless(args: &Args) -> Result<(), String> {
+    let cart = read_cart(args.cart_path.as_ref().unwrap(), &args.patches)?;
+    let mut headless = Headless::new(cart);
+
+    if !args.camera_images.is_empty() {
+        let source = create_image_source(&args.camera_images)?;
+        headless.cpu.bus.cart.set_image_source(source)?;
+    }
+
+    let apu = &mut headless.cpu.bus.io.apu;
+
+    if let Some(sample_rate) = args.sample_rate {
+        apu.set_sample_rate(sample_rate);
+    }
+
+    if let Some(path) = &args.record_audio {
+        headless.audio_recorder = Some(AudioRecorder::new(path, args.record_channels, apu)?);
+    }
+
+    if let Some(path) = &args.record_video {
+        let palette = headless.cpu.bus.io.lcd.current_pallet;
+        headless.video_recorder = Some(VideoRecorder::new(
+            path,
+            headless.cpu.bus.io.apu.get_sample_rate(),
+            &palette,
+        )?);
+    }
+
+    headless.run_frames(args.frames.unwrap_or_default())?;
+    println!("Ran {} frames", headless.get_frame());
+
+    if let Some(path) = &args.screenshot {
+        headless.save_screenshot(path, args.screenshot_format)?;
+        println!("Screenshot saved to {}", path.display());
+    }
+
+    if let Some(dir) = &args.camera_photos {
+        let paths = extract_photos(headless.cpu.bus.cart.ram_bytes(), dir)?;
+        println!("Saved {} camera photos to {}", paths.len(), dir.display());
+    }
+
+    headless.finish()
+}
@@ -0,0 +1,305 @@
+use crate::bus::Bus;
+use crate::{RAM_ADDRESS_START, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+pub const BYTES_PER_ROW: usize = 16;
+pub const BUS_SIZE: usize = 0x10000;
+const ROM_BUS_END: usize = 0x8000;
+
+/// Memory that can be shown by the hex viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemRegion {
+    /// Full 64 KiB address space as the CPU sees it.
+    Bus,
+    /// Raw 16 KiB ROM bank, independent of what the mapper currently has switched in.
+    RomBank(usize),
+    /// Raw 8 KiB external RAM bank, independent of the mapper state and RAM enable.
+    SramBank(usize),
+}
+
+impl MemRegion {
+    pub fn size(&self) -> usize {
+        match self {
+            MemRegion::Bus => BUS_SIZE,
+            MemRegion::RomBank(_) => ROM_BANK_SIZE,
+            MemRegion::SramBank(_) => RAM_BANK_SIZE,
+        }
+    }
+
+    /// Address shown for the first byte, so banks look like they do when mapped.
+    pub fn base_address(&self) -> usize {
+        match self {
+            MemRegion::Bus | MemRegion::RomBank(0) => 0,
+            MemRegion::RomBank(_) => ROM_BANK_SIZE,
+            MemRegion::SramBank(_) => RAM_ADDRESS_START,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            MemRegion::Bus => "BUS".to_string(),
+            MemRegion::RomBank(bank) => format!("ROM {:02X}", bank),
+            MemRegion::SramBank(bank) => format!("SRAM {:02X}", bank),
+        }
+    }
+
+    pub fn read(&self, bus: &Bus, offset: usize) -> u8 {
+        match self {
+            MemRegion::Bus => bus.read(offset as u16),
+            MemRegion::RomBank(bank) => bus
+                .cart
+                .data
+                .bytes
+                .get(bank * ROM_BANK_SIZE + offset)
+                .copied()
+                .unwrap_or(0xFF),
+            MemRegion::SramBank(bank) => bus
+                .cart
+                .ram_bytes()
+                .get(bank * RAM_BANK_SIZE + offset)
+                .copied()
+                .unwrap_or(0xFF),
+        }
+    }
+
+    pub fn write(&self, bus: &mut Bus, offset: usize, value: u8) -> Result<(), String> {
+        match self {
+            MemRegion::Bus => {
+                if offset < ROM_BUS_END {
+                    // a bus write here would be a mapper register write instead
+                    return Err("ROM is read-only on the bus, edit it in a ROM bank".to_string());
+                }
+
+                bus.write(offset as u16, value);
+            }
+            MemRegion::RomBank(bank) => {
+                let byte = bus
+                    .cart
+                    .data
+                    .bytes
+                    .get_mut(bank * ROM_BANK_SIZE + offset)
+                    .ok_or("Offset is out of ROM")?;
+                *byte = value;
+            }
+            MemRegion::SramBank(bank) => {
+                let byte = bus
+                    .cart
+                    .ram_bytes_mut()
+                    .get_mut(bank * RAM_BANK_SIZE + offset)
+                    .ok_or("Offset is out of SRAM")?;
+                *byte = value;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cycles Bus -> ROM banks -> SRAM banks (if any) -> Bus.
+    pub fn next(&self, bus: &Bus) -> MemRegion {
+        match self {
+            MemRegion::Bus => MemRegion::RomBank(0),
+            MemRegion::RomBank(_) if sram_banks_count(bus) > 0 => MemRegion::SramBank(0),
+            MemRegion::RomBank(_) | MemRegion::SramBank(_) => MemRegion::Bus,
+        }
+    }
+
+    /// Returns the same region in a bank moved by `delta`, wrapping around. Bus is not banked.
+    pub fn with_bank_delta(&self, bus: &Bus, delta: isize) -> MemRegion {
+        match self {
+            MemRegion::Bus => MemRegion::Bus,
+            MemRegion::RomBank(bank) => {
+                MemRegion::RomBank(wrap_bank(*bank, delta, rom_banks_count(bus)))
+            }
+            MemRegion::SramBank(bank) => {
+                MemRegion::SramBank(wrap_bank(*bank, delta, sram_banks_count(bus)))
+            }
+        }
+    }
+}
+
+pub fn rom_banks_count(bus: &Bus) -> usize {
+    bus.cart.data.bytes.len().div_ceil(ROM_BANK_SIZE).max(1)
+}
+
+pub fn sram_banks_count(bus: &Bus) -> usize {
+    bus.cart.ram_bytes().len().div_ceil(RAM_BANK_SIZE)
+}
+
+fn wrap_bank(bank: usize, delta: isize, count: usize) -> usize {
+    if count == 0 {
+        return 0;
+    }
+
+    (bank as isize + delta).rem_euclid(count as isize) as usize
+}
+
+/// Hex viewer state: a cursor over a region and the bytes that changed since the previous frame.
+#[derive(Debug, Clone)]
+pub struct MemView {
+    pub region: MemRegion,
+    pub cursor: usize,
+    pub top_row: usize,
+    pub visible_rows: usize,
+    prev_bytes: Vec<u8>,
+    changed: Vec<bool>,
+    /// High nibble typed but not yet written.
+    pending_nibble: Option<u8>,
+}
+
+impl MemView {
+    pub fn new(visible_rows: usize) -> Self {
+        Self {
+            region: MemRegion::Bus,
+            cursor: 0,
+            top_row: 0,
+            visible_rows,
+            prev_bytes: Vec::new(),
+            changed: Vec::new(),
+            pending_nibble: None,
+        }
+    }
+
+    pub fn set_region(&mut self, region: MemRegion) {
+        if self.region != region {
+            self.region = region;
+            self.cursor = self.cursor.min(region.size() - 1);
+            self.prev_bytes.clear();
+            self.pending_nibble = None;
+            self.scroll_to_cursor();
+        }
+    }
+
+    /// Takes a new snapshot of the region and marks bytes that differ from the previous one.
+    /// Should be called once per frame.
+    pub fn update(&mut self, bus: &Bus) {
+        let len = self.region.size();
+        let bytes: Vec<u8> = (0..len).map(|i| self.region.read(bus, i)).collect();
+
+        if self.prev_bytes.len() == len {
+            self.changed = bytes
+                .iter()
+                .zip(self.prev_bytes.iter())
+                .map(|(curr, prev)| curr != prev)
+                .collect();
+        } else {
+            self.changed = vec![false; len];
+        }
+
+        self.prev_bytes = bytes;
+    }
+
+    pub fn is_changed(&self, offset: usize) -> bool {
+        self.changed.get(offset).copied().unwrap_or(false)
+    }
+
+    pub fn address_of(&self, offset: usize) -> usize {
+        self.region.base_address() + offset
+    }
+
+    pub fn pending_nibble(&self) -> Option<u8> {
+        self.pending_nibble
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        let max = self.region.size() as isize - 1;
+        self.cursor = (self.cursor as isize + delta).clamp(0, max) as usize;
+        self.pending_nibble = None;
+        self.scroll_to_cursor();
+    }
+
+    /// Moves the cursor to an address as shown by the view (bank base included).
+    pub fn goto(&mut self, address: usize) {
+        let offset = address.saturating_sub(self.region.base_address());
+        self.cursor = offset.min(self.region.size() - 1);
+        self.pending_nibble = None;
+        self.scroll_to_cursor();
+    }
+
+    /// Types a hex digit at the cursor. The byte is written after the second digit and
+    /// the cursor moves to the next byte.
+    pub fn input_nibble(&mut self, bus: &mut Bus, nibble: u8) -> Result<(), String> {
+        let Some(high) = self.pending_nibble.take() else {
+            self.pending_nibble = Some(nibble & 0x0F);
+            return Ok(());
+        };
+
+        self.region
+            .write(bus, self.cursor, (high << 4) | (nibble & 0x0F))?;
+        self.move_cursor(1);
+
+        Ok(())
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        let row = self.cursor / BYTES_PER_ROW;
+
+        if row < self.top_row {
+            self.top_row = row;
+        } else if row >= self.top_row + self.visible_rows {
+            self.top_row = row + 1 - self.visible_rows;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_bytes() {
+        let mut bus = Bus::with_bytes(vec![0; BUS_SIZE]);
+        let mut view = MemView::new(16);
+
+        view.update(&bus);
+        assert!(!view.is_changed(0xC000));
+
+        bus.write(0xC000, 5);
+        view.update(&bus);
+        assert!(view.is_changed(0xC000));
+        assert!(!view.is_changed(0xC001));
+
+        view.update(&bus);
+        assert!(!view.is_changed(0xC000));
+    }
+
+    #[test]
+    fn test_edit_in_place() {
+        let mut bus = Bus::with_bytes(vec![0; BUS_SIZE]);
+        let mut view = MemView::new(16);
+        view.goto(0xC010);
+
+        view.input_nibble(&mut bus, 0xA).unwrap();
+        assert_eq!(bus.read(0xC010), 0);
+        view.input_nibble(&mut bus, 0x5).unwrap();
+        assert_eq!(bus.read(0xC010), 0xA5);
+        assert_eq!(view.cursor, 0xC011);
+        assert_eq!(view.top_row, 0xC011 / BYTES_PER_ROW + 1 - 16);
+    }
+
+    #[test]
+    fn test_rom_edit_via_bank() {
+        let mut bus = Bus::with_bytes(vec![0; BUS_SIZE]);
+        let mut view = MemView::new(16);
+        view.goto(0x0100);
+
+        view.input_nibble(&mut bus, 1).unwrap();
+        assert!(view.input_nibble(&mut bus, 2).is_err());
+
+        view.set_region(MemRegion::RomBank(0));
+        view.goto(0x0100);
+        view.input_nibble(&mut bus, 1).unwrap();
+        view.input_nibble(&mut bus, 2).unwrap();
+        assert_eq!(bus.cart.data.bytes[0x0100], 0x12);
+    }
+
+    #[test]
+    fn test_bank_wrap() {
+        let bus = Bus::with_bytes(vec![0; BUS_SIZE]);
+
+        assert_eq!(
+            MemRegion::RomBank(0).with_bank_delta(&bus, -1),
+            MemRegion::RomBank(0)
+        );
+        assert_eq!(MemRegion::RomBank(0).next(&bus), MemRegion::Bus);
+        assert_eq!(MemRegion::SramBank(0).base_address(), 0xA000);
+    }
+}
@@ -0,0 +1,581 @@
+use crate::archive::RomFile;
+use crate::cart::header::CartHeader;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// In order of precedence when several patches are found, BPS and UPS check the source and
+/// target checksums while IPS doesn't.
+pub const PATCH_EXTENSIONS: [&str; 3] = ["bps", "ups", "ips"];
+/// The largest ROM a mapper can address, 512 banks of 16 KiB.
+const MAX_TARGET_SIZE: usize = 8 * 1024 * 1024;
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+/// Source, target and patch CRC32 which end UPS and BPS patches.
+const FOOTER_LEN: usize = 12;
+const HEADER_END: usize = 0x0150;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl PatchFormat {
+    pub fn from_bytes(patch: &[u8]) -> Result<Self, String> {
+        if patch.starts_with(IPS_MAGIC) {
+            Ok(PatchFormat::Ips)
+        } else if patch.starts_with(UPS_MAGIC) {
+            Ok(PatchFormat::Ups)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Ok(PatchFormat::Bps)
+        } else {
+            Err("Unknown patch format".to_string())
+        }
+    }
+}
+
+/// Patch named after the ROM next to it, or next to its archive: `game.ips` for `game.gb`.
+/// Only one is applied, the first of `PATCH_EXTENSIONS` found.
+pub fn find_patch(rom_path: &str, rom: &RomFile) -> Option<PathBuf> {
+    let (archive_path, _) = crate::archive::split_entry_path(rom_path);
+    let dir = Path::new(archive_path).parent().unwrap_or(Path::new(""));
+    let stem = Path::new(&rom.name).file_stem().unwrap_or_default();
+
+    PATCH_EXTENSIONS
+        .iter()
+        .map(|extension| dir.join(format!("{}.{}", stem.to_string_lossy(), extension)))
+        .find(|path| path.is_file())
+}
+
+/// Applies the patch file to the ROM in memory, the ROM file itself is not touched.
+pub fn apply_patch_file(rom: &mut RomFile, path: &Path) -> Result<(), String> {
+    let patch = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let patched = apply_patch(&rom.bytes, &patch)
+        .map_err(|e| format!("Failed to apply {}: {}", path.display(), e))?;
+
+    println!("Patch applied: {}", path.display());
+
+    for change in get_header_changes(&rom.bytes, &patched) {
+        println!("\t {}", change);
+    }
+
+    rom.bytes = patched;
+    rom.patches.push(
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+    );
+
+    Ok(())
+}
+
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    match PatchFormat::from_bytes(patch)? {
+        PatchFormat::Ips => apply_ips(rom, patch),
+        PatchFormat::Ups => apply_ups(rom, patch),
+        PatchFormat::Bps => apply_bps(rom, patch),
+    }
+}
+
+/// Records of a 24-bit offset and 16-bit size followed by data, or by a 16-bit count and a
+/// byte to repeat when the size is 0. An optional 24-bit size after `EOF` truncates the ROM.
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = PatchReader::new(patch, IPS_MAGIC.len());
+    let mut target = rom.to_vec();
+
+    loop {
+        if reader.peek(IPS_EOF.len()) == Some(IPS_EOF) {
+            reader.skip(IPS_EOF.len());
+            break;
+        }
+
+        let offset = reader.read_be(3)?;
+        let size = reader.read_be(2)?;
+
+        let data = if size == 0 {
+            let count = reader.read_be(2)?;
+            vec![reader.read_byte()?; count]
+        } else {
+            reader.read_bytes(size)?.to_vec()
+        };
+
+        if target.len() < offset + data.len() {
+            check_target_size(offset + data.len())?;
+            target.resize(offset + data.len(), 0);
+        }
+
+        target[offset..offset + data.len()].copy_from_slice(&data);
+    }
+
+    if let Ok(size) = reader.read_be(3) {
+        target.truncate(size);
+    }
+
+    Ok(target)
+}
+
+/// Hunks of a relative offset and bytes XORed with the source up to a 0.
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let footer = read_footer(patch)?;
+    let mut reader = PatchReader::new(&patch[..patch.len() - FOOTER_LEN], UPS_MAGIC.len());
+    let source_size = reader.read_varint()?;
+    let target_size = reader.read_varint()?;
+
+    if source_size != rom.len() || footer.source_crc != crc32fast::hash(rom) {
+        return Err("The patch is made for another ROM".to_string());
+    }
+
+    check_target_size(target_size)?;
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+    let mut offset: usize = 0;
+
+    while !reader.is_empty() {
+        offset = offset
+            .checked_add(reader.read_varint()?)
+            .ok_or("Invalid offset in the patch")?;
+
+        loop {
+            let byte = reader.read_byte()?;
+
+            if offset < target.len() {
+                target[offset] ^= byte;
+            }
+
+            offset = offset.checked_add(1).ok_or("Invalid offset in the patch")?;
+
+            if byte == 0 {
+                break;
+            }
+        }
+    }
+
+    verify_target(&target, &footer)?;
+
+    Ok(target)
+}
+
+/// Actions copying from the source or the target at the same or a relative offset, or
+/// from the patch itself.
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let footer = read_footer(patch)?;
+    let mut reader = PatchReader::new(&patch[..patch.len() - FOOTER_LEN], BPS_MAGIC.len());
+    let source_size = reader.read_varint()?;
+    let target_size = reader.read_varint()?;
+    let metadata_size = reader.read_varint()?;
+    reader.skip(metadata_size);
+
+    if source_size != rom.len() || footer.source_crc != crc32fast::hash(rom) {
+        return Err("The patch is made for another ROM".to_string());
+    }
+
+    check_target_size(target_size)?;
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset: isize = 0;
+    let mut target_offset: isize = 0;
+
+    while !reader.is_empty() {
+        let data = reader.read_varint()?;
+        let length = (data >> 2) + 1;
+
+        if length > target_size - target.len() {
+            return Err("The patch writes past the target size".to_string());
+        }
+
+        match data & 0b11 {
+            // source read
+            0 => {
+                let start = target.len();
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or("Source read out of bounds")?;
+                target.extend_from_slice(bytes);
+            }
+            // target read
+            1 => target.extend_from_slice(reader.read_bytes(length)?),
+            // source copy
+            2 => {
+                source_offset += reader.read_signed_varint()?;
+                let start = usize::try_from(source_offset).map_err(|_| "Invalid source copy")?;
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or("Source copy out of bounds")?;
+                target.extend_from_slice(bytes);
+                source_offset += length as isize;
+            }
+            // target copy, may overlap the bytes it writes
+            _ => {
+                target_offset += reader.read_signed_varint()?;
+
+                for _ in 0..length {
+                    let index =
+                        usize::try_from(target_offset).map_err(|_| "Invalid target copy")?;
+                    let byte = *target.get(index).ok_or("Target copy out of bounds")?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(format!(
+            "Invalid target size: actual={}, expected={}",
+            target.len(),
+            target_size
+        ));
+    }
+
+    verify_target(&target, &footer)?;
+
+    Ok(target)
+}
+
+fn check_target_size(size: usize) -> Result<(), String> {
+    if size > MAX_TARGET_SIZE {
+        return Err(format!(
+            "Invalid target size: {}, the maximum is {}",
+            size, MAX_TARGET_SIZE
+        ));
+    }
+
+    Ok(())
+}
+
+/// Differences in the header fields shown when a cart is loaded.
+pub fn get_header_changes(before: &[u8], after: &[u8]) -> Vec<String> {
+    if before.len() < HEADER_END || after.len() < HEADER_END {
+        return Vec::new();
+    }
+
+    get_header_fields(before)
+        .into_iter()
+        .zip(get_header_fields(after))
+        .filter(|((_, before), (_, after))| before != after)
+        .map(|((name, before), (_, after))| format!("{}: {} -> {}", name, before, after))
+        .collect()
+}
+
+fn get_header_fields(bytes: &[u8]) -> [(&'static str, String); 6] {
+    [
+        ("Title", CartHeader::parse_title(bytes).unwrap_or_default()),
+        ("Type", format!("{:?}", CartHeader::parse_cart_type(bytes))),
+        (
+            "ROM Size",
+            format!("{:?}", CartHeader::parse_rom_size(bytes)),
+        ),
+        (
+            "RAM Size",
+            format!("{:?}", CartHeader::parse_ram_size(bytes)),
+        ),
+        (
+            "ROM Version",
+            format!("{:02X}", CartHeader::get_rom_version(bytes)),
+        ),
+        (
+            "Global Checksum",
+            format!("{:04X}", CartHeader::get_global_checksum(bytes)),
+        ),
+    ]
+}
+
+struct Footer {
+    source_crc: u32,
+    target_crc: u32,
+}
+
+fn read_footer(patch: &[u8]) -> Result<Footer, String> {
+    if patch.len() < UPS_MAGIC.len() + FOOTER_LEN {
+        return Err("The patch is truncated".to_string());
+    }
+
+    let footer = &patch[patch.len() - FOOTER_LEN..];
+    let read_crc = |index: usize| u32::from_le_bytes(footer[index..index + 4].try_into().unwrap());
+
+    if read_crc(8) != crc32fast::hash(&patch[..patch.len() - 4]) {
+        return Err("The patch is corrupted, its CRC32 doesn't match".to_string());
+    }
+
+    Ok(Footer {
+        source_crc: read_crc(0),
+        target_crc: read_crc(4),
+    })
+}
+
+fn verify_target(target: &[u8], footer: &Footer) -> Result<(), String> {
+    if crc32fast::hash(target) != footer.target_crc {
+        return Err("The patched ROM CRC32 doesn't match".to_string());
+    }
+
+    Ok(())
+}
+
+struct PatchReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(bytes: &'a [u8], position: usize) -> Self {
+        Self { bytes, position }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn peek(&self, len: usize) -> Option<&'a [u8]> {
+        self.bytes.get(self.position..self.position + len)
+    }
+
+    fn skip(&mut self, len: usize) {
+        self.position += len;
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.peek(len).ok_or("The patch is truncated")?;
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_be(&mut self, len: usize) -> Result<usize, String> {
+        Ok(self
+            .read_bytes(len)?
+            .iter()
+            .fold(0, |value, byte| (value << 8) | *byte as usize))
+    }
+
+    /// Variable length number of UPS and BPS, 7 bits per byte with the last one flagged.
+    fn read_varint(&mut self) -> Result<usize, String> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+
+        loop {
+            let byte = self.read_byte()?;
+            value = ((byte & 0x7F) as usize)
+                .checked_mul(shift)
+                .and_then(|bits| value.checked_add(bits))
+                .ok_or("Invalid number in the patch")?;
+
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+
+            shift = shift
+                .checked_mul(0x80)
+                .ok_or("Invalid number in the patch")?;
+            value = value
+                .checked_add(shift)
+                .ok_or("Invalid number in the patch")?;
+        }
+    }
+
+    /// BPS relative offset, the lowest bit is the sign.
+    fn read_signed_varint(&mut self) -> Result<isize, String> {
+        let value = self.read_varint()?;
+        let offset = (value >> 1) as isize;
+
+        Ok(if value & 1 != 0 { -offset } else { offset })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_varint(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                out.push(byte | 0x80);
+                return;
+            }
+
+            out.push(byte);
+            value -= 1;
+        }
+    }
+
+    fn finish(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend(crc32fast::hash(source).to_le_bytes());
+        patch.extend(crc32fast::hash(target).to_le_bytes());
+        patch.extend(crc32fast::hash(&patch).to_le_bytes());
+
+        patch
+    }
+
+    #[test]
+    fn test_ips() {
+        let rom = [0u8; 8];
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0, 0, 2, 0, 2, 0xAA, 0xBB]);
+        // RLE past the end extends the ROM
+        patch.extend([0, 0, 7, 0, 0, 0, 3, 0xCC]);
+        patch.extend(b"EOF");
+
+        assert_eq!(
+            apply_patch(&rom, &patch).unwrap(),
+            vec![0, 0, 0xAA, 0xBB, 0, 0, 0, 0xCC, 0xCC, 0xCC]
+        );
+
+        patch.extend([0, 0, 4]);
+        assert_eq!(apply_patch(&rom, &patch).unwrap(), vec![0, 0, 0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn test_ups() {
+        let source = [1u8, 2, 3, 4];
+        let target = [1u8, 7, 3, 4, 9];
+        let mut patch = b"UPS1".to_vec();
+        encode_varint(source.len(), &mut patch);
+        encode_varint(target.len(), &mut patch);
+        encode_varint(1, &mut patch);
+        patch.extend([2 ^ 7, 0]);
+        encode_varint(1, &mut patch);
+        patch.extend([9, 0]);
+        let patch = finish(patch, &source, &target);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+        assert!(apply_patch(&[1, 2, 3, 5], &patch)
+            .unwrap_err()
+            .contains("another ROM"));
+
+        let mut corrupted = patch.clone();
+        corrupted[6] ^= 1;
+        assert!(apply_patch(&source, &corrupted)
+            .unwrap_err()
+            .contains("corrupted"));
+    }
+
+    #[test]
+    fn test_bps() {
+        let source = [1u8, 2, 3, 4, 5, 6];
+        let target = [1u8, 2, 9, 5, 6, 9, 5, 6];
+        let mut patch = b"BPS1".to_vec();
+        encode_varint(source.len(), &mut patch);
+        encode_varint(target.len(), &mut patch);
+        encode_varint(0, &mut patch);
+        // source read 2
+        encode_varint(1 << 2, &mut patch);
+        // target read 1
+        encode_varint(1, &mut patch);
+        patch.push(9);
+        // source copy 2 from 4
+        encode_varint((1 << 2) | 2, &mut patch);
+        encode_varint(4 << 1, &mut patch);
+        // target copy 3 from 2
+        encode_varint((2 << 2) | 3, &mut patch);
+        encode_varint(2 << 1, &mut patch);
+        let patch = finish(patch, &source, &target);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn test_target_size() {
+        let source = [0u8; 4];
+        let mut header = b"UPS1".to_vec();
+        encode_varint(source.len(), &mut header);
+        encode_varint(MAX_TARGET_SIZE + 1, &mut header);
+        let patch = finish(header, &source, &source);
+
+        assert!(apply_patch(&source, &patch)
+            .unwrap_err()
+            .contains("Invalid target size"));
+
+        // a target copy longer than the target
+        let mut patch = b"BPS1".to_vec();
+        encode_varint(source.len(), &mut patch);
+        encode_varint(2, &mut patch);
+        encode_varint(0, &mut patch);
+        encode_varint(1 << 2, &mut patch);
+        encode_varint((4 << 2) | 3, &mut patch);
+        encode_varint(0, &mut patch);
+        let patch = finish(patch, &source, &source[..2]);
+
+        assert!(apply_patch(&source, &patch)
+            .unwrap_err()
+            .contains("past the target size"));
+
+        // a hunk running past the largest offset
+        let mut patch = b"UPS1".to_vec();
+        encode_varint(source.len(), &mut patch);
+        encode_varint(source.len(), &mut patch);
+        encode_varint(usize::MAX, &mut patch);
+        patch.extend([1, 0]);
+        let patch = finish(patch, &source, &source);
+
+        assert!(apply_patch(&source, &patch)
+            .unwrap_err()
+            .contains("Invalid offset"));
+    }
+
+    #[test]
+    fn test_varint_overflow() {
+        let mut patch = vec![0x7F; 10];
+        patch.push(0xFF);
+        let mut reader = PatchReader::new(&patch, 0);
+
+        assert!(reader.read_varint().is_err());
+    }
+
+    #[test]
+    fn test_find_patch() {
+        let dir = std::env::temp_dir().join("gmboy_test_find_patch");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let rom = RomFile {
+            name: "game.gb".to_string(),
+            bytes: Vec::new(),
+            patches: Vec::new(),
+        };
+        let rom_path = dir.join("game.gb").to_string_lossy().to_string();
+
+        assert_eq!(find_patch(&rom_path, &rom), None);
+
+        fs::write(dir.join("game.ips"), b"PATCH").unwrap();
+        assert_eq!(find_patch(&rom_path, &rom), Some(dir.join("game.ips")));
+
+        fs::write(dir.join("game.bps"), b"BPS1").unwrap();
+        fs::write(dir.join("game.ups"), b"UPS1").unwrap();
+        assert_eq!(find_patch(&rom_path, &rom), Some(dir.join("game.bps")));
+
+        // dots in the stem are kept
+        let rom = RomFile {
+            name: "Game (T+Eng1.0).gb".to_string(),
+            bytes: Vec::new(),
+            patches: Vec::new(),
+        };
+        let rom_path = dir.join("Game (T+Eng1.0).gb").to_string_lossy().to_string();
+        fs::write(dir.join("Game (T+Eng1.ips"), b"PATCH").unwrap();
+        fs::write(dir.join("Game (T+Eng1.0).ips"), b"PATCH").unwrap();
+        assert_eq!(
+            find_patch(&rom_path, &rom),
+            Some(dir.join("Game (T+Eng1.0).ips"))
+        );
+    }
+
+    #[test]
+    fn test_header_changes() {
+        let before = vec![0u8; HEADER_END];
+        let mut after = before.clone();
+        after[0x0134..0x0137].copy_from_slice(b"ABC");
+        after[0x014C] = 1;
+
+        assert_eq!(
+            get_header_changes(&before, &after),
+            vec!["Title:  -> ABC", "ROM Version: 00 -> 01"]
+        );
+    }
+}
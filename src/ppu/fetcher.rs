@@ -1,25 +1,25 @@
 use crate::bus::Bus;
-use crate::ppu::sprite::SpriteFetcher;
+use crate::ppu::sprite::{SpriteFetcher, SPRITE_FETCH_DOTS};
 use crate::ppu::tile::{get_color_index, Pixel, TILE_BITS_COUNT, TILE_HEIGHT, TILE_WIDTH};
 use crate::ppu::{LCD_X_RES, LCD_Y_RES};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
 pub const MAX_FIFO_SIZE: usize = 8;
-pub const MAX_FIFO_SPRITES_SIZE: usize = 10;
+/// Dots from the start of a tile fetch until a sprite fetch can begin.
+const BG_READY_DOTS: usize = 5;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BgwFetchedData {
     pub tile_idx: u8,
     pub byte1: u8,
     pub byte2: u8,
-    pub map_x: u8,
     pub map_y: u8,
     pub data_area: u16,
-    pub is_window: bool,
 }
 
 impl BgwFetchedData {
-    pub fn get_data_addr(&mut self) -> u16 {
+    pub fn get_data_addr(&self) -> u16 {
         let tile_y = (self.map_y % TILE_HEIGHT as u8) * 2;
 
         self.data_area
@@ -34,185 +34,428 @@ impl BgwFetchedData {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Sprite pixel waiting in the OBJ FIFO, palettes are applied when it is mixed with BG.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct ObjPixel {
+    color_idx: usize,
+    is_palette_1: bool,
+    is_bg_priority: bool,
+}
+
+/// Mode 3 pixel pipeline, `process` is called once per dot. Every step of the background
+/// fetcher takes 2 dots and the first fetch of a line is done twice, so a line without
+/// scrolling, window and sprites takes 172 dots. Fine scroll discards SCX % 8 pixels, the
+/// window restarts the fetcher (6 dots) and each sprite stalls output for up to 11 dots
+/// depending on how far the background fetcher is from finishing its tile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PixelFetcher {
     pub pushed_x: u8,
     pub sprite_fetcher: SpriteFetcher,
     pub buffer: Vec<Pixel>,
 
     fetch_step: FetchStep,
-    line_x: u8,
-    fetch_x: u8,
-    fifo_x: u8,
-    pixel_fifo: VecDeque<Pixel>,
+    step_dot: u8,
+    tile_x: u8,
+    is_first_fetch: bool,
+    is_window: bool,
+    is_line_start: bool,
+    discard: u8,
+    sprite_idx: Option<usize>,
+    sprite_dots: usize,
+    is_hidden_wait_done: bool,
+    /// The background fetcher waits a dot after a sprite fetched before the first pixel.
+    is_fetcher_held: bool,
+    bg_fifo: VecDeque<usize>,
+    obj_fifo: VecDeque<ObjPixel>,
     bgw_fetched_data: BgwFetchedData,
 }
 
 impl Default for PixelFetcher {
     fn default() -> PixelFetcher {
         Self {
-            fetch_step: FetchStep::Tile,
-            pixel_fifo: Default::default(),
-            line_x: 0,
             pushed_x: 0,
-            fetch_x: 0,
-            bgw_fetched_data: Default::default(),
-            fifo_x: 0,
-            buffer: vec![Pixel::default(); LCD_Y_RES as usize * LCD_X_RES as usize],
             sprite_fetcher: Default::default(),
+            buffer: vec![Pixel::default(); LCD_Y_RES as usize * LCD_X_RES as usize],
+            fetch_step: FetchStep::Tile,
+            step_dot: 0,
+            tile_x: 0,
+            is_first_fetch: true,
+            is_window: false,
+            is_line_start: true,
+            discard: 0,
+            sprite_idx: None,
+            sprite_dots: 0,
+            is_hidden_wait_done: false,
+            is_fetcher_held: false,
+            bg_fifo: VecDeque::with_capacity(MAX_FIFO_SIZE),
+            obj_fifo: VecDeque::with_capacity(MAX_FIFO_SIZE),
+            bgw_fetched_data: Default::default(),
         }
     }
 }
 
 impl PixelFetcher {
-    pub fn process(&mut self, bus: &Bus, line_ticks: usize) {
-        if line_ticks & 1 != 0 {
+    pub fn process(&mut self, bus: &Bus) {
+        if self.sprite_dots > 0 {
+            self.sprite_dots -= 1;
+
+            if self.sprite_dots == 0 {
+                self.merge_sprite(bus);
+            }
+
+            return;
+        }
+
+        // the first tile was pushed before the sprite, the fetcher restarts with its pixels
+        if self.is_fetcher_held {
+            self.is_fetcher_held = false;
+        } else {
             self.fetch(bus);
         }
 
+        if self.bg_fifo.is_empty() {
+            return;
+        }
+
+        if self.sprite_idx.is_none() {
+            if self.try_start_window(bus) {
+                return;
+            }
+
+            if bus.io.lcd.control.obj_enabled() {
+                self.sprite_idx = self
+                    .sprite_fetcher
+                    .find_sprite(self.pushed_x, self.is_line_start);
+            }
+        }
+
+        if let Some(idx) = self.sprite_idx {
+            let x = self.sprite_fetcher.line_sprites[idx].x;
+
+            if x < 8 || self.is_bg_ready() {
+                self.start_sprite_fetch(x);
+            }
+
+            return;
+        }
+
         self.push_pixel(bus);
     }
 
+    /// Output is paused until the background fetcher is about to finish its tile.
+    fn is_bg_ready(&self) -> bool {
+        match self.fetch_step {
+            FetchStep::Tile | FetchStep::Data0 => false,
+            FetchStep::Data1 => self.step_dot > 0,
+            FetchStep::Push => true,
+        }
+    }
+
+    /// Sprites partially hidden on the left belong to a tile that is never fetched, the
+    /// first of them waits for it without advancing the background fetcher. This dot is
+    /// the first one of the fetch.
+    fn start_sprite_fetch(&mut self, x: u8) {
+        let mut dots = SPRITE_FETCH_DOTS;
+
+        if x < 8 {
+            self.is_fetcher_held = true;
+
+            if !self.is_hidden_wait_done {
+                self.is_hidden_wait_done = true;
+                dots += BG_READY_DOTS.saturating_sub(x as usize);
+            }
+        }
+
+        self.sprite_dots = dots - 1;
+    }
+
+    fn try_start_window(&mut self, bus: &Bus) -> bool {
+        let window = &bus.io.lcd.window;
+
+        // WX 166 starts the window on the last pixel, larger values never reach it
+        if self.is_window
+            || self.discard > 0
+            || !window.on(&bus.io.lcd)
+            || !(self.pushed_x as u16 + 7 == window.x as u16 || window.x < 7 && self.pushed_x == 0)
+        {
+            return false;
+        }
+
+        self.is_window = true;
+        self.discard = 7u8.saturating_sub(window.x);
+        self.bg_fifo.clear();
+        self.tile_x = 0;
+        self.fetch_step = FetchStep::Tile;
+        self.step_dot = 0;
+        // the restarted fetcher already runs on this dot
+        self.fetch(bus);
+
+        true
+    }
+
     fn push_pixel(&mut self, bus: &Bus) {
-        if self.pixel_fifo.len() > MAX_FIFO_SIZE {
-            let pixel = self.pixel_fifo.pop_front().unwrap();
-
-            // Check if we are in the window or background layer
-            // For the window layer, bypass scroll_x to avoid horizontal scrolling
-            if self.bgw_fetched_data.is_window {
-                // No horizontal scroll for window, only adjust based on `line_x` and `pushed_x`
-                let index = (self.pushed_x as usize)
-                    .wrapping_add(bus.io.lcd.ly as usize * LCD_X_RES as usize);
-                self.buffer[index] = pixel;
-                self.pushed_x += 1;
+        let Some(bg_color_idx) = self.bg_fifo.pop_front() else {
+            return;
+        };
+
+        self.is_line_start = false;
+
+        if self.discard > 0 {
+            self.discard -= 1;
+            return;
+        }
+
+        let lcd = &bus.io.lcd;
+        let obj = self.obj_fifo.pop_front().unwrap_or_default();
+        let pixel = if obj.color_idx != 0 && (!obj.is_bg_priority || bg_color_idx == 0) {
+            let colors = if obj.is_palette_1 {
+                &lcd.sp2_colors
             } else {
-                // For the background layer, apply scroll_x for horizontal scrolling
-                if self.line_x >= bus.io.lcd.scroll_x % TILE_WIDTH as u8 {
-                    let index = (self.pushed_x as usize)
-                        .wrapping_add(bus.io.lcd.ly as usize * LCD_X_RES as usize);
-                    self.buffer[index] = pixel;
-                    self.pushed_x += 1;
-                }
+                &lcd.sp1_colors
+            };
+
+            Pixel::new(colors[obj.color_idx], obj.color_idx.into())
+        } else {
+            Pixel::new(lcd.bg_colors[bg_color_idx], bg_color_idx.into())
+        };
+
+        let index = self.pushed_x as usize + lcd.ly as usize * LCD_X_RES as usize;
+
+        if let Some(buffer_pixel) = self.buffer.get_mut(index) {
+            *buffer_pixel = pixel;
+        }
+
+        self.pushed_x += 1;
+    }
+
+    fn merge_sprite(&mut self, bus: &Bus) {
+        let Some(idx) = self.sprite_idx.take() else {
+            return;
+        };
+
+        let sprite = self.sprite_fetcher.line_sprites[idx];
+        let line = self.sprite_fetcher.fetch_sprite_line(bus, idx);
+        let hidden = 8u8.saturating_sub(sprite.x);
+
+        for offset in hidden..TILE_BITS_COUNT {
+            let bit = if sprite.f_x_flip() {
+                7 - offset
+            } else {
+                offset
+            };
+            let pos = (offset - hidden) as usize;
+
+            if pos >= self.obj_fifo.len() {
+                self.obj_fifo.push_back(ObjPixel::default());
             }
 
-            self.line_x += 1;
+            // sprites fetched earlier have priority, only transparent pixels are replaced
+            if self.obj_fifo[pos].color_idx == 0 {
+                self.obj_fifo[pos] = ObjPixel {
+                    color_idx: get_color_index(line.byte1, line.byte2, bit),
+                    is_palette_1: sprite.f_pn(),
+                    is_bg_priority: sprite.f_bgp(),
+                };
+            }
         }
     }
 
     fn fetch(&mut self, bus: &Bus) {
-        match self.fetch_step {
-            FetchStep::Tile => {
-                if bus.io.lcd.control.bgw_enabled() {
-                    if let Some(tile_idx) = bus.io.lcd.window.get_tile_idx(self.fetch_x as u16, bus)
-                    {
-                        self.bgw_fetched_data.is_window = true;
-                        self.bgw_fetched_data.map_y =
-                            bus.io.lcd.ly.wrapping_add(bus.io.lcd.window.y);
-                        self.bgw_fetched_data.map_x =
-                            self.fetch_x.wrapping_add(bus.io.lcd.window.x);
-                        self.bgw_fetched_data.tile_idx = tile_idx;
-                    } else {
-                        self.bgw_fetched_data.is_window = false;
-                        self.bgw_fetched_data.map_y =
-                            bus.io.lcd.ly.wrapping_add(bus.io.lcd.scroll_y);
-                        self.bgw_fetched_data.map_x =
-                            self.fetch_x.wrapping_add(bus.io.lcd.scroll_x);
-                        let addr = bus.io.lcd.control.bg_map_area()
-                            + (self.bgw_fetched_data.map_x as u16 / TILE_WIDTH)
-                            + ((self.bgw_fetched_data.map_y as u16 / TILE_HEIGHT) * 32);
-                        self.bgw_fetched_data.tile_idx = bus.read(addr);
-                    }
-                    
-                    self.bgw_fetched_data.data_area = bus.io.lcd.control.bgw_data_area();
-                    self.bgw_fetched_data.normalize_tile_idx();
-                }
+        if self.fetch_step == FetchStep::Push {
+            if self.bg_fifo.is_empty() {
+                self.push_tile(bus);
+                self.fetch_step = FetchStep::Tile;
+            }
 
-                if bus.io.lcd.control.obj_enabled() {
-                    self.sprite_fetcher
-                        .fetch_sprite_tiles(bus.io.lcd.scroll_x, self.fetch_x);
-                }
+            return;
+        }
 
+        // memory is accessed on the second dot of a step
+        if self.step_dot == 0 {
+            self.step_dot = 1;
+            return;
+        }
+
+        self.step_dot = 0;
+
+        match self.fetch_step {
+            FetchStep::Tile => {
+                self.fetch_tile_idx(bus);
                 self.fetch_step = FetchStep::Data0;
-                self.fetch_x = self.fetch_x.wrapping_add(TILE_WIDTH as u8);
             }
             FetchStep::Data0 => {
-                self.bgw_fetched_data.byte1 = bus.read(self.bgw_fetched_data.get_data_addr());
-                self.sprite_fetcher.fetch_sprite_data(bus, 0);
+                self.bgw_fetched_data.byte1 = bus.video_ram.read(self.bgw_fetched_data.get_data_addr());
                 self.fetch_step = FetchStep::Data1;
             }
             FetchStep::Data1 => {
-                self.bgw_fetched_data.byte2 = bus.read(self.bgw_fetched_data.get_data_addr() + 1);
-                self.sprite_fetcher.fetch_sprite_data(bus, 1);
-                self.fetch_step = FetchStep::Idle;
-            }
-            FetchStep::Idle => self.fetch_step = FetchStep::Push,
-            FetchStep::Push => {
-                if self.try_fifo_add(bus) {
+                self.bgw_fetched_data.byte2 = bus.video_ram.read(self.bgw_fetched_data.get_data_addr() + 1);
+
+                if self.is_first_fetch {
+                    // the first tile of a line is fetched twice
+                    self.is_first_fetch = false;
                     self.fetch_step = FetchStep::Tile;
+                } else {
+                    self.fetch_step = FetchStep::Push;
                 }
             }
+            FetchStep::Push => unreachable!(),
         }
     }
 
-    fn try_fifo_add(&mut self, bus: &Bus) -> bool {
-        if self.pixel_fifo.len() > MAX_FIFO_SIZE {
-            return false;
-        }
+    fn fetch_tile_idx(&mut self, bus: &Bus) {
+        let lcd = &bus.io.lcd;
+
+        let addr = if self.is_window {
+            self.bgw_fetched_data.map_y = lcd.window.line_number;
 
-        let x: i32 = self.fetch_x.wrapping_sub(8 - (bus.io.lcd.scroll_x % 8)) as i32;
+            lcd.control.win_map_area()
+                + (self.tile_x as u16 & 31)
+                + (lcd.window.line_number as u16 / TILE_HEIGHT) * 32
+        } else {
+            self.bgw_fetched_data.map_y = lcd.ly.wrapping_add(lcd.scroll_y);
+            let map_x = (lcd.scroll_x as u16 / TILE_WIDTH + self.tile_x as u16) & 31;
+
+            lcd.control.bg_map_area()
+                + map_x
+                + (self.bgw_fetched_data.map_y as u16 / TILE_HEIGHT) * 32
+        };
+
+        self.bgw_fetched_data.tile_idx = bus.video_ram.read(addr);
+        self.bgw_fetched_data.data_area = lcd.control.bgw_data_area();
+        self.bgw_fetched_data.normalize_tile_idx();
+    }
+
+    fn push_tile(&mut self, bus: &Bus) {
+        let is_enabled = bus.io.lcd.control.bgw_enabled();
 
         for bit in 0..TILE_BITS_COUNT {
-            let bgw_color_index = get_color_index(
-                self.bgw_fetched_data.byte1,
-                self.bgw_fetched_data.byte2,
-                bit,
-            );
-
-            let bgw_pixel = if bus.io.lcd.control.bgw_enabled() {
-                Pixel::new(
-                    bus.io.lcd.bg_colors[bgw_color_index],
-                    bgw_color_index.into(),
+            let color_idx = if is_enabled {
+                get_color_index(
+                    self.bgw_fetched_data.byte1,
+                    self.bgw_fetched_data.byte2,
+                    bit,
                 )
             } else {
-                Pixel::new(bus.io.lcd.bg_colors[0], 0.into())
+                0
             };
 
-            let sprite_pixel = if bus.io.lcd.control.obj_enabled() {
-                self.sprite_fetcher
-                    .fetch_sprite_pixel(&bus.io.lcd, self.fifo_x, bgw_color_index)
-            } else {
-                None
-            };
-
-            let pixel = sprite_pixel.unwrap_or(bgw_pixel);
-
-            if x >= 0 {
-                self.pixel_fifo.push_back(pixel);
-                self.fifo_x += 1;
-            }
+            self.bg_fifo.push_back(color_idx);
         }
 
-        true
+        self.tile_x = self.tile_x.wrapping_add(1);
+    }
+
+    /// Whether the window was triggered on the current line.
+    pub fn is_window(&self) -> bool {
+        self.is_window
     }
 
-    pub fn reset(&mut self) {
+    pub fn reset(&mut self, bus: &Bus) {
         self.fetch_step = FetchStep::Tile;
-        self.line_x = 0;
-        self.fetch_x = 0;
+        self.step_dot = 0;
+        self.tile_x = 0;
         self.pushed_x = 0;
-        self.fifo_x = 0;
+        self.is_first_fetch = true;
+        self.is_window = false;
+        self.is_line_start = true;
+        self.discard = bus.io.lcd.scroll_x % TILE_WIDTH as u8;
+        self.sprite_idx = None;
+        self.sprite_dots = 0;
+        self.is_hidden_wait_done = false;
+        self.is_fetcher_held = false;
+        self.clear();
     }
 
     pub fn clear(&mut self) {
-        self.pixel_fifo.clear();
+        self.bg_fifo.clear();
+        self.obj_fifo.clear();
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FetchStep {
     Tile,
     Data0,
     Data1,
-    Idle,
     Push,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::lcd::PpuMode;
+    use crate::ppu::Ppu;
+
+    /// Runs a line from the start of OAM scan, returns the mode 3 length in dots.
+    fn mode3_length(bus: &mut Bus) -> usize {
+        let mut ppu = Ppu::default();
+        bus.io.lcd.status.set_ppu_mode(PpuMode::Oam);
+
+        while bus.io.lcd.status.ppu_mode() != PpuMode::Transfer {
+            ppu.tick(bus);
+        }
+
+        let start = ppu.line_ticks;
+
+        while bus.io.lcd.status.ppu_mode() == PpuMode::Transfer {
+            ppu.tick(bus);
+        }
+
+        ppu.line_ticks - start
+    }
+
+    fn new_bus() -> Bus {
+        let mut bus = Bus::with_bytes(vec![0; 0x10000]);
+        bus.io.lcd.control.byte = 0x93; // bg and sprites on
+        bus.io.lcd.ly = 0;
+
+        bus
+    }
+
+    #[test]
+    fn test_mode3_scx() {
+        for scx in 0..16 {
+            let mut bus = new_bus();
+            bus.io.lcd.scroll_x = scx;
+
+            assert_eq!(mode3_length(&mut bus), 172 + scx as usize % 8);
+        }
+    }
+
+    #[test]
+    fn test_mode3_window() {
+        let mut bus = new_bus();
+        bus.io.lcd.control.byte |= 0b0010_0000;
+        bus.io.lcd.window.x = 87;
+        bus.io.lcd.window.y = 0;
+
+        assert_eq!(mode3_length(&mut bus), 172 + 6);
+    }
+
+    #[test]
+    fn test_mode3_sprites() {
+        // with SCX = 0 a single sprite costs 11 - min(5, x % 8)
+        for (x, penalty) in [(0, 11), (8, 11), (9, 10), (12, 7), (13, 6), (15, 6), (16, 11)] {
+            let mut bus = new_bus();
+            bus.oam_ram.entries[0].y = 16;
+            bus.oam_ram.entries[0].x = x;
+
+            assert_eq!(mode3_length(&mut bus), 172 + penalty, "x = {}", x);
+        }
+
+        let mut bus = new_bus();
+        bus.oam_ram.entries[0].y = 16;
+        bus.oam_ram.entries[0].x = 8;
+        bus.oam_ram.entries[1].y = 16;
+        bus.oam_ram.entries[1].x = 8;
+        assert_eq!(mode3_length(&mut bus), 172 + 11 + 6);
+
+        // a hidden sprite doesn't shorten the wait of one in the next tile
+        let mut bus = new_bus();
+        bus.oam_ram.entries[0].y = 16;
+        bus.oam_ram.entries[0].x = 1;
+        bus.oam_ram.entries[1].y = 16;
+        bus.oam_ram.entries[1].x = 9;
+        assert_eq!(mode3_length(&mut bus), 172 + 10 + 10);
+    }
+}
@@ -1,10 +1,10 @@
-use crate::cpu::interrupts::{InterruptType, Interrupts};
 use crate::ppu::tile::{
     PixelColor, BG_TILE_MAP_1_ADDR_START, BG_TILE_MAP_2_ADDR_START, TILE_SET_DATA_1_START,
     TILE_SET_DATA_2_START,
 };
 use crate::ppu::window::Window;
 use crate::{get_bit_flag, set_bit};
+use serde::{Deserialize, Serialize};
 
 pub const LCD_ADDRESS_START: u16 = 0xFF40;
 pub const LCD_ADDRESS_END: u16 = 0xFF4B;
@@ -23,6 +23,8 @@ pub const LCD_OBJ_PALETTE_1_ADDRESS: u16 = 0xFF49;
 pub const LCD_WINDOW_Y_ADDRESS: u16 = 0xFF4A;
 pub const LCD_WINDOW_X_ADDRESS: u16 = 0xFF4B;
 const LCD_STATUS_UNUSED_MASK: u8 = 0b1000_0000;
+/// Interrupt source bits, mode and LY = LYC flag are read-only.
+const LCD_STATUS_WRITE_MASK: u8 = 0b0111_1000;
 
 pub const BLACK_WHITE_PALLET: [PixelColor; 4] = [
     PixelColor::from_hex(0xFFFFFFFF),
@@ -41,7 +43,7 @@ pub const HOLLOW_PALLET: [PixelColor; 4] = [
 // todo: move pallets to file conf
 pub const PALLETS: [[PixelColor; 4]; 2] = [BLACK_WHITE_PALLET, HOLLOW_PALLET];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[repr(C)]
 pub struct Lcd {
     // Registers
@@ -55,6 +57,8 @@ pub struct Lcd {
     pub bg_palette: u8,
     pub obj_palette: [u8; 2],
     pub window: Window,
+    /// Mode reported in STAT, it lags behind the PPU by 4 dots.
+    pub stat_mode: PpuMode,
 
     // Other data
     pub bg_colors: [PixelColor; 4],
@@ -77,6 +81,7 @@ impl Default for Lcd {
             bg_palette: 0xFC,
             obj_palette: [0xFF, 0xFF],
             window: Window::default(),
+            stat_mode: PpuMode::HBlank,
             current_pallet,
             bg_colors: current_pallet,
             sp1_colors: current_pallet,
@@ -89,7 +94,9 @@ impl Lcd {
     pub fn read(&self, address: u16) -> u8 {
         match address {
             LCD_CONTROL_ADDRESS => self.control.byte,
-            LCD_STATUS_ADDRESS => self.status.byte | LCD_STATUS_UNUSED_MASK,
+            LCD_STATUS_ADDRESS => {
+                (self.status.byte & !0b11) | self.stat_mode as u8 | LCD_STATUS_UNUSED_MASK
+            }
             LCD_SCROLL_Y_ADDRESS => self.scroll_y,
             LCD_SCROLL_X_ADDRESS => self.scroll_x,
             LCD_LY_ADDRESS => self.ly,
@@ -130,7 +137,10 @@ impl Lcd {
     pub fn write(&mut self, address: u16, value: u8) {
         match address {
             LCD_CONTROL_ADDRESS => self.control.byte = value,
-            LCD_STATUS_ADDRESS => self.status.byte = value,
+            LCD_STATUS_ADDRESS => {
+                self.status.byte =
+                    (value & LCD_STATUS_WRITE_MASK) | (self.status.byte & !LCD_STATUS_WRITE_MASK)
+            }
             LCD_SCROLL_Y_ADDRESS => self.scroll_y = value,
             LCD_SCROLL_X_ADDRESS => self.scroll_x = value,
             LCD_LY_ADDRESS => self.ly = value,
@@ -154,35 +164,17 @@ impl Lcd {
         }
     }
 
-    pub fn increment_ly(&mut self, interrupts: &mut Interrupts) {
-        if self.window.is_visible(self) && self.window.on(self) {
-            self.window.line_number += 1;
-        }
-
+    pub fn increment_ly(&mut self) {
         self.ly += 1;
-        self.compare_ly(interrupts);
     }
 
-    pub fn reset_ly(&mut self, interrupts: &mut Interrupts) {
+    pub fn reset_ly(&mut self) {
         self.ly = 0;
-        self.window.line_number = 0;
-        self.compare_ly(interrupts);
-    }
-
-    fn compare_ly(&mut self, interrupts: &mut Interrupts) {
-        if self.ly == self.ly_compare {
-            self.status.lyc_set(true);
-
-            if self.status.is_stat_interrupt(LcdStatSrc::Lyc) {
-                interrupts.request_interrupt(InterruptType::LCDStat);
-            } else {
-                self.status.lyc_set(false);
-            }
-        }
+        self.window.reset();
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[repr(C)]
 pub struct LcdControl {
     pub byte: u8,
@@ -243,7 +235,7 @@ impl LcdControl {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[repr(C)]
 pub struct LcdStatus {
     pub byte: u8,
@@ -272,7 +264,7 @@ impl LcdStatus {
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PpuMode {
     HBlank,
     VBlank,
@@ -1,8 +1,9 @@
 pub mod lcd;
 pub mod oam;
+pub mod oam_inspector;
 mod fetcher;
 pub mod ppu;
-mod sprite;
+pub mod sprite;
 pub mod tile;
 pub mod vram;
 mod window;
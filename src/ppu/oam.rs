@@ -1,11 +1,14 @@
+use serde::{Deserialize, Serialize};
+
 // Object attributes reside in the object attribute memory (OAM) at $FE00-FE9F.
 // Has 40 movable objects.
 
 pub const OAM_ENTRIES_COUNT: usize = 40;
 pub const OAM_ADDR_START: u16 = 0xFE00;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OamRam {
+    #[serde(with = "crate::big_array")]
     pub entries: [OamEntry; OAM_ENTRIES_COUNT],
 }
 
@@ -59,7 +62,7 @@ impl OamRam {
 //  Bit3   Tile VRAM-Bank  **CGB Mode Only**     (0=Bank 0, 1=Bank 1)
 //  Bit2-0 Palette number  **CGB Mode Only**     (OBP0-7)
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct OamEntry {
     pub y: u8,
     pub x: u8,
@@ -0,0 +1,154 @@
+use crate::bus::Bus;
+use crate::ppu::oam::{OamEntry, OAM_ENTRIES_COUNT};
+use crate::ppu::sprite::OamScanLine;
+use crate::ppu::tile::{
+    ColorId, TILE_BIT_SIZE, TILE_LINE_BYTES_COUNT, TILE_SET_DATA_1_START, TILE_WIDTH,
+};
+
+pub const MAX_SPRITE_HEIGHT: usize = 16;
+
+/// Snapshot of a single OAM entry prepared for the debug view.
+#[derive(Debug, Clone)]
+pub struct SpriteInfo {
+    pub oam_index: usize,
+    pub entry: OamEntry,
+    /// 8 or 16 depending on LCDC bit 2
+    pub height: u8,
+    /// Color ids as the sprite appears on screen (flips applied). Only `height` rows are used.
+    pub pixels: [[ColorId; TILE_WIDTH as usize]; MAX_SPRITE_HEIGHT],
+    /// Scanlines on which the entry was loaded into `SpriteFetcher::line_sprites`.
+    pub selected_lines: Vec<u8>,
+    /// Scanlines on which the entry was skipped because of the 10 sprites per line limit.
+    pub dropped_lines: Vec<u8>,
+}
+
+impl SpriteInfo {
+    pub fn new(bus: &Bus, oam_index: usize, scan_lines: &[OamScanLine]) -> Self {
+        let entry = bus.oam_ram.entries[oam_index];
+        let height = bus.io.lcd.control.obj_height();
+        let mut selected_lines = Vec::new();
+        let mut dropped_lines = Vec::new();
+
+        for (ly, line) in scan_lines.iter().enumerate() {
+            if line.is_selected(oam_index) {
+                selected_lines.push(ly as u8);
+            }
+
+            if line.is_dropped(oam_index) {
+                dropped_lines.push(ly as u8);
+            }
+        }
+
+        Self {
+            oam_index,
+            entry,
+            height,
+            pixels: read_sprite_pixels(bus, &entry, height),
+            selected_lines,
+            dropped_lines,
+        }
+    }
+
+    pub fn is_selected(&self) -> bool {
+        !self.selected_lines.is_empty()
+    }
+
+    pub fn is_dropped(&self) -> bool {
+        !self.dropped_lines.is_empty()
+    }
+}
+
+/// Collects info about all 40 OAM entries.
+pub fn inspect_sprites(bus: &Bus, scan_lines: &[OamScanLine]) -> Vec<SpriteInfo> {
+    (0..OAM_ENTRIES_COUNT)
+        .map(|i| SpriteInfo::new(bus, i, scan_lines))
+        .collect()
+}
+
+fn read_sprite_pixels(
+    bus: &Bus,
+    entry: &OamEntry,
+    height: u8,
+) -> [[ColorId; TILE_WIDTH as usize]; MAX_SPRITE_HEIGHT] {
+    let mut pixels = [[ColorId::default(); TILE_WIDTH as usize]; MAX_SPRITE_HEIGHT];
+    let tile_index = if height == 16 {
+        entry.tile_index & !1
+    } else {
+        entry.tile_index
+    };
+    let tile_addr = TILE_SET_DATA_1_START + tile_index as u16 * TILE_BIT_SIZE;
+
+    for (y, row) in pixels.iter_mut().take(height as usize).enumerate() {
+        let src_y = if entry.f_y_flip() {
+            height as usize - 1 - y
+        } else {
+            y
+        };
+        // 8x16 sprites use two consecutive tiles, so the address just keeps going
+        let line = bus
+            .video_ram
+            .get_tile_line(tile_addr + (src_y * TILE_LINE_BYTES_COUNT) as u16);
+
+        for (x, color_id) in row.iter_mut().enumerate() {
+            let bit = if entry.f_x_flip() {
+                TILE_WIDTH as usize - 1 - x
+            } else {
+                x
+            };
+
+            *color_id = line.get_color_id(bit as u8);
+        }
+    }
+
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::sprite::SpriteFetcher;
+    use crate::ppu::vram::VRAM_ADDR_START;
+
+    #[test]
+    fn test_sprite_pixels_flip() {
+        let mut bus = Bus::with_bytes(vec![0; 0x10000]);
+        // tile 1, first line: leftmost pixel has color id 3
+        bus.video_ram.write(VRAM_ADDR_START + 16, 0b1000_0000);
+        bus.video_ram.write(VRAM_ADDR_START + 17, 0b1000_0000);
+        bus.oam_ram.entries[0].tile_index = 1;
+
+        let info = SpriteInfo::new(&bus, 0, &[]);
+        assert_eq!(info.pixels[0][0], ColorId::Darkest);
+        assert_eq!(info.pixels[0][7], ColorId::Lightest);
+
+        bus.oam_ram.entries[0].flags = 0b0110_0000; // x and y flip
+        let info = SpriteInfo::new(&bus, 0, &[]);
+        assert_eq!(info.pixels[7][7], ColorId::Darkest);
+        assert_eq!(info.pixels[0][0], ColorId::Lightest);
+    }
+
+    #[test]
+    fn test_sprites_over_line_limit_are_dropped() {
+        let mut bus = Bus::with_bytes(vec![0; 0x10000]);
+        let mut fetcher = SpriteFetcher::default();
+
+        for (i, entry) in bus.oam_ram.entries.iter_mut().take(12).enumerate() {
+            entry.y = 16;
+            entry.x = 8 + i as u8;
+        }
+
+        bus.io.lcd.ly = 0;
+        fetcher.load_line_sprites(&mut bus);
+        let sprites = inspect_sprites(&bus, &fetcher.scan_lines);
+
+        assert_eq!(fetcher.line_sprites.len(), 10);
+        assert!(sprites[..10]
+            .iter()
+            .all(|s| s.is_selected() && !s.is_dropped()));
+        assert!(sprites[10..12]
+            .iter()
+            .all(|s| !s.is_selected() && s.is_dropped()));
+        assert_eq!(sprites[11].dropped_lines, vec![0]);
+        assert!(!sprites[12].is_selected() && !sprites[12].is_dropped());
+    }
+}
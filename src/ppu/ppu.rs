@@ -3,29 +3,50 @@ use crate::bus::Bus;
 use crate::cpu::interrupts::InterruptType;
 use crate::ppu::lcd::{PpuMode, LcdStatSrc};
 use crate::ppu::fetcher::PixelFetcher;
+use crate::ppu::oam::OAM_ENTRIES_COUNT;
+use crate::ppu::tile::{ColorId, Pixel};
 use std::time::{Duration, Instant};
-use crate::auxiliary::clock::spin_wait;
+use crate::auxiliary::clock::precise_wait;
+use serde::{Deserialize, Serialize};
 
 pub const LINES_PER_FRAME: usize = 154;
 pub const TICKS_PER_LINE: usize = 456;
+pub const OAM_SCAN_TICKS: usize = 80;
+const STAT_MODE_DELAY: usize = 4;
+/// Mode 0 shows in STAT sooner, checked by mooneye intr_2_mode0_timing_sprites.
+const STAT_HBLANK_DELAY: usize = 2;
 pub const LCD_Y_RES: u8 = 144;
 pub const LCD_X_RES: u8 = 160;
 pub const TARGET_FPS_F: f64 = 60.0;
 pub const TARGET_FRAME_TIME_MILLIS: u64 = 1000 / 60;
 pub const LCD_PIXELS_COUNT: usize = LCD_Y_RES as usize * LCD_X_RES as usize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ppu {
     pub current_frame: usize,
     pub line_ticks: usize,
+    /// Wall clock times since `timer`, restarted when a save state is read.
+    #[serde(skip)]
     pub prev_frame_duration: Duration,
+    #[serde(skip)]
     pub frame_start_duration: Duration,
+    #[serde(skip)]
     pub last_frame_duration: Duration,
     pub target_frame_duration: Duration,
     pub frame_count: usize,
     pub fps: usize,
+    #[serde(skip, default = "Instant::now")]
     pub timer: Instant,
     pub pipeline: PixelFetcher,
+    /// All enabled STAT sources ORed, the interrupt is requested on its rising edge only.
+    stat_line: bool,
+    /// Mode 2 source, raised with the LY change before STAT reports the mode.
+    is_oam_stat: bool,
+    is_lcd_on: bool,
+    /// The first line after the LCD is turned on has no OAM scan.
+    is_first_line: bool,
+    /// Ticks since the PPU mode differs from the one reported in STAT.
+    stat_mode_ticks: usize,
 }
 
 impl Default for Ppu {
@@ -41,6 +62,11 @@ impl Default for Ppu {
             fps: 0,
             timer: Instant::now(),
             pipeline: Default::default(),
+            stat_line: false,
+            is_oam_stat: false,
+            is_lcd_on: true,
+            is_first_line: false,
+            stat_mode_ticks: 0,
         }
     }
 }
@@ -58,6 +84,11 @@ impl Ppu {
             fps: 0,
             timer: Instant::now(),
             line_ticks: 0,
+            stat_line: false,
+            is_oam_stat: false,
+            is_lcd_on: true,
+            is_first_line: false,
+            stat_mode_ticks: 0,
         }
     }
 
@@ -65,84 +96,236 @@ impl Ppu {
         self.target_frame_duration = Duration::from_secs_f64(1.0 / fps);
     }
 
+    /// Frames are not limited, e.g. when pacing is done by audio.
+    pub fn clear_fps_limit(&mut self) {
+        self.target_frame_duration = Duration::ZERO;
+    }
+
     pub fn tick(&mut self, bus: &mut Bus) {
+        if !bus.io.lcd.control.lcd_enable() {
+            self.tick_lcd_off(bus);
+            return;
+        }
+
+        if !self.is_lcd_on {
+            self.is_lcd_on = true;
+            self.is_first_line = true;
+            // the first line is 4 dots shorter
+            self.line_ticks = 4;
+        }
+
         self.line_ticks += 1;
 
         match bus.io.lcd.status.ppu_mode() {
             PpuMode::Oam => self.mode_oam(bus),
             PpuMode::Transfer => self.mode_transfer(bus),
-            PpuMode::HBlank => self.mode_hblank(&mut bus.io),
+            PpuMode::HBlank => self.mode_hblank(bus),
             PpuMode::VBlank => self.mode_vblank(&mut bus.io),
         }
+
+        self.update_stat_mode(&mut bus.io);
+        self.update_stat_line(&mut bus.io);
     }
 
-    pub fn mode_oam(&mut self, bus: &mut Bus) {
-        if self.line_ticks >= 80 {
-            bus.io.lcd.status.set_ppu_mode(PpuMode::Transfer);
-            self.pipeline.reset();
+    /// Ticks which `skip` can run at once. Only HBlank, VBlank and the LCD off period are
+    /// skipped, until the tick which ends the line or the frame.
+    pub fn get_ticks_to_event(&self, bus: &Bus) -> usize {
+        let lcd = &bus.io.lcd;
+
+        if !lcd.control.lcd_enable() {
+            if !self.is_lcd_on {
+                return (TICKS_PER_LINE * LINES_PER_FRAME).saturating_sub(self.line_ticks + 1);
+            }
+
+            return 0;
+        }
+
+        let mode = lcd.status.ppu_mode();
+        let is_idle = match mode {
+            PpuMode::HBlank => !self.is_first_line,
+            PpuMode::VBlank => true,
+            PpuMode::Oam | PpuMode::Transfer => false,
+        };
+
+        if !self.is_lcd_on || !is_idle || lcd.stat_mode != mode || self.line_ticks < STAT_MODE_DELAY
+        {
+            return 0;
+        }
+
+        TICKS_PER_LINE.saturating_sub(self.line_ticks + 1)
+    }
+
+    /// Runs ticks in which only the line counter advances, see `get_ticks_to_event`.
+    pub fn skip(&mut self, ticks: usize, bus: &mut Bus) {
+        self.line_ticks += ticks;
+
+        if self.is_lcd_on {
+            // STAT sources may have been changed by the CPU since the last tick
+            self.update_stat_line(&mut bus.io);
+        }
+    }
+
+    /// While the LCD is off LY stays 0 in mode 0 and the screen is blank, frames are still
+    /// counted so output and pacing go on.
+    fn tick_lcd_off(&mut self, bus: &mut Bus) {
+        if self.is_lcd_on {
+            self.is_lcd_on = false;
+            self.is_oam_stat = false;
+            self.line_ticks = 0;
+            bus.io.lcd.reset_ly();
+            bus.io.lcd.status.set_ppu_mode(PpuMode::HBlank);
+            bus.io.lcd.stat_mode = PpuMode::HBlank;
+            self.pipeline.clear();
+            self.pipeline.sprite_fetcher.clear_scan_lines();
+            self.pipeline
+                .buffer
+                .fill(Pixel::new(bus.io.lcd.bg_colors[0], ColorId::Lightest));
         }
 
-        // todo:
-        // GB fetches sprites progressively during the first 80 ticks of the scanline, not instantly
-        //if self.line_ticks % 2 == 0 && self.line_ticks < 80 {
-        //    self.pipeline.sprite_fetcher.load_next_sprite(bus);
-        //}
+        self.line_ticks += 1;
+
+        if self.line_ticks >= TICKS_PER_LINE * LINES_PER_FRAME {
+            self.line_ticks = 0;
+            self.finish_frame();
+        }
+    }
+
+    fn update_stat_mode(&mut self, io: &mut Io) {
+        if io.lcd.stat_mode == io.lcd.status.ppu_mode() {
+            return;
+        }
+
+        self.stat_mode_ticks += 1;
+        let delay = match io.lcd.status.ppu_mode() {
+            PpuMode::HBlank => STAT_HBLANK_DELAY,
+            _ => STAT_MODE_DELAY,
+        };
+
+        if self.stat_mode_ticks >= delay {
+            self.stat_mode_ticks = 0;
+            io.lcd.stat_mode = io.lcd.status.ppu_mode();
+        }
+    }
+
+    fn update_stat_line(&mut self, io: &mut Io) {
+        let lcd = &mut io.lcd;
+        // LY = LYC is not reported until the line is past its first M-cycle
+        lcd.status
+            .lyc_set(lcd.ly == lcd.ly_compare && self.line_ticks >= STAT_MODE_DELAY);
+        let status = lcd.status;
+
+        let is_mode_stat = match status.ppu_mode() {
+            PpuMode::HBlank => status.is_stat_interrupt(LcdStatSrc::HBlank),
+            PpuMode::VBlank => status.is_stat_interrupt(LcdStatSrc::VBlank),
+            PpuMode::Oam | PpuMode::Transfer => false,
+        };
+        let line = is_mode_stat
+            || self.is_oam_stat && status.is_stat_interrupt(LcdStatSrc::Oam)
+            || status.lyc() && status.is_stat_interrupt(LcdStatSrc::Lyc);
+
+        if line && !self.stat_line {
+            io.interrupts.request_interrupt(InterruptType::LCDStat);
+        }
+
+        self.stat_line = line;
+    }
+
+    pub fn mode_oam(&mut self, bus: &mut Bus) {
+        let lcd = &mut bus.io.lcd;
+        lcd.window.check_y(lcd.ly, lcd.control.win_enable());
+
+        // OAM is scanned progressively, one entry every 2 ticks
         if self.line_ticks == 1 {
-            // read oam on the first tick only
-            self.pipeline.sprite_fetcher.load_line_sprites(bus);
+            self.pipeline.sprite_fetcher.start_scan();
+        } else if self.line_ticks.is_multiple_of(2) {
+            let oam_index = self.line_ticks / 2 - 1;
+
+            if oam_index < OAM_ENTRIES_COUNT {
+                self.pipeline.sprite_fetcher.scan_entry(bus, oam_index);
+            }
+        }
+
+        if self.line_ticks >= OAM_SCAN_TICKS {
+            self.start_transfer(bus);
         }
     }
 
+    fn start_transfer(&mut self, bus: &mut Bus) {
+        self.is_oam_stat = false;
+        bus.io.lcd.status.set_ppu_mode(PpuMode::Transfer);
+        self.pipeline.reset(bus);
+    }
+
     fn mode_transfer(&mut self, bus: &mut Bus) {
-        self.pipeline.process(bus, self.line_ticks);
+        self.pipeline.process(bus);
 
         if self.pipeline.pushed_x >= LCD_X_RES {
+            if self.pipeline.is_window() {
+                bus.io.lcd.window.line_number += 1;
+            }
+
             self.pipeline.clear();
             bus.io.lcd.status.set_ppu_mode(PpuMode::HBlank);
-
-            if bus.io.lcd.status.is_stat_interrupt(LcdStatSrc::HBlank) {
-                bus.io.interrupts.request_interrupt(InterruptType::LCDStat);
-            }
         }
     }
 
     fn mode_vblank(&mut self, io: &mut Io) {
         if self.line_ticks >= TICKS_PER_LINE {
-            io.lcd.increment_ly(&mut io.interrupts);
+            io.lcd.increment_ly();
+            self.is_oam_stat = false;
 
             if io.lcd.ly as usize >= LINES_PER_FRAME {
                 io.lcd.status.set_ppu_mode(PpuMode::Oam);
-                io.lcd.reset_ly(&mut io.interrupts);
+                io.lcd.reset_ly();
+                self.is_oam_stat = true;
             }
 
             self.line_ticks = 0;
         }
     }
 
-    fn mode_hblank(&mut self, io: &mut Io) {
+    fn mode_hblank(&mut self, bus: &mut Bus) {
+        if self.is_first_line {
+            // mode 0 is reported instead of the OAM scan, so no sprites are drawn
+            let lcd = &mut bus.io.lcd;
+            lcd.window.check_y(lcd.ly, lcd.control.win_enable());
+
+            if self.line_ticks >= OAM_SCAN_TICKS {
+                self.is_first_line = false;
+                self.pipeline.sprite_fetcher.skip_scan(lcd.ly);
+                self.start_transfer(bus);
+            }
+
+            return;
+        }
+
+        let io = &mut bus.io;
+
         if self.line_ticks >= TICKS_PER_LINE {
-            io.lcd.increment_ly(&mut io.interrupts);
+            io.lcd.increment_ly();
 
             if io.lcd.ly >= LCD_Y_RES {
                 io.lcd.status.set_ppu_mode(PpuMode::VBlank);
                 io.interrupts.request_interrupt(InterruptType::VBlank);
-
-                if io.lcd.status.is_stat_interrupt(LcdStatSrc::VBlank) {
-                    io.interrupts.request_interrupt(InterruptType::LCDStat);
-                }
-
-                self.current_frame += 1;
-                self.calc_fps();
-                self.limit();
-                self.prev_frame_duration = self.timer.elapsed();
+                // the OAM source is raised at the start of VBlank too
+                self.is_oam_stat = true;
+                self.finish_frame();
             } else {
                 io.lcd.status.set_ppu_mode(PpuMode::Oam);
+                self.is_oam_stat = true;
             }
 
             self.line_ticks = 0;
         }
     }
 
+    fn finish_frame(&mut self) {
+        self.current_frame += 1;
+        self.calc_fps();
+        self.limit();
+        self.prev_frame_duration = self.timer.elapsed();
+    }
+
     pub fn calc_fps(&mut self) {
         let current_duration = self.timer.elapsed();
         self.last_frame_duration = current_duration - self.prev_frame_duration;
@@ -158,9 +341,110 @@ impl Ppu {
 
     pub fn limit(&self) {
         if self.last_frame_duration < self.target_frame_duration {
-            spin_wait(self.target_frame_duration - self.last_frame_duration);
+            precise_wait(self.target_frame_duration - self.last_frame_duration);
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::sprite::OamScanLine;
+
+    /// Ticks a whole line, returns the number of STAT interrupts requested.
+    fn count_stat_irqs(ppu: &mut Ppu, bus: &mut Bus) -> usize {
+        let mut count = 0;
+
+        for _ in 0..TICKS_PER_LINE {
+            ppu.tick(bus);
+
+            if bus.io.interrupts.int_flags & InterruptType::LCDStat as u8 != 0 {
+                bus.io.interrupts.int_flags = 0;
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    fn new_bus() -> Bus {
+        let mut bus = Bus::with_bytes(vec![0; 0x10000]);
+        bus.io.lcd.control.byte = 0x91;
+        bus.io.lcd.ly = 0;
+        bus.io.lcd.status.set_ppu_mode(PpuMode::Oam);
 
+        bus
+    }
+
+    #[test]
+    fn test_stat_irq_blocking() {
+        let mut ppu = Ppu::default();
+        let mut bus = new_bus();
+        bus.io.lcd.status.byte |= LcdStatSrc::HBlank as u8 | LcdStatSrc::Lyc as u8;
+        bus.io.lcd.ly_compare = 0;
+
+        // LY = LYC keeps the line high through HBlank
+        assert_eq!(count_stat_irqs(&mut ppu, &mut bus), 1);
+        assert_eq!(bus.io.lcd.ly, 1);
+        assert_eq!(count_stat_irqs(&mut ppu, &mut bus), 1);
+    }
+
+    #[test]
+    fn test_lcd_off() {
+        let mut ppu = Ppu::default();
+        let mut bus = new_bus();
+
+        for _ in 0..TICKS_PER_LINE * 3 + 100 {
+            ppu.tick(&mut bus);
+        }
+
+        bus.io.lcd.control.byte &= 0x7F;
+        ppu.tick(&mut bus);
+
+        assert_eq!(bus.io.lcd.ly, 0);
+        assert_eq!(bus.io.lcd.read(0xFF41) & 0b11, PpuMode::HBlank as u8);
+        assert!(ppu.pipeline.buffer.iter().all(|p| p.color_id == ColorId::Lightest));
+
+        bus.io.lcd.control.byte |= 0x80;
+
+        for _ in 0..OAM_SCAN_TICKS / 2 {
+            ppu.tick(&mut bus);
+        }
+
+        // no OAM scan on the first line
+        assert_eq!(bus.io.lcd.read(0xFF41) & 0b11, PpuMode::HBlank as u8);
+    }
+
+    #[test]
+    fn test_lcd_off_scan_lines() {
+        let mut ppu = Ppu::default();
+        let mut bus = new_bus();
+        // a sprite on the first two lines
+        bus.oam_ram.entries[3].y = 16;
+        bus.oam_ram.entries[3].x = 8;
+
+        for _ in 0..TICKS_PER_LINE * 2 {
+            ppu.tick(&mut bus);
+        }
+
+        let scan_lines = &ppu.pipeline.sprite_fetcher.scan_lines;
+        assert!(scan_lines[0].is_selected(3) && scan_lines[1].is_selected(3));
+
+        bus.io.lcd.control.byte &= 0x7F;
+        ppu.tick(&mut bus);
+        let scan_lines = &ppu.pipeline.sprite_fetcher.scan_lines;
+        assert!(scan_lines.iter().all(|line| *line == OamScanLine::default()));
+
+        // the first line is recorded without the sprite, the next one is scanned
+        ppu.pipeline.sprite_fetcher.scan_lines[0].selected = 1 << 3;
+        bus.io.lcd.control.byte |= 0x80;
+
+        for _ in 0..TICKS_PER_LINE * 2 {
+            ppu.tick(&mut bus);
+        }
+
+        let scan_lines = &ppu.pipeline.sprite_fetcher.scan_lines;
+        assert!(!scan_lines[0].is_selected(3));
+        assert!(scan_lines[1].is_selected(3));
+    }
+}
@@ -1,175 +1,148 @@
 use crate::bus::Bus;
-use crate::ppu::fetcher::MAX_FIFO_SPRITES_SIZE;
-use crate::ppu::lcd::Lcd;
-use crate::ppu::oam::OamEntry;
-use crate::ppu::tile::{
-    get_color_index, Pixel, TileLineData, TILE_BIT_SIZE, TILE_LINE_BYTES_COUNT,
-    TILE_SET_DATA_1_START,
-};
+use crate::ppu::oam::{OamEntry, OAM_ENTRIES_COUNT};
+use crate::ppu::tile::{TileLineData, TILE_BIT_SIZE, TILE_LINE_BYTES_COUNT, TILE_SET_DATA_1_START};
+use crate::ppu::LCD_Y_RES;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
-#[derive(Debug, Clone, Default, Copy)]
-pub struct SpriteFetchedData {
-    pub tile_line: TileLineData,
+pub const MAX_LINE_SPRITES: usize = 10;
+/// Dots spent on a single sprite fetch once the background fetcher is ready.
+pub const SPRITE_FETCH_DOTS: usize = 6;
+
+/// Result of the OAM scan for a single scanline as bitmasks over OAM entry indices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OamScanLine {
+    /// Entries loaded into `line_sprites`.
+    pub selected: u64,
+    /// Entries that were on the line but didn't fit into the 10 sprites per line limit.
+    pub dropped: u64,
 }
 
-#[derive(Debug, Clone, Default)]
+impl OamScanLine {
+    pub fn is_selected(&self, oam_index: usize) -> bool {
+        self.selected & (1 << oam_index) != 0
+    }
+
+    pub fn is_dropped(&self, oam_index: usize) -> bool {
+        self.dropped & (1 << oam_index) != 0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpriteFetcher {
+    /// Sprites of the current line in OAM order, X is not checked by the scan.
     pub line_sprites: VecDeque<OamEntry>,
-    pub fetched_sprites_count: usize,
-    pub fetched_sprites: [OamEntry; 3], //entries fetched during pipeline.
-    pub fetched_sprite_data: [SpriteFetchedData; 3],
+    /// OAM scan results of the last frame, one per visible scanline.
+    pub scan_lines: Vec<OamScanLine>,
+    scan_line: OamScanLine,
+    /// Bitmask over `line_sprites` of sprites already fetched on the line.
+    fetched: u16,
+}
+
+impl Default for SpriteFetcher {
+    fn default() -> Self {
+        Self {
+            line_sprites: Default::default(),
+            scan_lines: vec![OamScanLine::default(); LCD_Y_RES as usize],
+            scan_line: Default::default(),
+            fetched: 0,
+        }
+    }
 }
 
 impl SpriteFetcher {
+    /// Runs the whole OAM scan at once.
     pub fn load_line_sprites(&mut self, bus: &mut Bus) {
-        self.line_sprites.clear();
-        let cur_y: i32 = bus.io.lcd.ly as i32;
-        let sprite_height = bus.io.lcd.control.obj_height() as i32;
+        self.start_scan();
 
-        for ram_entry in bus.oam_ram.entries.iter() {
-            if ram_entry.x == 0 {
-                // Not visible (X = 0 means hidden on real hardware)
-                continue;
-            }
-
-            if self.line_sprites.len() >= MAX_FIFO_SPRITES_SIZE {
-                // Already reached max sprites per scanline (Game Boy limit = 10)
-                break;
-            }
-
-            // Check if the sprite is on the current scanline
-            if ram_entry.y as i32 <= cur_y + 16 && ram_entry.y as i32 + sprite_height > cur_y + 16 {
-                let mut inserted = false;
-
-                // Iterate through sorted list to insert at correct position
-                for i in 0..self.line_sprites.len() {
-                    let current_entry = &self.line_sprites[i];
-
-                    if ram_entry.x < current_entry.x && ram_entry.x != current_entry.x {
-                        // Sort by X first, then by OAM index if X is the same
-                        self.line_sprites.insert(i, ram_entry.to_owned());
-                        inserted = true;
-                        break;
-                    }
-                }
-
-                if !inserted {
-                    // If no earlier insertion, push to the back
-                    self.line_sprites.push_back(ram_entry.to_owned());
-                }
-            }
+        for oam_index in 0..OAM_ENTRIES_COUNT {
+            self.scan_entry(bus, oam_index);
         }
     }
 
-    pub fn fetch_sprite_tiles(&mut self, scroll_x: u8, fetch_x: u8) {
-        self.fetched_sprites_count = 0;
-        let fetch_x = fetch_x as i32;
+    pub fn start_scan(&mut self) {
+        self.line_sprites.clear();
+        self.scan_line = OamScanLine::default();
+        self.fetched = 0;
+    }
 
-        for sprite in self.line_sprites.iter() {
-            let sp_x = self.calc_sprite_x(sprite.x, scroll_x);
+    /// Checks a single OAM entry, the hardware takes 2 dots for each of them.
+    pub fn scan_entry(&mut self, bus: &Bus, oam_index: usize) {
+        let entry = bus.oam_ram.entries[oam_index];
+        let cur_y = bus.io.lcd.ly as i32 + 16;
+        let sprite_height = bus.io.lcd.control.obj_height() as i32;
 
-            if (sp_x >= fetch_x && sp_x < fetch_x + 8)
-                || (sp_x + 8 >= fetch_x && sp_x + 8 < fetch_x + 8)
-            {
-                // need to add
-                self.fetched_sprites[self.fetched_sprites_count] = sprite.to_owned();
-                self.fetched_sprites_count += 1;
+        if entry.y as i32 <= cur_y && entry.y as i32 + sprite_height > cur_y {
+            if self.line_sprites.len() >= MAX_LINE_SPRITES {
+                self.scan_line.dropped |= 1 << oam_index;
+            } else {
+                self.scan_line.selected |= 1 << oam_index;
+                self.line_sprites.push_back(entry);
             }
+        }
 
-            if self.fetched_sprites_count >= self.fetched_sprites.len() {
-                // max 3 sprites on pixels
-                break;
-            }
+        if oam_index == OAM_ENTRIES_COUNT - 1 {
+            self.finish_scan(bus.io.lcd.ly);
         }
     }
-    
-    fn calc_sprite_x(&self, sprite_x: u8, scroll_x: u8) -> i32 {
-        (sprite_x as i32 - 8) + (scroll_x as i32 % 8)
+
+    /// The first line after the LCD turns on has no OAM scan, it is recorded without sprites.
+    pub fn skip_scan(&mut self, ly: u8) {
+        self.start_scan();
+        self.finish_scan(ly);
     }
 
-    pub fn fetch_sprite_data(&mut self, bus: &Bus, byte_offset: u16) {
-        let cur_y: i32 = bus.io.lcd.ly as i32;
-        let sprite_height: u8 = bus.io.lcd.control.obj_height();
-
-        for i in 0..self.fetched_sprites_count {
-            let sprite = self.fetched_sprites[i];
-            let mut tile_y: u8 = cur_y
-                .wrapping_add(TILE_BIT_SIZE as i32)
-                .wrapping_sub(sprite.y as i32)
-                .wrapping_mul(TILE_LINE_BYTES_COUNT as i32) as u8;
-
-            if sprite.f_y_flip() {
-                tile_y = sprite_height
-                    .wrapping_mul(2)
-                    .wrapping_sub(2)
-                    .wrapping_sub(tile_y);
-            }
+    /// Forgets the scan results while the LCD is off.
+    pub fn clear_scan_lines(&mut self) {
+        self.scan_lines.fill(OamScanLine::default());
+    }
 
-            let tile_index = if sprite_height == 16 {
-                // remove last bit
-                sprite.tile_index & !1
-            } else {
-                sprite.tile_index
-            };
-
-            let addr = TILE_SET_DATA_1_START
-                .wrapping_add(tile_index as u16 * TILE_BIT_SIZE)
-                .wrapping_add(tile_y as u16)
-                .wrapping_add(byte_offset);
-
-            match byte_offset {
-                0 => self.fetched_sprite_data[i].tile_line.byte1 = bus.read(addr),
-                1 => self.fetched_sprite_data[i].tile_line.byte2 = bus.read(addr),
-                _ => unreachable!(),
-            }
+    fn finish_scan(&mut self, ly: u8) {
+        if let Some(line) = self.scan_lines.get_mut(ly as usize) {
+            *line = self.scan_line;
         }
     }
 
-    pub fn fetch_sprite_pixel(
-        &self,
-        lcd: &Lcd,
-        fifo_x: u8,
-        bg_color_index: usize,
-    ) -> Option<Pixel> {
-        for i in 0..self.fetched_sprites_count {
-            let sprite = self.fetched_sprites[i];
-            let sprite_x = self.calc_sprite_x(sprite.x, lcd.scroll_x);
-
-            if sprite_x + 8 < fifo_x as i32 {
-                continue; // Skip past sprites
-            }
-
-            let offset = fifo_x as i32 - sprite_x;
-            if !(0..=7).contains(&offset) {
-                continue; // Out of sprite range
-            }
-
-            let bit = if self.fetched_sprites[i].f_x_flip() {
-                7 - offset
-            } else {
-                offset
-            };
+    /// Returns the index of the first not yet fetched sprite that starts at the pixel.
+    /// Sprites partially hidden on the left start at the first pixel, X = 0 ones too
+    /// even though nothing of them is visible.
+    pub fn find_sprite(&self, pushed_x: u8, is_line_start: bool) -> Option<usize> {
+        self.line_sprites
+            .iter()
+            .enumerate()
+            .find(|(i, sprite)| {
+                self.fetched & (1 << i) == 0
+                    && if sprite.x < 8 {
+                        is_line_start
+                    } else {
+                        sprite.x as u16 == pushed_x as u16 + 8
+                    }
+            })
+            .map(|(i, _)| i)
+    }
 
-            let data = self.fetched_sprite_data[i];
-            let color_index =
-                get_color_index(data.tile_line.byte1, data.tile_line.byte2, bit as u8);
+    /// Marks the sprite as fetched and reads its line, flips are applied by the caller.
+    pub fn fetch_sprite_line(&mut self, bus: &Bus, idx: usize) -> TileLineData {
+        self.fetched |= 1 << idx;
+        let sprite = self.line_sprites[idx];
+        let sprite_height = bus.io.lcd.control.obj_height();
+        let mut tile_y = (bus.io.lcd.ly as i32 + 16 - sprite.y as i32) as u8 % sprite_height;
 
-            if color_index == 0 {
-                continue; // Transparent
-            }
+        if sprite.f_y_flip() {
+            tile_y = sprite_height - 1 - tile_y;
+        }
 
-            if !self.fetched_sprites[i].f_bgp() || bg_color_index == 0 {
-                let color = if self.fetched_sprites[i].f_pn() {
-                    lcd.sp2_colors[color_index]
-                } else {
-                    lcd.sp1_colors[color_index]
-                };
+        let tile_index = if sprite_height == 16 {
+            // remove last bit
+            sprite.tile_index & !1
+        } else {
+            sprite.tile_index
+        };
 
-                return Some(Pixel::new(color, color_index.into()));
-            }
-        }
+        let addr = TILE_SET_DATA_1_START
+            .wrapping_add(tile_index as u16 * TILE_BIT_SIZE)
+            .wrapping_add(tile_y as u16 * TILE_LINE_BYTES_COUNT as u16);
 
-        None
+        bus.video_ram.get_tile_line(addr)
     }
 }
@@ -1,5 +1,6 @@
 use crate::hex_to_rgba;
 use crate::ppu::vram::{VRAM_ADDR_END, VRAM_ADDR_START};
+use serde::{Deserialize, Serialize};
 
 // Tile sets addresses
 pub const TILE_SET_DATA_1_START: u16 = VRAM_ADDR_START;
@@ -40,7 +41,7 @@ pub struct PixelData {
     pub bit: u8,
 }
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Pixel {
     pub color: PixelColor,
     pub color_id: ColorId,
@@ -62,7 +63,7 @@ impl PixelData {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ColorId {
     #[default]
     Lightest,
@@ -136,7 +137,7 @@ impl Iterator for TileLineIterator {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PixelColor {
     hex: u32,
 }
@@ -3,6 +3,7 @@ use crate::ppu::tile::{
     BG_TILE_MAP_2_ADDR_END, BG_TILE_MAP_2_ADDR_START, TILE_BIT_SIZE, TILE_HEIGHT,
     TILE_LINE_BYTES_COUNT, TILE_SET_2_END, TILE_SET_DATA_1_START,
 };
+use serde::{Deserialize, Serialize};
 
 pub const VRAM_SIZE: usize = 0x2000;
 pub const VRAM_ADDR_START: u16 = 0x8000;
@@ -28,8 +29,9 @@ impl From<u16> for VRamAddressLocation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VideoRam {
+    #[serde(with = "crate::big_array")]
     pub bytes: [u8; VRAM_SIZE],
 }
 
@@ -1,44 +1,33 @@
-use crate::bus::Bus;
 use crate::ppu::lcd::Lcd;
-use crate::ppu::{LCD_X_RES, LCD_Y_RES};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Window {
     // registers
     pub y: u8,
     pub x: u8,
     // additional data
+    /// Internal line counter, only advanced on lines where the window was drawn.
     pub line_number: u8,
+    /// Set once WY matched LY with the window enabled, stays on for the rest of the frame.
+    pub is_y_triggered: bool,
 }
 
 impl Window {
-    pub fn on(&self, lcd: &Lcd) -> bool {
-        self.is_visible(lcd) && lcd.ly >= self.y && (lcd.ly as u16) < (self.y as u16 + LCD_Y_RES as u16)
-    }
-    pub fn is_visible(&self, lcd: &Lcd) -> bool {
-        lcd.control.win_enable() && self.x <= 166 && self.y < LCD_Y_RES
-    }
-
-    pub fn get_tile_idx(&self, fetch_x: u16, bus: &Bus) -> Option<u8> {
-        if !self.is_visible(&bus.io.lcd) {
-            return None;
+    /// Checks the WY condition, the hardware does it during the OAM scan of every line.
+    pub fn check_y(&mut self, lcd_ly: u8, is_enabled: bool) {
+        if is_enabled && lcd_ly == self.y {
+            self.is_y_triggered = true;
         }
+    }
 
-        let fetch_x = fetch_x + 7;
-
-        if fetch_x >= self.x as u16
-            && fetch_x < self.x as u16 + LCD_X_RES as u16 + 14
-            && bus.io.lcd.ly as u16 >= self.y as u16
-            && (bus.io.lcd.ly as u16) < self.y as u16 + LCD_Y_RES as u16
-        {
-            let w_tile_x = (fetch_x - self.x as u16) / 8; // Convert pixel X to tile X
-            let w_tile_y = (self.line_number / 8) as u16; // Convert pixel Y to tile Y
-            let area = bus.io.lcd.control.win_map_area(); // Get window tile map base address (0x9800 or 0x9C00)
-            let addr = area + w_tile_x + (w_tile_y * 32); // Calculate correct tile map index
-
-            return Some(bus.read(addr)); // Fetch tile index from VRAM
-        }
+    /// Whether the window can be triggered on the current line, WX is checked by the fetcher.
+    pub fn on(&self, lcd: &Lcd) -> bool {
+        lcd.control.win_enable() && self.is_y_triggered
+    }
 
-        None
+    pub fn reset(&mut self) {
+        self.line_number = 0;
+        self.is_y_triggered = false;
     }
 }
@@ -0,0 +1,180 @@
+use crate::bus::Bus;
+use std::ops::RangeInclusive;
+
+/// Writable memory worth searching: external RAM, WRAM and HRAM.
+pub const SEARCH_RANGES: [RangeInclusive<u16>; 3] =
+    [0xA000..=0xBFFF, 0xC000..=0xDFFF, 0xFF80..=0xFFFE];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchWidth {
+    U8,
+    /// Little-endian, same as the CPU stores 16-bit values.
+    U16,
+}
+
+impl SearchWidth {
+    pub fn bytes(&self) -> u16 {
+        match self {
+            SearchWidth::U8 => 1,
+            SearchWidth::U16 => 2,
+        }
+    }
+
+    pub fn read(&self, bus: &Bus, address: u16) -> u16 {
+        match self {
+            SearchWidth::U8 => bus.read(address) as u16,
+            SearchWidth::U16 => {
+                bus.read(address) as u16 | (bus.read(address.wrapping_add(1)) as u16) << 8
+            }
+        }
+    }
+}
+
+/// Compares the current value of a candidate against its value at the previous step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchFilter {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    Value(u16),
+}
+
+impl SearchFilter {
+    pub fn matches(&self, prev: u16, curr: u16) -> bool {
+        match self {
+            SearchFilter::Equal => curr == prev,
+            SearchFilter::Changed => curr != prev,
+            SearchFilter::Increased => curr > prev,
+            SearchFilter::Decreased => curr < prev,
+            SearchFilter::Value(value) => curr == *value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchCandidate {
+    pub address: u16,
+    /// Value at the last snapshot or filter step.
+    pub value: u16,
+}
+
+/// Classic cheat-finder: snapshot all RAM, then narrow the candidates down with filters,
+/// each one comparing against the values seen at the previous step.
+#[derive(Debug, Clone)]
+pub struct RamSearch {
+    pub width: SearchWidth,
+    pub candidates: Vec<SearchCandidate>,
+    /// Number of filters applied since the snapshot.
+    pub steps: usize,
+}
+
+impl RamSearch {
+    /// Starts a new search with every address of `SEARCH_RANGES` as a candidate.
+    pub fn new(bus: &Bus, width: SearchWidth) -> Self {
+        let mut candidates = Vec::new();
+
+        for range in SEARCH_RANGES.iter() {
+            // 16-bit values must fit in the range
+            let end = range.end() + 1 - width.bytes();
+
+            for address in *range.start()..=end {
+                candidates.push(SearchCandidate {
+                    address,
+                    value: width.read(bus, address),
+                });
+            }
+        }
+
+        Self {
+            width,
+            candidates,
+            steps: 0,
+        }
+    }
+
+    /// Keeps candidates matching the filter and remembers their current values for the next step.
+    pub fn filter(&mut self, bus: &Bus, filter: SearchFilter) {
+        let width = self.width;
+
+        self.candidates.retain_mut(|candidate| {
+            let curr = width.read(bus, candidate.address);
+            let is_match = filter.matches(candidate.value, curr);
+            candidate.value = curr;
+
+            is_match
+        });
+        self.steps += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_bus() -> Bus {
+        Bus::with_bytes(vec![0; 0x10000])
+    }
+
+    #[test]
+    fn test_snapshot_size() {
+        let bus = new_bus();
+
+        let search = RamSearch::new(&bus, SearchWidth::U8);
+        assert_eq!(search.len(), 0x2000 + 0x2000 + 0x7F);
+
+        let search = RamSearch::new(&bus, SearchWidth::U16);
+        assert_eq!(search.len(), 0x1FFF + 0x1FFF + 0x7E);
+    }
+
+    #[test]
+    fn test_filters_8bit() {
+        let mut bus = new_bus();
+        bus.write(0xC100, 3);
+        let mut search = RamSearch::new(&bus, SearchWidth::U8);
+
+        bus.write(0xC100, 2);
+        bus.write(0xC200, 7);
+        search.filter(&bus, SearchFilter::Changed);
+        assert_eq!(search.len(), 2);
+
+        search.filter(&bus, SearchFilter::Equal);
+        assert_eq!(search.len(), 2);
+
+        bus.write(0xC100, 1);
+        bus.write(0xC200, 8);
+        let mut decreased = search.clone();
+        decreased.filter(&bus, SearchFilter::Decreased);
+        assert_eq!(decreased.candidates[0].address, 0xC100);
+        search.filter(&bus, SearchFilter::Increased);
+        assert_eq!(search.candidates[0].address, 0xC200);
+        assert_eq!(search.candidates[0].value, 8);
+        assert_eq!(search.steps, 3);
+    }
+
+    #[test]
+    fn test_filter_value_16bit() {
+        let mut bus = new_bus();
+        let mut search = RamSearch::new(&bus, SearchWidth::U16);
+
+        bus.write(0xFF90, 0x34);
+        bus.write(0xFF91, 0x12);
+        search.filter(&bus, SearchFilter::Value(0x1234));
+
+        assert_eq!(
+            search.candidates,
+            vec![SearchCandidate {
+                address: 0xFF90,
+                value: 0x1234
+            }]
+        );
+    }
+}
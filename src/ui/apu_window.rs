@@ -0,0 +1,248 @@
+use crate::apu::channels::channel::ChannelInfo;
+use crate::apu::scope::{trigger_window, SCOPE_SIZE};
+use crate::apu::Apu;
+use crate::ppu::tile::PixelColor;
+use crate::ui::text::{draw_text, fill_texture, CHAR_HEIGHT};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Canvas, Texture};
+use sdl2::video::{Window, WindowPos};
+use sdl2::VideoSubsystem;
+
+pub const WIDTH: u32 = 640;
+pub const CHANNEL_HEIGHT: u32 = 100;
+pub const INFO_WIDTH: u32 = 200;
+pub const WAVE_BAR_WIDTH: u32 = 12;
+pub const WAVE_BAR_SCALE: u32 = 4;
+pub const PADDING: u32 = 6;
+const WAVE_HEIGHT: u32 = 15 * WAVE_BAR_SCALE + CHAR_HEIGHT as u32 * 2 + PADDING * 4;
+const LINE_HEIGHT: usize = CHAR_HEIGHT + 4;
+
+const BG_COLOR: Color = Color::RGB(18, 18, 18);
+const GRID_COLOR: Color = Color::RGB(45, 45, 45);
+const TRACE_COLOR: Color = Color::RGB(60, 180, 75);
+const SILENT_TRACE_COLOR: Color = Color::RGB(90, 90, 90);
+const WAVE_COLOR: Color = Color::RGB(70, 130, 220);
+const WAVE_POS_COLOR: Color = Color::RGB(230, 200, 60);
+const TEXT_COLOR: PixelColor = PixelColor::from_hex(0xFFE0E0E0);
+const MUTED_TEXT_COLOR: PixelColor = PixelColor::from_hex(0xFFE05070);
+
+/// Live oscilloscope and state of the four APU channels with CH3 wave RAM.
+/// Keys 1-4 mute channels, Q W E R solo them.
+pub struct ApuWindow {
+    pub canvas: Canvas<Window>,
+    text_texture: Texture,
+    height: u32,
+}
+
+impl ApuWindow {
+    pub fn new(video_subsystem: &VideoSubsystem) -> ApuWindow {
+        let height = CHANNEL_HEIGHT * 4 + WAVE_HEIGHT + LINE_HEIGHT as u32 + PADDING;
+
+        let window = video_subsystem
+            .window("APU Channels", WIDTH, height)
+            .position_centered()
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        let mut text_texture = canvas
+            .texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGBA32, WIDTH, height)
+            .unwrap();
+        text_texture.set_blend_mode(BlendMode::Blend);
+
+        Self {
+            canvas,
+            text_texture,
+            height,
+        }
+    }
+
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.canvas
+            .window_mut()
+            .set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    }
+
+    pub fn handle_key(&mut self, apu: &mut Apu, keycode: Keycode) {
+        let mixer = apu.get_mixer_mut();
+
+        match keycode {
+            Keycode::NUM_1 => mixer.muted[0] = !mixer.muted[0],
+            Keycode::NUM_2 => mixer.muted[1] = !mixer.muted[1],
+            Keycode::NUM_3 => mixer.muted[2] = !mixer.muted[2],
+            Keycode::NUM_4 => mixer.muted[3] = !mixer.muted[3],
+            Keycode::Q => mixer.soloed[0] = !mixer.soloed[0],
+            Keycode::W => mixer.soloed[1] = !mixer.soloed[1],
+            Keycode::E => mixer.soloed[2] = !mixer.soloed[2],
+            Keycode::R => mixer.soloed[3] = !mixer.soloed[3],
+            _ => (),
+        }
+    }
+
+    pub fn draw(&mut self, apu: &Apu) {
+        self.canvas.set_draw_color(BG_COLOR);
+        self.canvas.clear();
+        fill_texture(&mut self.text_texture, PixelColor::from_hex(0));
+
+        for (idx, info) in apu.get_channels_info().iter().enumerate() {
+            let y = (idx as u32 * CHANNEL_HEIGHT) as i32;
+            self.draw_channel_info(apu, info, idx, y);
+            self.draw_trace(apu, info, idx, y);
+        }
+
+        self.draw_wave_ram(apu, (CHANNEL_HEIGHT * 4) as i32);
+        draw_text(
+            &mut self.text_texture,
+            "1 4 MUTE  Q W E R SOLO",
+            TEXT_COLOR,
+            PADDING as usize,
+            (self.height - LINE_HEIGHT as u32) as usize,
+            1,
+        );
+
+        self.canvas
+            .copy(
+                &self.text_texture,
+                None,
+                Rect::new(0, 0, WIDTH, self.height),
+            )
+            .unwrap();
+        self.canvas.present();
+    }
+
+    fn draw_channel_info(&mut self, apu: &Apu, info: &ChannelInfo, idx: usize, y: i32) {
+        let mixer = apu.get_mixer();
+        let status = if mixer.soloed[idx] {
+            " SOLO"
+        } else if mixer.muted[idx] {
+            " MUTE"
+        } else {
+            ""
+        };
+        let lines = [
+            format!(
+                "CH{} {}{}",
+                idx + 1,
+                if info.is_on { "ON" } else { "OFF" },
+                if info.is_dac_enabled { "" } else { " NO DAC" }
+            ),
+            format!("FREQ {} HZ", info.frequency as u32),
+            match info.duty {
+                Some(duty) => format!("DUTY {}", duty),
+                None => String::new(),
+            },
+            format!("VOL {}", info.volume),
+            format!(
+                "LEN {}{}",
+                info.length_counter,
+                if info.is_length_enabled { " ON" } else { "" }
+            ),
+        ];
+        let text_color = if mixer.is_audible(idx) {
+            TEXT_COLOR
+        } else {
+            MUTED_TEXT_COLOR
+        };
+        let mut text_y = y as usize + PADDING as usize;
+
+        for (i, line) in lines.iter().enumerate() {
+            let line = if i == 0 {
+                format!("{}{}", line, status)
+            } else {
+                line.to_owned()
+            };
+            draw_text(
+                &mut self.text_texture,
+                &line,
+                text_color,
+                PADDING as usize,
+                text_y,
+                1,
+            );
+            text_y += LINE_HEIGHT;
+        }
+    }
+
+    fn draw_trace(&mut self, apu: &Apu, info: &ChannelInfo, idx: usize, y: i32) {
+        let x = INFO_WIDTH as i32;
+        let width = WIDTH - INFO_WIDTH - PADDING;
+        let height = CHANNEL_HEIGHT - PADDING * 2;
+        let rect = Rect::new(x, y + PADDING as i32, width, height);
+        let mid_y = rect.y() + height as i32 / 2;
+
+        self.canvas.set_draw_color(GRID_COLOR);
+        self.canvas.draw_rect(rect).unwrap();
+        self.canvas
+            .draw_line(Point::new(x, mid_y), Point::new(x + width as i32, mid_y))
+            .unwrap();
+
+        let samples = apu.get_scope().get_samples(idx);
+        let window = trigger_window(&samples, SCOPE_SIZE / 2);
+        let points: Vec<Point> = (0..width)
+            .map(|px| {
+                let sample = window[px as usize * window.len() / width as usize];
+                let py = mid_y - (sample.clamp(-1.0, 1.0) * (height / 2 - 1) as f32) as i32;
+
+                Point::new(x + px as i32, py)
+            })
+            .collect();
+
+        let color = if info.is_on && apu.get_mixer().is_audible(idx) {
+            TRACE_COLOR
+        } else {
+            SILENT_TRACE_COLOR
+        };
+        self.canvas.set_draw_color(color);
+        self.canvas.draw_lines(points.as_slice()).unwrap();
+    }
+
+    fn draw_wave_ram(&mut self, apu: &Apu, y: i32) {
+        let wave_ram = apu.get_wave_ram();
+        let samples = wave_ram.get_samples();
+        let base_y = y + (PADDING * 2 + LINE_HEIGHT as u32 + 15 * WAVE_BAR_SCALE) as i32;
+
+        draw_text(
+            &mut self.text_texture,
+            "CH3 WAVE RAM",
+            TEXT_COLOR,
+            PADDING as usize,
+            (y + PADDING as i32) as usize,
+            1,
+        );
+
+        for (i, sample) in samples.iter().enumerate() {
+            let bar_height = *sample as u32 * WAVE_BAR_SCALE;
+            let color = if i == wave_ram.get_sample_index() {
+                WAVE_POS_COLOR
+            } else {
+                WAVE_COLOR
+            };
+
+            self.canvas.set_draw_color(color);
+            self.canvas
+                .fill_rect(Rect::new(
+                    PADDING as i32 + (i as u32 * WAVE_BAR_WIDTH) as i32,
+                    base_y - bar_height as i32,
+                    WAVE_BAR_WIDTH - 2,
+                    bar_height.max(1),
+                ))
+                .unwrap();
+        }
+
+        let hex: String = samples
+            .chunks(2)
+            .map(|pair| format!("{:X}{:X}", pair[0], pair[1]))
+            .collect::<Vec<_>>()
+            .join(" ");
+        draw_text(
+            &mut self.text_texture,
+            &hex,
+            TEXT_COLOR,
+            PADDING as usize,
+            (base_y + PADDING as i32) as usize,
+            1,
+        );
+    }
+}
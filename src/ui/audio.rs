@@ -1,19 +1,32 @@
-use crate::apu::{Apu, AUDIO_BUFFER_SIZE, SAMPLING_FREQ};
+use crate::apu::{Apu, AUDIO_BUFFER_SIZE};
+use crate::config::AudioConfig;
 use sdl2::audio::{AudioQueue, AudioSpecDesired};
 use sdl2::{AudioSubsystem, Sdl};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CHANNELS: u32 = 2;
+const BYTES_PER_FRAME: u32 = CHANNELS * size_of::<f32>() as u32;
+/// Largest resampling adjustment, small enough to not be heard as pitch change.
+const MAX_RATIO_DELTA: f64 = 0.005;
+const MAX_UNSYNCED_LATENCY_FACTOR: f64 = 4.0;
+const SYNC_SLEEP: Duration = Duration::from_millis(1);
+/// Stops waiting for a device that does not play, so emulation never hangs on it.
+const MAX_SYNC_WAIT: Duration = Duration::from_millis(100);
 
 pub struct GameAudio {
     device: AudioQueue<f32>,
+    config: AudioConfig,
     _audio_subsystem: AudioSubsystem,
 }
 
 impl GameAudio {
-    pub fn new(sdl: &Sdl) -> Self {
+    pub fn new(sdl: &Sdl, config: &AudioConfig) -> Self {
         let audio_subsystem = sdl.audio().unwrap();
 
         let desired_spec = AudioSpecDesired {
-            freq: Some(SAMPLING_FREQ as i32),
-            channels: Some(2),
+            freq: Some(config.sample_rate as i32),
+            channels: Some(CHANNELS as u8),
             samples: Some(AUDIO_BUFFER_SIZE as u16),
         };
 
@@ -24,16 +37,81 @@ impl GameAudio {
 
         Self {
             device,
-
+            config: config.clone(),
             _audio_subsystem: audio_subsystem,
         }
     }
 
-    pub fn play(&mut self, apu: &mut Apu) -> Result<(), String> {
-        if apu.output_ready() {
-            self.device.queue_audio(apu.take_output())?;
+    /// Milliseconds of audio queued and not yet played.
+    pub fn get_queued_ms(&self) -> f64 {
+        let frames = self.device.size() / BYTES_PER_FRAME;
+
+        frames as f64 * 1000.0 / self.config.sample_rate as f64
+    }
+
+    /// Queues ready output. With `sync` it blocks while more than the target latency is queued,
+    /// which paces emulation, and nudges the resampling ratio to keep the queue near the
+    /// target. Without it output is dropped once the queue gets far behind, e.g. in turbo mode.
+    pub fn play(&mut self, apu: &mut Apu, sync: bool) -> Result<(), String> {
+        if !apu.output_ready() {
+            return Ok(());
         }
 
-        Ok(())
+        let target_ms = self.config.latency_ms as f64;
+
+        if sync {
+            let start = Instant::now();
+
+            while self.get_queued_ms() > target_ms && start.elapsed() < MAX_SYNC_WAIT {
+                thread::sleep(SYNC_SLEEP);
+            }
+
+            apu.set_resample_ratio(calc_resample_ratio(self.get_queued_ms(), target_ms));
+        } else {
+            apu.set_resample_ratio(1.0);
+
+            if self.get_queued_ms() > target_ms * MAX_UNSYNCED_LATENCY_FACTOR {
+                apu.take_output();
+                return Ok(());
+            }
+        }
+
+        let output = apu.take_output();
+
+        if self.config.volume >= 1.0 {
+            return self.device.queue_audio(output);
+        }
+
+        let output: Vec<f32> = output.iter().map(|x| x * self.config.volume).collect();
+        self.device.queue_audio(&output)
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.config.volume = volume.clamp(0.0, 1.0);
+    }
+}
+
+/// Returns more than 1 when the queue is below the target so it fills up, less when above.
+pub fn calc_resample_ratio(queued_ms: f64, target_ms: f64) -> f64 {
+    if target_ms <= 0.0 {
+        return 1.0;
+    }
+
+    let error = ((target_ms - queued_ms) / target_ms).clamp(-1.0, 1.0);
+
+    1.0 + error * MAX_RATIO_DELTA
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample_ratio() {
+        assert_eq!(calc_resample_ratio(60.0, 60.0), 1.0);
+        assert_eq!(calc_resample_ratio(0.0, 60.0), 1.0 + MAX_RATIO_DELTA);
+        assert_eq!(calc_resample_ratio(500.0, 60.0), 1.0 - MAX_RATIO_DELTA);
+        assert!(calc_resample_ratio(50.0, 60.0) > 1.0);
+        assert!(calc_resample_ratio(70.0, 60.0) < 1.0);
     }
 }
@@ -0,0 +1,237 @@
+use crate::ppu::{LCD_X_RES, LCD_Y_RES};
+use crate::ui::filters::Image;
+use png::{ColorType, Decoder, Transformations};
+use sdl2::rect::Rect;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+
+/// SGB sized border, the screen is drawn in its center.
+pub const BORDER_WIDTH: u32 = 256;
+pub const BORDER_HEIGHT: u32 = 224;
+const BORDER_SCREEN_X: u32 = (BORDER_WIDTH - LCD_X_RES as u32) / 2;
+const BORDER_SCREEN_Y: u32 = (BORDER_HEIGHT - LCD_Y_RES as u32) / 2;
+
+/// How the screen (or the border around it) is fitted into the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum DisplayMode {
+    /// Largest size with square pixels, the scale may be fractional.
+    #[default]
+    AspectFit,
+    /// Largest whole scale, the rest of the window is letterboxed.
+    Integer,
+    /// Fills the whole window ignoring the aspect ratio.
+    Stretch,
+}
+
+impl DisplayMode {
+    pub fn get_next(&self) -> DisplayMode {
+        match self {
+            DisplayMode::AspectFit => DisplayMode::Integer,
+            DisplayMode::Integer => DisplayMode::Stretch,
+            DisplayMode::Stretch => DisplayMode::AspectFit,
+        }
+    }
+}
+
+/// Where the border and the screen are drawn in the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayRects {
+    pub border: Option<Rect>,
+    pub screen: Rect,
+}
+
+impl DisplayRects {
+    pub fn new(window_width: u32, window_height: u32, mode: DisplayMode, has_border: bool) -> Self {
+        let (content_width, content_height) = get_content_size(has_border);
+        let content = get_content_rect(
+            window_width,
+            window_height,
+            content_width,
+            content_height,
+            mode,
+        );
+
+        if !has_border {
+            return Self {
+                border: None,
+                screen: content,
+            };
+        }
+
+        let scale_x = content.width() as f32 / BORDER_WIDTH as f32;
+        let scale_y = content.height() as f32 / BORDER_HEIGHT as f32;
+        let screen = Rect::new(
+            content.x() + (BORDER_SCREEN_X as f32 * scale_x).round() as i32,
+            content.y() + (BORDER_SCREEN_Y as f32 * scale_y).round() as i32,
+            (LCD_X_RES as f32 * scale_x).round() as u32,
+            (LCD_Y_RES as f32 * scale_y).round() as u32,
+        );
+
+        Self {
+            border: Some(content),
+            screen,
+        }
+    }
+}
+
+/// Size of what is scaled into the window: the screen or the border around it.
+pub fn get_content_size(has_border: bool) -> (u32, u32) {
+    if has_border {
+        (BORDER_WIDTH, BORDER_HEIGHT)
+    } else {
+        (LCD_X_RES as u32, LCD_Y_RES as u32)
+    }
+}
+
+/// Window size which the integer mode fills without letterboxing.
+pub fn snap_window_size(window_width: u32, window_height: u32, has_border: bool) -> (u32, u32) {
+    let (content_width, content_height) = get_content_size(has_border);
+    let scale = get_integer_scale(window_width, window_height, content_width, content_height);
+
+    (content_width * scale, content_height * scale)
+}
+
+fn get_integer_scale(
+    window_width: u32,
+    window_height: u32,
+    content_width: u32,
+    content_height: u32,
+) -> u32 {
+    (window_width / content_width)
+        .min(window_height / content_height)
+        .max(1)
+}
+
+fn get_content_rect(
+    window_width: u32,
+    window_height: u32,
+    content_width: u32,
+    content_height: u32,
+    mode: DisplayMode,
+) -> Rect {
+    let (width, height) = match mode {
+        DisplayMode::Stretch => (window_width, window_height),
+        DisplayMode::Integer => {
+            let scale =
+                get_integer_scale(window_width, window_height, content_width, content_height);
+            (content_width * scale, content_height * scale)
+        }
+        DisplayMode::AspectFit => {
+            let window_aspect = window_width as f32 / window_height as f32;
+            let content_aspect = content_width as f32 / content_height as f32;
+
+            if window_aspect > content_aspect {
+                // Window is wider than content: Fit height, adjust width
+                let width = (window_height as f32 * content_aspect) as u32;
+                (width, window_height)
+            } else {
+                // Window is taller than content: Fit width, adjust height
+                let height = (window_width as f32 / content_aspect) as u32;
+                (window_width, height)
+            }
+        }
+    };
+
+    // Center the image in the window, a too small window crops it from the top left
+    let x = (window_width.saturating_sub(width) / 2) as i32;
+    let y = (window_height.saturating_sub(height) / 2) as i32;
+
+    Rect::new(x, y, width.max(1), height.max(1))
+}
+
+/// Loads a 256x224 PNG border, the screen area is covered by the game.
+pub fn load_border(path: &str) -> Result<Image, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut decoder = Decoder::new(file);
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+
+    if info.width != BORDER_WIDTH || info.height != BORDER_HEIGHT {
+        return Err(format!(
+            "Border must be {}x{}, got {}x{}",
+            BORDER_WIDTH, BORDER_HEIGHT, info.width, info.height
+        ));
+    }
+
+    let pixels = data[..info.buffer_size()]
+        .chunks(info.line_size)
+        .flat_map(|line| {
+            line.chunks(info.color_type.samples())
+                .map(|pixel| match info.color_type {
+                    ColorType::Grayscale => [pixel[0], pixel[0], pixel[0], 0xFF],
+                    ColorType::GrayscaleAlpha => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                    ColorType::Rgb => [pixel[0], pixel[1], pixel[2], 0xFF],
+                    _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    Ok(Image {
+        width: BORDER_WIDTH as usize,
+        height: BORDER_HEIGHT as usize,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_modes() {
+        let rects = DisplayRects::new(500, 400, DisplayMode::Integer, false);
+        assert_eq!(rects.screen, Rect::new(90, 56, 320, 288));
+        assert_eq!(rects.border, None);
+
+        let rects = DisplayRects::new(500, 400, DisplayMode::AspectFit, false);
+        assert_eq!(rects.screen, Rect::new(28, 0, 444, 400));
+
+        let rects = DisplayRects::new(500, 400, DisplayMode::Stretch, false);
+        assert_eq!(rects.screen, Rect::new(0, 0, 500, 400));
+
+        // smaller than 1x
+        let rects = DisplayRects::new(100, 100, DisplayMode::Integer, false);
+        assert_eq!(rects.screen, Rect::new(0, 0, 160, 144));
+    }
+
+    #[test]
+    fn test_border() {
+        let rects = DisplayRects::new(800, 700, DisplayMode::Integer, true);
+        assert_eq!(rects.border, Some(Rect::new(16, 14, 768, 672)));
+        assert_eq!(rects.screen, Rect::new(16 + 48 * 3, 14 + 40 * 3, 480, 432));
+    }
+
+    #[test]
+    fn test_load_border() {
+        let path = std::env::temp_dir().join("gmboy_test_border.png");
+        let write_png = |width: u32, height: u32| {
+            let file = File::create(&path).unwrap();
+            let mut encoder = png::Encoder::new(file, width, height);
+            encoder.set_color(ColorType::Rgb);
+            let mut writer = encoder.write_header().unwrap();
+            let data: Vec<u8> = (0..width * height * 3)
+                .map(|i| (i % 3 * 100) as u8)
+                .collect();
+            writer.write_image_data(&data).unwrap();
+        };
+
+        write_png(BORDER_WIDTH, BORDER_HEIGHT);
+        let border = load_border(path.to_str().unwrap()).unwrap();
+        assert_eq!(border.pixels.len(), 256 * 224);
+        assert_eq!(border.pixels[1000], [0, 100, 200, 0xFF]);
+
+        write_png(160, 144);
+        assert!(load_border(path.to_str().unwrap()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_snap_window_size() {
+        assert_eq!(snap_window_size(500, 400, false), (320, 288));
+        assert_eq!(snap_window_size(800, 700, true), (768, 672));
+        assert_eq!(snap_window_size(10, 10, false), (160, 144));
+    }
+}
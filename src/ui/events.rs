@@ -4,6 +4,8 @@ use crate::emu::RunMode;
 
 pub trait UiEventHandler {
     fn on_event(&mut self, bus: &mut Bus, event: UiEvent);
+    /// While open, pressed keys go to the menu as `UiEvent::MenuKey`.
+    fn is_menu_open(&self) -> bool;
 }
 
 pub enum UiEvent {
@@ -13,4 +15,11 @@ pub enum UiEvent {
     Restart,
     ConfigChanged(GraphicsConfig),
     Mode(RunMode),
+    ToggleCheats,
+    ToggleAudioRecording,
+    ToggleVideoRecording,
+    Screenshot,
+    ToggleMenu,
+    /// SDL name of a key pressed while the menu is open.
+    MenuKey(String),
 }
@@ -0,0 +1,525 @@
+use crate::ppu::tile::Pixel;
+use crate::ppu::{LCD_X_RES, LCD_Y_RES};
+use serde::{Deserialize, Serialize};
+
+pub type Rgba = [u8; 4];
+
+/// Cell size of the LCD grid when no scaler is selected.
+pub const GRID_SCALE: usize = 3;
+/// Brightness kept by the gaps between LCD pixels, in percent.
+const GRID_BRIGHTNESS: u32 = 70;
+/// Colors closer than this in YUV are treated as equal by the xBR edge detection.
+const XBR_THRESHOLD: u32 = 48;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Scaler {
+    #[default]
+    None,
+    Scale2x,
+    Scale3x,
+    /// 2x with edge-directed corner blending, as in the first level of xBR.
+    Xbr2x,
+}
+
+impl Scaler {
+    pub fn get_factor(&self) -> usize {
+        match self {
+            Scaler::None => 1,
+            Scaler::Scale2x | Scaler::Xbr2x => 2,
+            Scaler::Scale3x => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilterConfig {
+    #[serde(default)]
+    pub scaler: Scaler,
+    /// Darkens the gaps between pixels like a DMG screen.
+    #[serde(default)]
+    pub lcd_grid: bool,
+    /// Weight of the previous frame from 0.0 (off) to 1.0, mimics the slow LCD response
+    /// some games rely on to draw transparency by flickering.
+    #[serde(default)]
+    pub ghosting: f32,
+    /// Applies the CGB screen color curves.
+    #[serde(default)]
+    pub color_correction: bool,
+}
+
+impl FilterConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.scaler != Scaler::None || self.lcd_grid || self.ghosting > 0.0 || self.color_correction
+    }
+}
+
+/// RGBA frame in row order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgba>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![[0, 0, 0, 0xFF]; width * height],
+        }
+    }
+
+    pub fn from_pixels(buffer: &[Pixel]) -> Image {
+        Image {
+            width: LCD_X_RES as usize,
+            height: LCD_Y_RES as usize,
+            pixels: buffer
+                .iter()
+                .map(|pixel| {
+                    let (r, g, b, a) = pixel.color.as_rgba();
+                    [r, g, b, a]
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the pixel at the position clamped to the edges.
+    pub fn get(&self, x: isize, y: isize) -> Rgba {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+
+        self.pixels[x + y * self.width]
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: Rgba) {
+        self.pixels[x + y * self.width] = color;
+    }
+}
+
+/// Applies the filters of the config in order: color correction, ghosting, scaler and
+/// LCD grid. Keeps the previous frame for ghosting.
+#[derive(Debug, Clone, Default)]
+pub struct FilterPipeline {
+    pub config: FilterConfig,
+    previous: Option<Image>,
+}
+
+impl FilterPipeline {
+    pub fn new(config: FilterConfig) -> Self {
+        Self {
+            config,
+            previous: None,
+        }
+    }
+
+    pub fn apply(&mut self, image: Image) -> Image {
+        let mut image = image;
+
+        if self.config.color_correction {
+            image = correct_colors(&image);
+        }
+
+        if self.config.ghosting > 0.0 {
+            let blended = match &self.previous {
+                Some(previous) => blend(&image, previous, self.config.ghosting),
+                None => image.clone(),
+            };
+            self.previous = Some(image);
+            image = blended;
+        } else {
+            self.previous = None;
+        }
+
+        image = match self.config.scaler {
+            Scaler::None => image,
+            Scaler::Scale2x => scale2x(&image),
+            Scaler::Scale3x => scale3x(&image),
+            Scaler::Xbr2x => xbr2x(&image),
+        };
+
+        if self.config.lcd_grid {
+            image = match self.config.scaler {
+                Scaler::None => lcd_grid(&scale_nearest(&image, GRID_SCALE), GRID_SCALE),
+                scaler => lcd_grid(&image, scaler.get_factor()),
+            };
+        }
+
+        image
+    }
+}
+
+pub fn scale_nearest(image: &Image, factor: usize) -> Image {
+    let mut output = Image::new(image.width * factor, image.height * factor);
+
+    for y in 0..output.height {
+        for x in 0..output.width {
+            output.set(x, y, image.pixels[x / factor + y / factor * image.width]);
+        }
+    }
+
+    output
+}
+
+/// Scale2x (EPX): every pixel becomes 2x2, corners take the neighbor color where two
+/// adjacent neighbors agree.
+pub fn scale2x(image: &Image) -> Image {
+    let mut output = Image::new(image.width * 2, image.height * 2);
+
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let (xi, yi) = (x as isize, y as isize);
+            let e = image.get(xi, yi);
+            let b = image.get(xi, yi - 1);
+            let d = image.get(xi - 1, yi);
+            let f = image.get(xi + 1, yi);
+            let h = image.get(xi, yi + 1);
+            let mut out = [e; 4];
+
+            if b != h && d != f {
+                out[0] = if d == b { d } else { e };
+                out[1] = if b == f { f } else { e };
+                out[2] = if d == h { d } else { e };
+                out[3] = if h == f { f } else { e };
+            }
+
+            for (i, color) in out.into_iter().enumerate() {
+                output.set(x * 2 + i % 2, y * 2 + i / 2, color);
+            }
+        }
+    }
+
+    output
+}
+
+/// Scale3x (AdvMAME3x), the 3x3 extension of Scale2x.
+pub fn scale3x(image: &Image) -> Image {
+    let mut output = Image::new(image.width * 3, image.height * 3);
+
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let (xi, yi) = (x as isize, y as isize);
+            let a = image.get(xi - 1, yi - 1);
+            let b = image.get(xi, yi - 1);
+            let c = image.get(xi + 1, yi - 1);
+            let d = image.get(xi - 1, yi);
+            let e = image.get(xi, yi);
+            let f = image.get(xi + 1, yi);
+            let g = image.get(xi - 1, yi + 1);
+            let h = image.get(xi, yi + 1);
+            let i = image.get(xi + 1, yi + 1);
+            let mut out = [e; 9];
+
+            if b != h && d != f {
+                out[0] = if d == b { d } else { e };
+                out[1] = if (d == b && e != c) || (b == f && e != a) {
+                    b
+                } else {
+                    e
+                };
+                out[2] = if b == f { f } else { e };
+                out[3] = if (d == b && e != g) || (d == h && e != a) {
+                    d
+                } else {
+                    e
+                };
+                out[5] = if (b == f && e != i) || (h == f && e != c) {
+                    f
+                } else {
+                    e
+                };
+                out[6] = if d == h { d } else { e };
+                out[7] = if (d == h && e != i) || (h == f && e != g) {
+                    h
+                } else {
+                    e
+                };
+                out[8] = if h == f { f } else { e };
+            }
+
+            for (n, color) in out.into_iter().enumerate() {
+                output.set(x * 3 + n % 3, y * 3 + n / 3, color);
+            }
+        }
+    }
+
+    output
+}
+
+/// 2x upscaler with the xBR level 1 edge rule: a corner is blended with its neighbor
+/// when the weighted color distances across the corner show an edge along it. Unlike
+/// Scale2x it compares colors by YUV distance, so it also smooths shaded edges.
+pub fn xbr2x(image: &Image) -> Image {
+    let mut output = Image::new(image.width * 2, image.height * 2);
+    // corner subpixel and the directions towards it
+    let corners = [(1, 1, 1, 1), (0, 1, -1, 1), (0, 0, -1, -1), (1, 0, 1, -1)];
+
+    for y in 0..image.height {
+        for x in 0..image.width {
+            for (cx, cy, dx, dy) in corners {
+                let color = get_xbr_corner(image, x as isize, y as isize, dx, dy);
+                output.set(x * 2 + cx, y * 2 + cy, color);
+            }
+        }
+    }
+
+    output
+}
+
+/// Returns the corner of pixel E towards (dx, dy). Names follow the bottom-right case:
+///
+/// ```text
+///     A B C
+///     D E F F4
+///     G H I I4
+///       H5 I5
+/// ```
+fn get_xbr_corner(image: &Image, x: isize, y: isize, dx: isize, dy: isize) -> Rgba {
+    // mirror the neighborhood so the corner always points to the bottom right, the rule
+    // is symmetric so mirroring works like rotating
+    let at = |u: isize, v: isize| image.get(x + u * dx, y + v * dy);
+    let (b, c, d, e, f) = (at(0, -1), at(1, -1), at(-1, 0), at(0, 0), at(1, 0));
+    let (g, h, i) = (at(-1, 1), at(0, 1), at(1, 1));
+    let (f4, i4, h5, i5) = (at(2, 0), at(2, 1), at(0, 2), at(1, 2));
+
+    if e == f && e == h {
+        return e;
+    }
+
+    let across = yuv_distance(e, c)
+        + yuv_distance(e, g)
+        + yuv_distance(i, f4)
+        + yuv_distance(i, h5)
+        + 4 * yuv_distance(h, f);
+    let along = yuv_distance(h, d)
+        + yuv_distance(h, i5)
+        + yuv_distance(f, i4)
+        + yuv_distance(f, b)
+        + 4 * yuv_distance(e, i);
+
+    if across < along && yuv_distance(h, f) < XBR_THRESHOLD {
+        let neighbor = if yuv_distance(e, f) <= yuv_distance(e, h) {
+            f
+        } else {
+            h
+        };
+
+        mix(e, neighbor, 0.5)
+    } else {
+        e
+    }
+}
+
+/// Upscaled image with the last row and column of every `cell` darkened.
+pub fn lcd_grid(image: &Image, cell: usize) -> Image {
+    let mut output = image.clone();
+
+    if cell < 2 {
+        return output;
+    }
+
+    for y in 0..image.height {
+        for x in 0..image.width {
+            if x % cell == cell - 1 || y % cell == cell - 1 {
+                let [r, g, b, a] = image.pixels[x + y * image.width];
+                let dim = |c: u8| (c as u32 * GRID_BRIGHTNESS / 100) as u8;
+                output.set(x, y, [dim(r), dim(g), dim(b), a]);
+            }
+        }
+    }
+
+    output
+}
+
+/// Blends the previous frame into the current one by `weight`.
+pub fn blend(current: &Image, previous: &Image, weight: f32) -> Image {
+    if current.width != previous.width || current.height != previous.height {
+        return current.clone();
+    }
+
+    Image {
+        width: current.width,
+        height: current.height,
+        pixels: current
+            .pixels
+            .iter()
+            .zip(previous.pixels.iter())
+            .map(|(current, previous)| mix(*current, *previous, weight.clamp(0.0, 1.0)))
+            .collect(),
+    }
+}
+
+/// Maps sRGB colors through the CGB screen response: channels bleed into each other and
+/// the result is darker and less saturated. The mix matches the common emulator curves
+/// and is done in linear light.
+pub fn correct_colors(image: &Image) -> Image {
+    let to_linear: Vec<f32> = (0..=255).map(|c| (c as f32 / 255.0).powf(2.2)).collect();
+
+    Image {
+        width: image.width,
+        height: image.height,
+        pixels: image
+            .pixels
+            .iter()
+            .map(|&[r, g, b, a]| {
+                let (r, g, b) = (
+                    to_linear[r as usize],
+                    to_linear[g as usize],
+                    to_linear[b as usize],
+                );
+                let corrected = [
+                    (13.0 * r + 2.0 * g + b) / 16.0,
+                    (3.0 * g + b) / 4.0,
+                    (3.0 * r + 2.0 * g + 11.0 * b) / 16.0,
+                ];
+                let [r, g, b] =
+                    corrected.map(|c| (c.powf(1.0 / 2.2) * 255.0).round().clamp(0.0, 255.0) as u8);
+
+                [r, g, b, a]
+            })
+            .collect(),
+    }
+}
+
+fn mix(a: Rgba, b: Rgba, weight: f32) -> Rgba {
+    let mut out = a;
+
+    for i in 0..3 {
+        out[i] = (a[i] as f32 * (1.0 - weight) + b[i] as f32 * weight).round() as u8;
+    }
+
+    out
+}
+
+fn yuv_distance(a: Rgba, b: Rgba) -> u32 {
+    let to_yuv = |[r, g, b, _a]: Rgba| {
+        let (r, g, b) = (r as f32, g as f32, b as f32);
+        (
+            0.299 * r + 0.587 * g + 0.114 * b,
+            -0.169 * r - 0.331 * g + 0.5 * b,
+            0.5 * r - 0.419 * g - 0.081 * b,
+        )
+    };
+    let (ya, ua, va) = to_yuv(a);
+    let (yb, ub, vb) = to_yuv(b);
+
+    (48.0 * (ya - yb).abs() + 7.0 * (ua - ub).abs() + 6.0 * (va - vb).abs()) as u32 / 8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: Rgba = [0xFF, 0xFF, 0xFF, 0xFF];
+    const K: Rgba = [0, 0, 0, 0xFF];
+
+    fn image(width: usize, pixels: &[Rgba]) -> Image {
+        Image {
+            width,
+            height: pixels.len() / width,
+            pixels: pixels.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_scale2x() {
+        let flat = image(2, &[W; 4]);
+        assert_eq!(scale2x(&flat).pixels, vec![W; 16]);
+
+        // the corners along the diagonal take the color of the neighbors
+        let output = scale2x(&image(2, &[K, W, W, K]));
+        assert_eq!((output.width, output.height), (4, 4));
+        assert_eq!(
+            output.pixels,
+            vec![K, K, W, W, K, W, K, W, W, K, W, K, W, W, K, K]
+        );
+    }
+
+    #[test]
+    fn test_scale3x() {
+        let flat = image(2, &[W; 4]);
+        assert_eq!(scale3x(&flat).pixels, vec![W; 36]);
+
+        let output = scale3x(&image(2, &[K, W, W, K]));
+        assert_eq!((output.width, output.height), (6, 6));
+        // the center of every source pixel keeps its color
+        assert_eq!(output.get(1, 1), K);
+        assert_eq!(output.get(4, 1), W);
+        // the corners along the diagonal take the color of the neighbors
+        assert_eq!(output.get(2, 2), W);
+        assert_eq!(output.get(3, 2), K);
+        assert_eq!(output.get(2, 3), K);
+    }
+
+    #[test]
+    fn test_xbr2x() {
+        let flat = image(3, &[W; 9]);
+        assert_eq!(xbr2x(&flat).pixels, vec![W; 36]);
+
+        // a black triangle below the diagonal
+        let input = image(4, &[W, W, W, W, K, W, W, W, K, K, W, W, K, K, K, W]);
+        let output = xbr2x(&input);
+        assert_eq!((output.width, output.height), (8, 8));
+        // the steps of the edge are blended, the rest is untouched
+        let gray = [0x80, 0x80, 0x80, 0xFF];
+        assert_eq!(output.get(1, 2), gray);
+        assert_eq!(output.get(5, 6), gray);
+        assert_eq!(output.get(0, 2), K);
+        assert_eq!(output.get(2, 2), W);
+        assert_eq!(output.get(6, 7), W);
+    }
+
+    #[test]
+    fn test_lcd_grid() {
+        let output = lcd_grid(&scale_nearest(&image(1, &[W]), 3), 3);
+        let gap = [178, 178, 178, 0xFF];
+
+        assert_eq!(output.pixels, vec![W, W, gap, W, W, gap, gap, gap, gap]);
+    }
+
+    #[test]
+    fn test_ghosting() {
+        let mut pipeline = FilterPipeline::new(FilterConfig {
+            ghosting: 0.5,
+            ..Default::default()
+        });
+
+        assert_eq!(pipeline.apply(image(1, &[W])).pixels, vec![W]);
+        // blended with the previous frame, not with the previous output
+        assert_eq!(
+            pipeline.apply(image(1, &[K])).pixels,
+            vec![[0x80, 0x80, 0x80, 0xFF]]
+        );
+        assert_eq!(pipeline.apply(image(1, &[K])).pixels, vec![K]);
+    }
+
+    #[test]
+    fn test_color_correction() {
+        let output = correct_colors(&image(3, &[W, K, [0xFF, 0, 0, 0xFF]]));
+
+        assert_eq!(output.pixels[0], W);
+        assert_eq!(output.pixels[1], K);
+        let [r, g, b, _a] = output.pixels[2];
+        assert!(r < 0xFF && r > 0xE0, "{}", r);
+        assert_eq!(g, 0);
+        assert!(b > 0x60 && b < 0x90, "{}", b);
+    }
+
+    #[test]
+    fn test_pipeline_size() {
+        let mut pipeline = FilterPipeline::new(FilterConfig {
+            scaler: Scaler::Scale3x,
+            lcd_grid: true,
+            ..Default::default()
+        });
+        let output = pipeline.apply(Image::new(160, 144));
+        assert_eq!((output.width, output.height), (480, 432));
+
+        pipeline.config.scaler = Scaler::None;
+        let output = pipeline.apply(Image::new(160, 144));
+        assert_eq!(
+            (output.width, output.height),
+            (160 * GRID_SCALE, 144 * GRID_SCALE)
+        );
+    }
+}
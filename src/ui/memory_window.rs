@@ -0,0 +1,376 @@
+use crate::bus::Bus;
+use crate::mem_view::{MemRegion, MemView, BYTES_PER_ROW};
+use crate::ppu::tile::PixelColor;
+use crate::ram_search::{RamSearch, SearchFilter, SearchWidth};
+use crate::ui::text::{draw_text, fill_texture, CHAR_SPACING, CHAR_WIDTH};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, Texture};
+use sdl2::video::{Window, WindowPos};
+use sdl2::VideoSubsystem;
+
+pub const HEX_ROWS: usize = 32;
+pub const RESULT_ROWS: usize = 10;
+pub const RESULT_COLS: usize = 3;
+pub const LINE_CHARS: usize = 56;
+pub const LINE_HEIGHT: usize = 12;
+pub const PADDING: usize = 6;
+const CHAR_ADVANCE: usize = CHAR_WIDTH + CHAR_SPACING;
+const RESULT_COL_CHARS: usize = 19;
+// header, blank, hex rows, blank, search header, results, blank, 2 help lines, status
+const TOTAL_LINES: usize = 2 + HEX_ROWS + 2 + RESULT_ROWS + 4;
+
+const BG_COLOR: Color = Color::RGB(18, 18, 18);
+const CHANGED_COLOR: Color = Color::RGB(130, 30, 50);
+const CURSOR_COLOR: Color = Color::RGB(40, 90, 180);
+const TEXT_COLOR: PixelColor = PixelColor::from_hex(0xFFE0E0E0);
+const DIM_TEXT_COLOR: PixelColor = PixelColor::from_hex(0xFF808080);
+const STATUS_COLOR: PixelColor = PixelColor::from_hex(0xFFE05070);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Input {
+    None,
+    Goto(String),
+    Value(String),
+}
+
+/// Hex viewer/editor over the bus or raw cart banks with a RAM search panel.
+/// Bytes changed since the previous frame are highlighted red.
+pub struct MemoryWindow {
+    pub canvas: Canvas<Window>,
+    text_texture: Texture,
+    width: u32,
+    height: u32,
+    view: MemView,
+    search_width: SearchWidth,
+    search: Option<RamSearch>,
+    input: Input,
+    status: String,
+}
+
+impl MemoryWindow {
+    pub fn new(video_subsystem: &VideoSubsystem) -> MemoryWindow {
+        let width = (LINE_CHARS * CHAR_ADVANCE + PADDING * 2) as u32;
+        let height = (TOTAL_LINES * LINE_HEIGHT + PADDING * 2) as u32;
+
+        let window = video_subsystem
+            .window("Memory Viewer", width, height)
+            .position_centered()
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        let mut text_texture = canvas
+            .texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+            .unwrap();
+        text_texture.set_blend_mode(BlendMode::Blend);
+
+        Self {
+            canvas,
+            text_texture,
+            width,
+            height,
+            view: MemView::new(HEX_ROWS),
+            search_width: SearchWidth::U8,
+            search: None,
+            input: Input::None,
+            status: String::new(),
+        }
+    }
+
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.canvas
+            .window_mut()
+            .set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    }
+
+    pub fn handle_key(&mut self, bus: &mut Bus, keycode: Keycode) {
+        if self.input != Input::None {
+            self.handle_input_key(bus, keycode);
+            return;
+        }
+
+        if let Some(nibble) = into_hex_digit(keycode) {
+            if let Err(err) = self.view.input_nibble(bus, nibble) {
+                self.status = err;
+            }
+
+            return;
+        }
+
+        self.status.clear();
+
+        match keycode {
+            Keycode::UP => self.view.move_cursor(-(BYTES_PER_ROW as isize)),
+            Keycode::DOWN => self.view.move_cursor(BYTES_PER_ROW as isize),
+            Keycode::LEFT => self.view.move_cursor(-1),
+            Keycode::RIGHT => self.view.move_cursor(1),
+            Keycode::PAGEUP => self
+                .view
+                .move_cursor(-((BYTES_PER_ROW * HEX_ROWS) as isize)),
+            Keycode::PAGEDOWN => self.view.move_cursor((BYTES_PER_ROW * HEX_ROWS) as isize),
+            Keycode::TAB => self.view.set_region(self.view.region.next(bus)),
+            Keycode::LEFTBRACKET => self
+                .view
+                .set_region(self.view.region.with_bank_delta(bus, -1)),
+            Keycode::RIGHTBRACKET => self
+                .view
+                .set_region(self.view.region.with_bank_delta(bus, 1)),
+            Keycode::G => self.input = Input::Goto(String::new()),
+            Keycode::V if self.search.is_some() => self.input = Input::Value(String::new()),
+            Keycode::W => {
+                self.search_width = match self.search_width {
+                    SearchWidth::U8 => SearchWidth::U16,
+                    SearchWidth::U16 => SearchWidth::U8,
+                };
+                self.search = None;
+            }
+            Keycode::F5 => self.search = Some(RamSearch::new(bus, self.search_width)),
+            Keycode::F6 => self.apply_filter(bus, SearchFilter::Equal),
+            Keycode::F7 => self.apply_filter(bus, SearchFilter::Changed),
+            Keycode::F8 => self.apply_filter(bus, SearchFilter::Increased),
+            Keycode::F9 => self.apply_filter(bus, SearchFilter::Decreased),
+            Keycode::J => {
+                if let Some(candidate) = self.search.as_ref().and_then(|s| s.candidates.first()) {
+                    let address = candidate.address as usize;
+                    self.view.set_region(MemRegion::Bus);
+                    self.view.goto(address);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_input_key(&mut self, bus: &Bus, keycode: Keycode) {
+        let text = match &mut self.input {
+            Input::Goto(text) | Input::Value(text) => text,
+            Input::None => return,
+        };
+
+        if let Some(nibble) = into_hex_digit(keycode) {
+            if text.len() < 4 {
+                text.push_str(&format!("{:X}", nibble));
+            }
+
+            return;
+        }
+
+        match keycode {
+            Keycode::BACKSPACE => {
+                text.pop();
+            }
+            Keycode::ESCAPE => self.input = Input::None,
+            Keycode::RETURN | Keycode::KP_ENTER => {
+                let value = u16::from_str_radix(text, 16).unwrap_or(0);
+
+                match self.input {
+                    Input::Goto(_) => self.view.goto(value as usize),
+                    Input::Value(_) => self.apply_filter(bus, SearchFilter::Value(value)),
+                    Input::None => {}
+                }
+
+                self.input = Input::None;
+            }
+            _ => (),
+        }
+    }
+
+    fn apply_filter(&mut self, bus: &Bus, filter: SearchFilter) {
+        if let Some(search) = self.search.as_mut() {
+            search.filter(bus, filter);
+        }
+    }
+
+    pub fn draw(&mut self, bus: &Bus) {
+        self.view.update(bus);
+        self.canvas.set_draw_color(BG_COLOR);
+        self.canvas.clear();
+        fill_texture(&mut self.text_texture, PixelColor::from_hex(0));
+
+        self.draw_line(0, &self.header(), TEXT_COLOR);
+        self.draw_hex_rows(bus, 2);
+        self.draw_search(bus, HEX_ROWS + 3);
+
+        let help_line = TOTAL_LINES - 3;
+        self.draw_line(
+            help_line,
+            "TAB REGION  BRACKETS BANK  G GOTO  0 F EDIT",
+            DIM_TEXT_COLOR,
+        );
+        self.draw_line(
+            help_line + 1,
+            "F5 NEW F6 EQ F7 CHG F8 INC F9 DEC V VAL W J",
+            DIM_TEXT_COLOR,
+        );
+        let status = self.status.to_uppercase();
+        self.draw_line(help_line + 2, &status, STATUS_COLOR);
+
+        self.canvas
+            .copy(
+                &self.text_texture,
+                None,
+                Rect::new(0, 0, self.width, self.height),
+            )
+            .unwrap();
+        self.canvas.present();
+    }
+
+    fn header(&self) -> String {
+        match &self.input {
+            Input::Goto(text) => format!("GOTO {}", text),
+            Input::Value(text) => format!("VALUE {}", text),
+            Input::None => format!(
+                "{}  {:04X}",
+                self.view.region.name(),
+                self.view.address_of(self.view.cursor)
+            ),
+        }
+    }
+
+    fn draw_hex_rows(&mut self, bus: &Bus, first_line: usize) {
+        let region = self.view.region;
+        let rows_count = region.size() / BYTES_PER_ROW;
+
+        for i in 0..HEX_ROWS {
+            let row = self.view.top_row + i;
+
+            if row >= rows_count {
+                break;
+            }
+
+            let line = first_line + i;
+            let row_offset = row * BYTES_PER_ROW;
+            let mut text = format!("{:04X} ", self.view.address_of(row_offset));
+
+            for col in 0..BYTES_PER_ROW {
+                let offset = row_offset + col;
+                let char_idx = 6 + col * 3;
+                let highlight = if offset == self.view.cursor {
+                    Some(CURSOR_COLOR)
+                } else if self.view.is_changed(offset) {
+                    Some(CHANGED_COLOR)
+                } else {
+                    None
+                };
+
+                if let Some(color) = highlight {
+                    self.canvas.set_draw_color(color);
+                    self.canvas
+                        .fill_rect(Rect::new(
+                            (PADDING + char_idx * CHAR_ADVANCE) as i32 - 1,
+                            (PADDING + line * LINE_HEIGHT) as i32 - 2,
+                            (2 * CHAR_ADVANCE) as u32,
+                            LINE_HEIGHT as u32,
+                        ))
+                        .unwrap();
+                }
+
+                let value = region.read(bus, offset);
+                match self.view.pending_nibble() {
+                    Some(high) if offset == self.view.cursor => {
+                        text.push_str(&format!(" {:X} ", high))
+                    }
+                    _ => text.push_str(&format!(" {:02X}", value)),
+                }
+            }
+
+            self.draw_line(line, &text, TEXT_COLOR);
+        }
+    }
+
+    fn draw_search(&mut self, bus: &Bus, first_line: usize) {
+        let width = match self.search_width {
+            SearchWidth::U8 => "8BIT",
+            SearchWidth::U16 => "16BIT",
+        };
+        let Some(search) = self.search.as_ref() else {
+            self.draw_line(
+                first_line,
+                &format!("SEARCH {}  F5 TO START", width),
+                TEXT_COLOR,
+            );
+            return;
+        };
+
+        let header = format!(
+            "SEARCH {}  STEP {}  RESULTS {}",
+            width,
+            search.steps,
+            search.len()
+        );
+        let lines: Vec<(usize, String)> = search
+            .candidates
+            .iter()
+            .take(RESULT_ROWS * RESULT_COLS)
+            .enumerate()
+            .map(|(i, candidate)| {
+                let curr = search.width.read(bus, candidate.address);
+                let text = match search.width {
+                    SearchWidth::U8 => format!(
+                        "{:04X} {:02X} {:02X}",
+                        candidate.address, candidate.value, curr
+                    ),
+                    SearchWidth::U16 => format!(
+                        "{:04X} {:04X} {:04X}",
+                        candidate.address, candidate.value, curr
+                    ),
+                };
+
+                (i, text)
+            })
+            .collect();
+
+        self.draw_line(first_line, &header, TEXT_COLOR);
+
+        for (i, text) in lines {
+            let line = first_line + 1 + i % RESULT_ROWS;
+            let x = PADDING + (i / RESULT_ROWS) * RESULT_COL_CHARS * CHAR_ADVANCE;
+            draw_text(
+                &mut self.text_texture,
+                &text,
+                TEXT_COLOR,
+                x,
+                PADDING + line * LINE_HEIGHT,
+                1,
+            );
+        }
+    }
+
+    fn draw_line(&mut self, line: usize, text: &str, color: PixelColor) {
+        // draw_text doesn't clip, so keep the text inside the window
+        let text: String = text.chars().take(LINE_CHARS).collect();
+        draw_text(
+            &mut self.text_texture,
+            &text,
+            color,
+            PADDING,
+            PADDING + line * LINE_HEIGHT,
+            1,
+        );
+    }
+}
+
+fn into_hex_digit(keycode: Keycode) -> Option<u8> {
+    let digit = match keycode {
+        Keycode::NUM_0 | Keycode::KP_0 => 0x0,
+        Keycode::NUM_1 | Keycode::KP_1 => 0x1,
+        Keycode::NUM_2 | Keycode::KP_2 => 0x2,
+        Keycode::NUM_3 | Keycode::KP_3 => 0x3,
+        Keycode::NUM_4 | Keycode::KP_4 => 0x4,
+        Keycode::NUM_5 | Keycode::KP_5 => 0x5,
+        Keycode::NUM_6 | Keycode::KP_6 => 0x6,
+        Keycode::NUM_7 | Keycode::KP_7 => 0x7,
+        Keycode::NUM_8 | Keycode::KP_8 => 0x8,
+        Keycode::NUM_9 | Keycode::KP_9 => 0x9,
+        Keycode::A => 0xA,
+        Keycode::B => 0xB,
+        Keycode::C => 0xC,
+        Keycode::D => 0xD,
+        Keycode::E => 0xE,
+        Keycode::F => 0xF,
+        _ => return None,
+    };
+
+    Some(digit)
+}
@@ -0,0 +1,797 @@
+use crate::archive::{get_entry_path, is_archive_path, is_rom_path, list_archive_roms};
+use crate::auxiliary::joypad::JoypadButton;
+use crate::config::Config;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Items visible at once, the list scrolls with the cursor.
+pub const MENU_ROWS: usize = 12;
+/// Longest item text, longer names are cut.
+pub const MENU_COLUMNS: usize = 14;
+pub const SAVE_SLOTS: usize = 4;
+const MAX_SCALE: f32 = 10.0;
+const VOLUME_STEP: f32 = 0.1;
+const CLOSE_KEY: &str = "Escape";
+
+/// What the emulator has to do after a key was handled by the menu.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuAction {
+    None,
+    Close,
+    LoadCart(String),
+    SaveState(usize),
+    LoadState(usize),
+    /// Index in `Menu::cheats`, the menu already shows the new state.
+    ToggleCheat(usize),
+    GraphicsChanged,
+    AudioChanged,
+    InputChanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MenuPage {
+    Main,
+    Browser,
+    Recent,
+    SaveState,
+    LoadState,
+    Cheats,
+    Keys,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MainItem {
+    Resume,
+    LoadRom,
+    Recent,
+    SaveState,
+    LoadState,
+    Cheats,
+    Palette,
+    Scale,
+    Volume,
+    Keys,
+}
+
+const MAIN_ITEMS: [MainItem; 10] = [
+    MainItem::Resume,
+    MainItem::LoadRom,
+    MainItem::Recent,
+    MainItem::SaveState,
+    MainItem::LoadState,
+    MainItem::Cheats,
+    MainItem::Palette,
+    MainItem::Scale,
+    MainItem::Volume,
+    MainItem::Keys,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+struct BrowserEntry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// Visible part of the current page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuView {
+    pub title: String,
+    pub items: Vec<String>,
+    /// Index in `items`.
+    pub cursor: usize,
+}
+
+/// In-window menu navigated with the keys bound to the joypad. It only edits `Config`
+/// and returns actions, drawing and applying them is up to the caller.
+#[derive(Debug, Clone)]
+pub struct Menu {
+    pub is_open: bool,
+    /// Slots which hold a state, shown by the save and load pages.
+    pub used_slots: [bool; SAVE_SLOTS],
+    /// Names of the cart cheats and whether they are enabled, shown by the cheats page.
+    pub cheats: Vec<(String, bool)>,
+    page: MenuPage,
+    cursor: usize,
+    browser_dir: PathBuf,
+    browser_entries: Vec<BrowserEntry>,
+    rebinding: Option<JoypadButton>,
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Self {
+            is_open: false,
+            used_slots: [false; SAVE_SLOTS],
+            cheats: Vec::new(),
+            page: MenuPage::Main,
+            cursor: 0,
+            browser_dir: PathBuf::new(),
+            browser_entries: Vec::new(),
+            rebinding: None,
+        }
+    }
+}
+
+impl Menu {
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.set_page(MenuPage::Main);
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.rebinding = None;
+    }
+
+    /// Handles a pressed key by its SDL name.
+    pub fn handle_key(&mut self, key: &str, config: &mut Config) -> MenuAction {
+        if let Some(button) = self.rebinding.take() {
+            if key == CLOSE_KEY {
+                return MenuAction::None;
+            }
+
+            config.input.set_key(button, key);
+            return MenuAction::InputChanged;
+        }
+
+        if key == CLOSE_KEY {
+            return self.go_back();
+        }
+
+        let Some(button) = config.input.find_button(key) else {
+            return MenuAction::None;
+        };
+        let len = self.get_items_count(config);
+
+        match button {
+            JoypadButton::Up if len > 0 => self.cursor = (self.cursor + len - 1) % len,
+            JoypadButton::Down if len > 0 => self.cursor = (self.cursor + 1) % len,
+            JoypadButton::Left => return self.change_value(config, false),
+            JoypadButton::Right => return self.change_value(config, true),
+            JoypadButton::A => return self.select(config),
+            JoypadButton::B => return self.go_back(),
+            JoypadButton::Start => {
+                self.close();
+                return MenuAction::Close;
+            }
+            _ => (),
+        }
+
+        MenuAction::None
+    }
+
+    pub fn get_view(&self, config: &Config) -> MenuView {
+        let (title, items): (String, Vec<String>) = match self.page {
+            MenuPage::Main => (
+                "MENU".to_string(),
+                MAIN_ITEMS
+                    .iter()
+                    .map(|item| get_main_item_text(*item, config))
+                    .collect(),
+            ),
+            MenuPage::Browser => (
+                get_dir_title(&self.browser_dir),
+                self.browser_entries
+                    .iter()
+                    .map(|entry| {
+                        if entry.is_dir {
+                            format!("{}/", entry.name)
+                        } else {
+                            entry.name.clone()
+                        }
+                    })
+                    .collect(),
+            ),
+            MenuPage::Recent => (
+                "RECENT".to_string(),
+                config
+                    .recent_carts
+                    .iter()
+                    .map(|path| get_file_name(Path::new(path)))
+                    .collect(),
+            ),
+            MenuPage::SaveState | MenuPage::LoadState => (
+                if self.page == MenuPage::SaveState {
+                    "SAVE STATE".to_string()
+                } else {
+                    "LOAD STATE".to_string()
+                },
+                self.used_slots
+                    .iter()
+                    .enumerate()
+                    .map(|(i, used)| {
+                        format!("SLOT {} {}", i + 1, if *used { "USED" } else { "EMPTY" })
+                    })
+                    .collect(),
+            ),
+            MenuPage::Cheats => (
+                "CHEATS".to_string(),
+                self.cheats
+                    .iter()
+                    .map(|(name, enabled)| {
+                        format!("{} {}", if *enabled { "ON" } else { "OFF" }, name)
+                    })
+                    .collect(),
+            ),
+            MenuPage::Keys => (
+                "KEYS".to_string(),
+                JoypadButton::ALL
+                    .iter()
+                    .map(|button| {
+                        if self.rebinding == Some(*button) {
+                            format!("{} PRESS KEY", button.get_name())
+                        } else {
+                            format!("{} {}", button.get_name(), config.input.get_key(*button))
+                        }
+                    })
+                    .collect(),
+            ),
+        };
+
+        let first = self.cursor.saturating_sub(MENU_ROWS - 1);
+        let items: Vec<String> = items
+            .into_iter()
+            .skip(first)
+            .take(MENU_ROWS)
+            .map(|item| item.to_uppercase().chars().take(MENU_COLUMNS).collect())
+            .collect();
+
+        MenuView {
+            title: title
+                .to_uppercase()
+                .chars()
+                .take(MENU_COLUMNS + 2)
+                .collect(),
+            items,
+            cursor: self.cursor - first,
+        }
+    }
+
+    fn get_items_count(&self, config: &Config) -> usize {
+        match self.page {
+            MenuPage::Main => MAIN_ITEMS.len(),
+            MenuPage::Browser => self.browser_entries.len(),
+            MenuPage::Recent => config.recent_carts.len(),
+            MenuPage::SaveState | MenuPage::LoadState => SAVE_SLOTS,
+            MenuPage::Cheats => self.cheats.len(),
+            MenuPage::Keys => JoypadButton::ALL.len(),
+        }
+    }
+
+    fn set_page(&mut self, page: MenuPage) {
+        self.page = page;
+        self.cursor = 0;
+    }
+
+    fn go_back(&mut self) -> MenuAction {
+        if self.page == MenuPage::Main {
+            self.close();
+            return MenuAction::Close;
+        }
+
+        let item = match self.page {
+            MenuPage::Browser => MainItem::LoadRom,
+            MenuPage::Recent => MainItem::Recent,
+            MenuPage::SaveState => MainItem::SaveState,
+            MenuPage::LoadState => MainItem::LoadState,
+            MenuPage::Cheats => MainItem::Cheats,
+            MenuPage::Keys | MenuPage::Main => MainItem::Keys,
+        };
+        self.set_page(MenuPage::Main);
+        self.cursor = MAIN_ITEMS.iter().position(|i| *i == item).unwrap_or(0);
+
+        MenuAction::None
+    }
+
+    fn select(&mut self, config: &mut Config) -> MenuAction {
+        match self.page {
+            MenuPage::Main => match MAIN_ITEMS[self.cursor] {
+                MainItem::Resume => {
+                    self.close();
+                    MenuAction::Close
+                }
+                MainItem::LoadRom => {
+                    let dir = config
+                        .roms_dir
+                        .as_ref()
+                        .map(PathBuf::from)
+                        .or_else(|| std::env::current_dir().ok())
+                        .unwrap_or_default();
+                    self.open_dir(&dir);
+                    MenuAction::None
+                }
+                MainItem::Recent => {
+                    self.set_page(MenuPage::Recent);
+                    MenuAction::None
+                }
+                MainItem::SaveState => {
+                    self.set_page(MenuPage::SaveState);
+                    MenuAction::None
+                }
+                MainItem::LoadState => {
+                    self.set_page(MenuPage::LoadState);
+                    MenuAction::None
+                }
+                MainItem::Cheats => {
+                    self.set_page(MenuPage::Cheats);
+                    MenuAction::None
+                }
+                MainItem::Keys => {
+                    self.set_page(MenuPage::Keys);
+                    MenuAction::None
+                }
+                MainItem::Palette | MainItem::Scale | MainItem::Volume => {
+                    self.change_value(config, true)
+                }
+            },
+            MenuPage::Browser => {
+                let Some(entry) = self.browser_entries.get(self.cursor).cloned() else {
+                    return MenuAction::None;
+                };
+
+                if entry.is_dir {
+                    self.open_dir(&entry.path);
+                    return MenuAction::None;
+                }
+
+                let path = entry.path.to_string_lossy().to_string();
+
+                if is_archive_path(&entry.path) {
+                    match list_archive_roms(&path) {
+                        Ok(roms) if roms.len() > 1 => {
+                            self.open_archive(&path, roms);
+                            return MenuAction::None;
+                        }
+                        Ok(roms) if roms.is_empty() => return MenuAction::None,
+                        Ok(_) => (),
+                        Err(err) => {
+                            eprintln!("Failed to read archive {}: {}", path, err);
+                            return MenuAction::None;
+                        }
+                    }
+                }
+
+                let dir = if is_archive_path(&self.browser_dir) {
+                    self.browser_dir.parent().unwrap_or(&self.browser_dir)
+                } else {
+                    &self.browser_dir
+                };
+                config.roms_dir = Some(dir.to_string_lossy().to_string());
+                self.close();
+                MenuAction::LoadCart(path)
+            }
+            MenuPage::Recent => match config.recent_carts.get(self.cursor) {
+                Some(path) => {
+                    self.close();
+                    MenuAction::LoadCart(path.clone())
+                }
+                None => MenuAction::None,
+            },
+            MenuPage::SaveState => {
+                self.close();
+                MenuAction::SaveState(self.cursor)
+            }
+            MenuPage::LoadState if self.used_slots[self.cursor] => {
+                self.close();
+                MenuAction::LoadState(self.cursor)
+            }
+            MenuPage::LoadState => MenuAction::None,
+            MenuPage::Cheats => match self.cheats.get_mut(self.cursor) {
+                Some((_, enabled)) => {
+                    *enabled = !*enabled;
+                    MenuAction::ToggleCheat(self.cursor)
+                }
+                None => MenuAction::None,
+            },
+            MenuPage::Keys => {
+                self.rebinding = Some(JoypadButton::ALL[self.cursor]);
+                MenuAction::None
+            }
+        }
+    }
+
+    fn change_value(&mut self, config: &mut Config, is_increase: bool) -> MenuAction {
+        if self.page != MenuPage::Main {
+            return MenuAction::None;
+        }
+
+        let graphics = &mut config.graphics;
+
+        match MAIN_ITEMS[self.cursor] {
+            MainItem::Palette => {
+                let len = graphics.pallets.len();
+                graphics.selected_pallet_idx = if is_increase {
+                    (graphics.selected_pallet_idx + 1) % len
+                } else {
+                    (graphics.selected_pallet_idx + len - 1) % len
+                };
+                MenuAction::GraphicsChanged
+            }
+            MainItem::Scale => {
+                let step = if is_increase { 1.0 } else { -1.0 };
+                graphics.scale = (graphics.scale + step).clamp(1.0, MAX_SCALE);
+                MenuAction::GraphicsChanged
+            }
+            MainItem::Volume => {
+                let step = if is_increase {
+                    VOLUME_STEP
+                } else {
+                    -VOLUME_STEP
+                };
+                let volume = (config.audio.volume + step).clamp(0.0, 1.0);
+                config.audio.volume = (volume * 10.0).round() / 10.0;
+                MenuAction::AudioChanged
+            }
+            _ => MenuAction::None,
+        }
+    }
+
+    /// Lists the ROMs of an archive to choose one, opening the menu if it is closed.
+    pub fn open_archive(&mut self, path: &str, roms: Vec<String>) {
+        let path = PathBuf::from(path);
+        let path = path.canonicalize().unwrap_or(path);
+        let mut entries: Vec<BrowserEntry> = roms
+            .into_iter()
+            .map(|rom| BrowserEntry {
+                path: PathBuf::from(get_entry_path(&path.to_string_lossy(), &rom)),
+                name: rom,
+                is_dir: false,
+            })
+            .collect();
+
+        if let Some(parent) = path.parent() {
+            entries.insert(
+                0,
+                BrowserEntry {
+                    name: "..".to_string(),
+                    path: parent.to_path_buf(),
+                    is_dir: true,
+                },
+            );
+        }
+
+        self.is_open = true;
+        self.browser_entries = entries;
+        self.browser_dir = path;
+        self.set_page(MenuPage::Browser);
+    }
+
+    fn open_dir(&mut self, dir: &Path) {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        self.browser_entries = read_browser_entries(&dir);
+        self.browser_dir = dir;
+        self.set_page(MenuPage::Browser);
+    }
+}
+
+fn get_main_item_text(item: MainItem, config: &Config) -> String {
+    match item {
+        MainItem::Resume => "RESUME".to_string(),
+        MainItem::LoadRom => "LOAD ROM".to_string(),
+        MainItem::Recent => "RECENT".to_string(),
+        MainItem::SaveState => "SAVE STATE".to_string(),
+        MainItem::LoadState => "LOAD STATE".to_string(),
+        MainItem::Cheats => "CHEATS".to_string(),
+        MainItem::Palette => {
+            let graphics = &config.graphics;
+            let name = graphics
+                .pallets
+                .get(graphics.selected_pallet_idx)
+                .map(|pallet| pallet.name.as_str())
+                .unwrap_or_default();
+            format!("PALETTE {}", name)
+        }
+        MainItem::Scale => format!("SCALE {}", config.graphics.scale),
+        MainItem::Volume => format!("VOLUME {}", (config.audio.volume * 100.0).round()),
+        MainItem::Keys => "KEYS".to_string(),
+    }
+}
+
+/// Parent first, then directories and ROMs sorted by name.
+fn read_browser_entries(dir: &Path) -> Vec<BrowserEntry> {
+    let mut entries: Vec<BrowserEntry> = fs::read_dir(dir)
+        .map(|read_dir| {
+            read_dir
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir() || is_rom_path(path) || is_archive_path(path))
+                .map(|path| BrowserEntry {
+                    name: get_file_name(&path),
+                    is_dir: path.is_dir(),
+                    path,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort_by_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+
+    if let Some(parent) = dir.parent() {
+        entries.insert(
+            0,
+            BrowserEntry {
+                name: "..".to_string(),
+                path: parent.to_path_buf(),
+                is_dir: true,
+            },
+        );
+    }
+
+    entries
+}
+
+fn get_file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn get_dir_title(dir: &Path) -> String {
+    let name = get_file_name(dir);
+
+    if name.is_empty() {
+        "/".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AudioConfig, EmulationConfig, GraphicsConfig, InputConfig, Pallet};
+
+    fn get_config() -> Config {
+        let pallet = |name: &str| Pallet {
+            name: name.to_string(),
+            hex_colors: Default::default(),
+        };
+
+        Config {
+            last_cart_path: None,
+            emulation: EmulationConfig {
+                rewind_size: 0,
+                slow_speed: 50.0,
+                turbo_speed: 300.0,
+            },
+            graphics: GraphicsConfig {
+                selected_pallet_idx: 0,
+                pallets: vec![pallet("Classic"), pallet("Rustic")],
+                scale: 4.0,
+                fps_limit: 60.0,
+                is_fullscreen: false,
+                show_fps: false,
+                text_scale: 1,
+                screenshot_format: Default::default(),
+                video_format: Default::default(),
+                filters: Default::default(),
+                display_mode: Default::default(),
+                border_path: None,
+                window_size: None,
+                window_position: None,
+            },
+            audio: AudioConfig::default(),
+            input: InputConfig::default(),
+            recent_carts: vec![],
+            roms_dir: None,
+            dat_path: None,
+        }
+    }
+
+    fn press(menu: &mut Menu, config: &mut Config, button: JoypadButton) -> MenuAction {
+        let key = config.input.get_key(button).to_string();
+
+        menu.handle_key(&key, config)
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut config = get_config();
+        let mut menu = Menu::default();
+        menu.open();
+
+        assert_eq!(menu.get_view(&config).items[0], "RESUME");
+        press(&mut menu, &mut config, JoypadButton::Up);
+        assert_eq!(menu.get_view(&config).cursor, MAIN_ITEMS.len() - 1);
+        press(&mut menu, &mut config, JoypadButton::Down);
+        press(&mut menu, &mut config, JoypadButton::Down);
+        press(&mut menu, &mut config, JoypadButton::Down);
+
+        // recent page and back to the same item
+        assert_eq!(
+            press(&mut menu, &mut config, JoypadButton::A),
+            MenuAction::None
+        );
+        assert_eq!(menu.get_view(&config).title, "RECENT");
+        assert_eq!(menu.handle_key("Escape", &mut config), MenuAction::None);
+        assert_eq!(menu.get_view(&config).cursor, 2);
+
+        assert_eq!(
+            press(&mut menu, &mut config, JoypadButton::B),
+            MenuAction::Close
+        );
+        assert!(!menu.is_open);
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut config = get_config();
+        let mut menu = Menu::default();
+        menu.open();
+        let select = |menu: &mut Menu, item: MainItem| {
+            menu.cursor = MAIN_ITEMS.iter().position(|i| *i == item).unwrap();
+        };
+
+        select(&mut menu, MainItem::Palette);
+        let action = press(&mut menu, &mut config, JoypadButton::Left);
+        assert_eq!(action, MenuAction::GraphicsChanged);
+        assert_eq!(config.graphics.selected_pallet_idx, 1);
+        assert_eq!(menu.get_view(&config).items[6], "PALETTE RUSTIC");
+
+        select(&mut menu, MainItem::Scale);
+        press(&mut menu, &mut config, JoypadButton::Right);
+        assert_eq!(config.graphics.scale, 5.0);
+
+        select(&mut menu, MainItem::Volume);
+        press(&mut menu, &mut config, JoypadButton::Right);
+        assert_eq!(config.audio.volume, 1.0);
+        press(&mut menu, &mut config, JoypadButton::Left);
+        press(&mut menu, &mut config, JoypadButton::Left);
+        assert_eq!(config.audio.volume, 0.8);
+        assert_eq!(menu.get_view(&config).items[8], "VOLUME 80");
+    }
+
+    #[test]
+    fn test_rebind_keys() {
+        let mut config = get_config();
+        let mut menu = Menu::default();
+        menu.open();
+        menu.cursor = MAIN_ITEMS
+            .iter()
+            .position(|i| *i == MainItem::Keys)
+            .unwrap();
+        press(&mut menu, &mut config, JoypadButton::A);
+        press(&mut menu, &mut config, JoypadButton::Down);
+        press(&mut menu, &mut config, JoypadButton::A);
+        assert_eq!(menu.get_view(&config).items[1], "DOWN PRESS KEY");
+
+        // the key of UP moves to DOWN, UP gets the previous key of DOWN
+        assert_eq!(menu.handle_key("Up", &mut config), MenuAction::InputChanged);
+        assert_eq!(config.input.down, "Up");
+        assert_eq!(config.input.up, "Down");
+
+        press(&mut menu, &mut config, JoypadButton::A);
+        assert_eq!(menu.handle_key("Escape", &mut config), MenuAction::None);
+        assert_eq!(config.input.find_button("Up"), Some(JoypadButton::Down));
+    }
+
+    #[test]
+    fn test_states() {
+        let mut config = get_config();
+        let mut menu = Menu::default();
+        menu.open();
+        menu.used_slots[1] = true;
+        menu.cursor = MAIN_ITEMS
+            .iter()
+            .position(|i| *i == MainItem::LoadState)
+            .unwrap();
+        press(&mut menu, &mut config, JoypadButton::A);
+        assert_eq!(menu.get_view(&config).items[1], "SLOT 2 USED");
+
+        // empty slots can not be loaded
+        assert_eq!(
+            press(&mut menu, &mut config, JoypadButton::A),
+            MenuAction::None
+        );
+        press(&mut menu, &mut config, JoypadButton::Down);
+        assert_eq!(
+            press(&mut menu, &mut config, JoypadButton::A),
+            MenuAction::LoadState(1)
+        );
+        assert!(!menu.is_open);
+    }
+
+    #[test]
+    fn test_cheats() {
+        let mut config = get_config();
+        let mut menu = Menu::default();
+        menu.open();
+        menu.cheats = vec![("Lives".to_string(), false), ("Time".to_string(), true)];
+        menu.cursor = MAIN_ITEMS
+            .iter()
+            .position(|i| *i == MainItem::Cheats)
+            .unwrap();
+        press(&mut menu, &mut config, JoypadButton::A);
+        assert_eq!(menu.get_view(&config).items, vec!["OFF LIVES", "ON TIME"]);
+
+        assert_eq!(
+            press(&mut menu, &mut config, JoypadButton::A),
+            MenuAction::ToggleCheat(0)
+        );
+        assert_eq!(menu.get_view(&config).items[0], "ON LIVES");
+        assert!(menu.is_open);
+    }
+
+    #[test]
+    fn test_browser() {
+        let dir = std::env::temp_dir().join("gmboy_test_browser");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("game.gb"), []).unwrap();
+        fs::write(dir.join("notes.txt"), []).unwrap();
+
+        let mut config = get_config();
+        config.roms_dir = Some(dir.to_string_lossy().to_string());
+        let mut menu = Menu::default();
+        menu.open();
+        menu.cursor = 1;
+        press(&mut menu, &mut config, JoypadButton::A);
+
+        let view = menu.get_view(&config);
+        assert_eq!(view.title, "GMBOY_TEST_BROWS");
+        assert_eq!(view.items, vec!["../", "SUB/", "GAME.GB"]);
+
+        press(&mut menu, &mut config, JoypadButton::Up);
+        let action = press(&mut menu, &mut config, JoypadButton::A);
+        let path = dir.canonicalize().unwrap().join("game.gb");
+        assert_eq!(
+            action,
+            MenuAction::LoadCart(path.to_string_lossy().to_string())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_archive() {
+        let dir = std::env::temp_dir().join("gmboy_test_menu_archive");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.canonicalize().unwrap().join("games.zip");
+
+        let mut config = get_config();
+        let mut menu = Menu::default();
+        let roms = vec!["a.gb".to_string(), "b.gbc".to_string()];
+        menu.open_archive(&path.to_string_lossy(), roms);
+        assert!(menu.is_open);
+
+        let view = menu.get_view(&config);
+        assert_eq!(view.title, "GAMES.ZIP");
+        assert_eq!(view.items, vec!["../", "A.GB", "B.GBC"]);
+
+        menu.cursor = 2;
+        let action = press(&mut menu, &mut config, JoypadButton::A);
+        let entry = get_entry_path(&path.to_string_lossy(), "b.gbc");
+        assert_eq!(action, MenuAction::LoadCart(entry));
+        assert_eq!(
+            config.roms_dir,
+            Some(dir.canonicalize().unwrap().to_string_lossy().to_string())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recent_carts() {
+        let mut config = get_config();
+
+        for i in 0..12 {
+            config.add_recent_cart(&format!("roms/{}.gb", i));
+        }
+
+        config.add_recent_cart("roms/5.gb");
+        assert_eq!(config.recent_carts.len(), MAX_RECENT_CARTS_TEST);
+        assert_eq!(config.recent_carts[0], "roms/5.gb");
+        assert_eq!(config.recent_carts[1], "roms/11.gb");
+
+        let mut menu = Menu::default();
+        menu.open();
+        menu.cursor = 2;
+        press(&mut menu, &mut config, JoypadButton::A);
+        assert_eq!(menu.get_view(&config).items[0], "5.GB");
+        assert_eq!(
+            press(&mut menu, &mut config, JoypadButton::A),
+            MenuAction::LoadCart("roms/5.gb".to_string())
+        );
+    }
+
+    const MAX_RECENT_CARTS_TEST: usize = crate::config::MAX_RECENT_CARTS;
+}
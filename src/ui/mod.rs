@@ -1,5 +1,11 @@
+mod apu_window;
 mod debug_window;
+pub mod display;
 pub mod events;
+mod memory_window;
+pub mod menu;
+mod oam_window;
+pub mod filters;
 mod text;
 pub mod ui;
 mod audio;
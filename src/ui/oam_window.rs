@@ -0,0 +1,202 @@
+use crate::bus::Bus;
+use crate::ppu::oam::OAM_ENTRIES_COUNT;
+use crate::ppu::oam_inspector::{inspect_sprites, SpriteInfo};
+use crate::ppu::sprite::OamScanLine;
+use crate::ppu::tile::PixelColor;
+use crate::ppu::LCD_Y_RES;
+use crate::ui::text::{draw_text, fill_texture, CHAR_HEIGHT};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, Texture};
+use sdl2::video::{Window, WindowPos};
+use sdl2::VideoSubsystem;
+
+pub const SPRITE_SCALE: u32 = 4;
+pub const CELL_COLS: u32 = 8;
+pub const CELL_ROWS: u32 = 5;
+pub const CELL_WIDTH: u32 = 110;
+pub const CELL_HEIGHT: u32 = 16 * SPRITE_SCALE + 4 * (CHAR_HEIGHT as u32 + 4) + 12;
+pub const SCANLINE_MAP_COL_WIDTH: u32 = 4;
+pub const SCANLINE_MAP_ROW_HEIGHT: u32 = 2;
+pub const PADDING: u32 = 6;
+
+const BG_COLOR: Color = Color::RGB(18, 18, 18);
+const TRANSPARENT_COLOR: Color = Color::RGB(40, 40, 40);
+const SELECTED_COLOR: Color = Color::RGB(60, 180, 75);
+const DROPPED_COLOR: Color = Color::RGB(230, 25, 75);
+const TEXT_COLOR: PixelColor = PixelColor::from_hex(0xFFE0E0E0);
+
+/// Lists all 40 OAM entries with their images and attributes. Entries dropped by the 10 sprites
+/// per line limit are framed red, entries rendered on at least one line are framed green.
+/// The bottom map shows per scanline (rows) which entries (columns) were selected or dropped.
+pub struct OamWindow {
+    pub canvas: Canvas<Window>,
+    text_texture: Texture,
+    width: u32,
+    height: u32,
+}
+
+impl OamWindow {
+    pub fn new(video_subsystem: &VideoSubsystem) -> OamWindow {
+        let width = CELL_COLS * CELL_WIDTH;
+        let map_height = LCD_Y_RES as u32 * SCANLINE_MAP_ROW_HEIGHT;
+        let height = CELL_ROWS * CELL_HEIGHT + map_height + PADDING * 2;
+
+        let window = video_subsystem
+            .window("OAM Inspector", width, height)
+            .position_centered()
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        let mut text_texture = canvas
+            .texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+            .unwrap();
+        text_texture.set_blend_mode(BlendMode::Blend);
+
+        Self {
+            canvas,
+            text_texture,
+            width,
+            height,
+        }
+    }
+
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.canvas
+            .window_mut()
+            .set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    }
+
+    pub fn draw(&mut self, bus: &Bus, scan_lines: &[OamScanLine]) {
+        let sprites = inspect_sprites(bus, scan_lines);
+        self.canvas.set_draw_color(BG_COLOR);
+        self.canvas.clear();
+        fill_texture(&mut self.text_texture, PixelColor::from_hex(0));
+
+        for sprite in sprites.iter() {
+            let col = sprite.oam_index as u32 % CELL_COLS;
+            let row = sprite.oam_index as u32 / CELL_COLS;
+            self.draw_cell(
+                bus,
+                sprite,
+                (col * CELL_WIDTH) as i32,
+                (row * CELL_HEIGHT) as i32,
+            );
+        }
+
+        self.draw_scanline_map(scan_lines, (CELL_ROWS * CELL_HEIGHT + PADDING) as i32);
+
+        self.canvas
+            .copy(
+                &self.text_texture,
+                None,
+                Rect::new(0, 0, self.width, self.height),
+            )
+            .unwrap();
+        self.canvas.present();
+    }
+
+    fn draw_cell(&mut self, bus: &Bus, sprite: &SpriteInfo, x: i32, y: i32) {
+        let frame_color = if sprite.is_dropped() {
+            Some(DROPPED_COLOR)
+        } else if sprite.is_selected() {
+            Some(SELECTED_COLOR)
+        } else {
+            None
+        };
+
+        if let Some(color) = frame_color {
+            self.canvas.set_draw_color(color);
+            self.canvas
+                .draw_rect(Rect::new(x + 1, y + 1, CELL_WIDTH - 2, CELL_HEIGHT - 2))
+                .unwrap();
+        }
+
+        let colors = if sprite.entry.f_pn() {
+            bus.io.lcd.sp2_colors
+        } else {
+            bus.io.lcd.sp1_colors
+        };
+        let img_x = x + PADDING as i32;
+        let img_y = y + PADDING as i32;
+
+        for (py, line) in sprite
+            .pixels
+            .iter()
+            .take(sprite.height as usize)
+            .enumerate()
+        {
+            for (px, color_id) in line.iter().enumerate() {
+                let color_idx = *color_id as usize;
+                let color = if color_idx == 0 {
+                    TRANSPARENT_COLOR
+                } else {
+                    let (r, g, b, a) = colors[color_idx].as_rgba();
+                    Color::RGBA(r, g, b, a)
+                };
+
+                self.canvas.set_draw_color(color);
+                self.canvas
+                    .fill_rect(Rect::new(
+                        img_x + (px as u32 * SPRITE_SCALE) as i32,
+                        img_y + (py as u32 * SPRITE_SCALE) as i32,
+                        SPRITE_SCALE,
+                        SPRITE_SCALE,
+                    ))
+                    .unwrap();
+            }
+        }
+
+        let entry = sprite.entry;
+        let lines = [
+            format!("{:02} T{:02X}", sprite.oam_index, entry.tile_index),
+            format!("X{:02X} Y{:02X}", entry.x, entry.y),
+            format!(
+                "P{} {}{}{}",
+                entry.f_pn() as u8,
+                if entry.f_x_flip() { "X" } else { " " },
+                if entry.f_y_flip() { "Y" } else { " " },
+                if entry.f_bgp() { "B" } else { " " },
+            ),
+            format!(
+                "L{} D{}",
+                sprite.selected_lines.len(),
+                sprite.dropped_lines.len()
+            ),
+        ];
+        let text_x = img_x as usize;
+        let mut text_y = (img_y + (16 * SPRITE_SCALE) as i32 + PADDING as i32) as usize;
+
+        for line in lines.iter() {
+            draw_text(&mut self.text_texture, line, TEXT_COLOR, text_x, text_y, 1);
+            text_y += CHAR_HEIGHT + 4;
+        }
+    }
+
+    fn draw_scanline_map(&mut self, scan_lines: &[OamScanLine], y: i32) {
+        let x = ((self.width - OAM_ENTRIES_COUNT as u32 * SCANLINE_MAP_COL_WIDTH) / 2) as i32;
+
+        for (ly, line) in scan_lines.iter().enumerate() {
+            for oam_index in 0..OAM_ENTRIES_COUNT {
+                let color = if line.is_dropped(oam_index) {
+                    DROPPED_COLOR
+                } else if line.is_selected(oam_index) {
+                    SELECTED_COLOR
+                } else {
+                    TRANSPARENT_COLOR
+                };
+
+                self.canvas.set_draw_color(color);
+                self.canvas
+                    .fill_rect(Rect::new(
+                        x + (oam_index as u32 * SCANLINE_MAP_COL_WIDTH) as i32,
+                        y + (ly as u32 * SCANLINE_MAP_ROW_HEIGHT) as i32,
+                        SCANLINE_MAP_COL_WIDTH - 1,
+                        SCANLINE_MAP_ROW_HEIGHT,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+}
@@ -49,7 +49,7 @@ pub fn draw_text(
                     continue;
                 }
 
-                let Some(char_index) = get_font_index(c) else {
+                let Some(char_index) = get_font_index(c.to_ascii_uppercase()) else {
                     continue;
                 };
 
@@ -71,7 +71,16 @@ pub fn draw_text(
                                     let px = text_pixel_x + dx;
                                     let py = text_pixel_y + dy;
 
+                                    // Clip to the texture
+                                    if px >= pitch / BYTES_PER_PIXEL {
+                                        continue;
+                                    }
+
                                     let text_offset = (py * pitch) + (px * BYTES_PER_PIXEL);
+                                    if text_offset + BYTES_PER_PIXEL > buffer.len() {
+                                        continue;
+                                    }
+
                                     let (r, g, b, a) = color.as_rgba();
                                     buffer[text_offset] = r;
                                     buffer[text_offset + 1] = g;
@@ -94,11 +103,17 @@ fn get_font_index(c: char) -> Option<usize> {
     match c {
         'A'..='Z' => Some((c as usize) - ('A' as usize)), // A=0, B=1, ..., Z=25
         '0'..='9' => Some((c as usize) - ('0' as usize) + 26), // 0=26, 1=27, ..., 9=35
-        _ => None,                                        // Unsupported character
+        '>' => Some(36),
+        '.' => Some(37),
+        '-' => Some(38),
+        '/' => Some(39),
+        '_' => Some(40),
+        ':' => Some(41),
+        _ => None, // Unsupported character
     }
 }
 
-const FONT: [[u8; 8]; 36] = [
+const FONT: [[u8; 8]; 42] = [
     // A-Z
     [
         0b01111110, 0b10000001, 0b10000001, 0b11111111, 0b10000001, 0b10000001, 0b10000001,
@@ -245,4 +260,29 @@ const FONT: [[u8; 8]; 36] = [
         0b01111110, 0b10000001, 0b10000001, 0b01111111, 0b00000001, 0b10000001, 0b01111110,
         0b00000000,
     ], // '9'
+    // Symbols
+    [
+        0b01100000, 0b00110000, 0b00011000, 0b00001100, 0b00011000, 0b00110000, 0b01100000,
+        0b00000000,
+    ], // '>'
+    [
+        0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00011000, 0b00011000,
+        0b00000000,
+    ], // '.'
+    [
+        0b00000000, 0b00000000, 0b00000000, 0b01111110, 0b00000000, 0b00000000, 0b00000000,
+        0b00000000,
+    ], // '-'
+    [
+        0b00000001, 0b00000010, 0b00000100, 0b00001000, 0b00010000, 0b00100000, 0b01000000,
+        0b00000000,
+    ], // '/'
+    [
+        0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b00000000, 0b11111111,
+        0b00000000,
+    ], // '_'
+    [
+        0b00000000, 0b00011000, 0b00011000, 0b00000000, 0b00011000, 0b00011000, 0b00000000,
+        0b00000000,
+    ], // ':'
 ];
@@ -1,12 +1,23 @@
 use crate::bus::Bus;
-use crate::config::GraphicsConfig;
+use crate::config::{AudioConfig, GraphicsConfig, InputConfig};
 use crate::emu::RunMode;
 use crate::ppu::{Ppu, LCD_X_RES, LCD_Y_RES};
 use crate::tile::PixelColor;
+use crate::ui::apu_window::ApuWindow;
 use crate::ui::audio::{GameAudio};
 use crate::ui::debug_window::DebugWindow;
+use crate::ui::display::{
+    get_content_size, load_border, snap_window_size, DisplayMode, DisplayRects, BORDER_HEIGHT,
+    BORDER_WIDTH,
+};
 use crate::ui::events::{UiEvent, UiEventHandler};
-use crate::ui::text::{calc_text_width, draw_text, fill_texture, get_text_height};
+use crate::ui::filters::{FilterPipeline, Image};
+use crate::ui::memory_window::MemoryWindow;
+use crate::ui::menu::MenuView;
+use crate::ui::oam_window::OamWindow;
+use crate::ui::text::{
+    calc_text_width, draw_text, fill_texture, get_text_height, CHAR_HEIGHT, CHAR_SPACING,
+};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::PixelFormatEnum;
@@ -18,6 +29,9 @@ use sdl2::EventPump;
 pub const SCREEN_WIDTH: u32 = 640;
 pub const SCREEN_HEIGHT: u32 = 480;
 pub const BYTES_PER_PIXEL: usize = 4;
+const MENU_X: usize = 2;
+const MENU_Y: usize = 2;
+const MENU_LINE_HEIGHT: usize = CHAR_HEIGHT + CHAR_SPACING;
 
 pub struct Ui {
     _sdl_context: sdl2::Sdl,
@@ -25,14 +39,21 @@ pub struct Ui {
 
     canvas: Canvas<Window>,
     texture: Texture,
+    filtered_texture: Option<Texture>,
+    border_texture: Option<Texture>,
+    filters: FilterPipeline,
     overlay_texture: Texture,
     fps_texture: Texture,
     debug_window: Option<DebugWindow>,
+    oam_window: Option<OamWindow>,
+    memory_window: Option<MemoryWindow>,
+    apu_window: Option<ApuWindow>,
     layout: Layout,
 
     pub audio: GameAudio,
 
     pub config: GraphicsConfig,
+    pub input: InputConfig,
     pub curr_palette: [PixelColor; 4],
 }
 
@@ -45,31 +66,60 @@ pub struct Layout {
 }
 
 impl Layout {
-    pub fn new(scale: f32) -> Self {
+    pub fn new(scale: f32, has_border: bool) -> Self {
+        let (width, height) = get_content_size(has_border);
+
         Self {
             spacer: 8 * scale as i32,
             y_spacer: scale as i32,
             x_draw_start: scale as i32 / 2,
-            win_width: LCD_X_RES as u32 * scale as u32,
-            win_height: LCD_Y_RES as u32 * scale as u32,
+            win_width: width * scale as u32,
+            win_height: height * scale as u32,
         }
     }
 }
 
 impl Ui {
-    pub fn new(config: GraphicsConfig, debug: bool) -> Result<Self, String> {
+    pub fn new(
+        config: GraphicsConfig,
+        audio_config: &AudioConfig,
+        input: InputConfig,
+        debug: bool,
+    ) -> Result<Self, String> {
         let sdl_context = sdl2::init()?;
         let video_subsystem = sdl_context.video()?;
-        let layout = Layout::new(config.scale);
+        let layout = Layout::new(config.scale, config.border_path.is_some());
+        let (width, height) = config
+            .window_size
+            .unwrap_or((layout.win_width, layout.win_height));
+        let mut main_window = video_subsystem.window("GMBoy", width, height);
+
+        if let Some((x, y)) = config.window_position {
+            main_window.position(x, y);
+        } else {
+            main_window.position_centered();
+        }
 
-        let main_window = video_subsystem
-            .window("GMBoy", layout.win_width, layout.win_height)
-            .position_centered()
-            .resizable()
-            .build()
-            .unwrap();
+        let main_window = main_window.resizable().build().unwrap();
         let main_canvas = main_window.into_canvas().build().unwrap();
         let texture_creator = main_canvas.texture_creator();
+        let border_texture = config.border_path.as_ref().and_then(|path| {
+            let border = load_border(path)
+                .inspect_err(|err| eprintln!("Failed to load border: {}", err))
+                .ok()?;
+            let mut texture = texture_creator
+                .create_texture_static(PixelFormatEnum::RGBA32, BORDER_WIDTH, BORDER_HEIGHT)
+                .unwrap();
+            texture
+                .update(
+                    None,
+                    border.pixels.as_flattened(),
+                    BORDER_WIDTH as usize * BYTES_PER_PIXEL,
+                )
+                .unwrap();
+
+            Some(texture)
+        });
         let texture = texture_creator
             .create_texture_streaming(PixelFormatEnum::RGBA32, LCD_X_RES as u32, LCD_Y_RES as u32)
             .unwrap();
@@ -91,13 +141,20 @@ impl Ui {
             event_pump: sdl_context.event_pump()?,
             canvas: main_canvas,
             debug_window: if debug { Some(debug_window) } else { None },
+            oam_window: None,
+            memory_window: None,
+            apu_window: None,
             layout,
             curr_palette: into_pallet(&config.pallets[config.selected_pallet_idx].hex_colors),
-            config,
             texture,
+            filtered_texture: None,
+            border_texture,
+            filters: FilterPipeline::new(config.filters.clone()),
+            config,
+            input,
             overlay_texture,
             fps_texture,
-            audio: GameAudio::new(&sdl_context),
+            audio: GameAudio::new(&sdl_context, audio_config),
 
             _sdl_context: sdl_context,
         })
@@ -105,7 +162,7 @@ impl Ui {
 
     fn set_scale(&mut self, scale: f32) -> Result<(), String> {
         self.config.scale = scale;
-        self.layout = Layout::new(scale);
+        self.layout = Layout::new(scale, self.border_texture.is_some());
         let window = self.canvas.window_mut();
         window
             .set_size(self.layout.win_width, self.layout.win_height)
@@ -118,7 +175,21 @@ impl Ui {
         Ok(())
     }
 
+    /// Applies graphics settings changed outside of the UI, e.g. by the menu.
+    pub fn apply_config(&mut self, bus: &mut Bus, config: GraphicsConfig) -> Result<(), String> {
+        let scale = config.scale;
+        let is_scale_changed = scale != self.config.scale;
+        self.config = config;
+        self.curr_palette =
+            into_pallet(&self.config.pallets[self.config.selected_pallet_idx].hex_colors);
+        bus.io.lcd.set_pallet(self.curr_palette);
+
+        if is_scale_changed {
+            self.set_scale(scale)?;
+        }
 
+        Ok(())
+    }
 
     pub fn draw(&mut self, ppu: &Ppu, bus: &Bus) {
         self.draw_main(ppu);
@@ -126,12 +197,101 @@ impl Ui {
         if let Some(debug_window) = self.debug_window.as_mut() {
             debug_window.draw(bus);
         }
+
+        if let Some(oam_window) = self.oam_window.as_mut() {
+            oam_window.draw(bus, &ppu.pipeline.sprite_fetcher.scan_lines);
+        }
+
+        if let Some(memory_window) = self.memory_window.as_mut() {
+            memory_window.draw(bus);
+        }
+
+        if let Some(apu_window) = self.apu_window.as_mut() {
+            apu_window.draw(&bus.io.apu);
+        }
+    }
+
+    /// Runs the frame through the filters on the CPU, the texture follows the output size.
+    fn draw_filtered(&mut self, ppu: &Ppu, dest_rect: Rect) {
+        let image = self.filters.apply(Image::from_pixels(&ppu.pipeline.buffer));
+        let (width, height) = (image.width as u32, image.height as u32);
+        let is_same_size = self.filtered_texture.as_ref().is_some_and(|texture| {
+            let query = texture.query();
+            query.width == width && query.height == height
+        });
+
+        if !is_same_size {
+            let texture = self
+                .canvas
+                .texture_creator()
+                .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+                .unwrap();
+            self.filtered_texture = Some(texture);
+        }
+
+        let texture = self.filtered_texture.as_mut().unwrap();
+        texture
+            .with_lock(None, |buffer: &mut [u8], pitch: usize| {
+                for (y, line) in image.pixels.chunks(image.width).enumerate() {
+                    for (x, color) in line.iter().enumerate() {
+                        let offset = (y * pitch) + (x * BYTES_PER_PIXEL);
+                        buffer[offset..offset + BYTES_PER_PIXEL].copy_from_slice(color);
+                    }
+                }
+            })
+            .unwrap();
+
+        self.canvas.copy(texture, None, Some(dest_rect)).unwrap();
+    }
+
+    fn toggle_oam_window(&mut self) {
+        if self.oam_window.is_some() {
+            self.oam_window = None;
+            return;
+        }
+
+        let video_subsystem = self._sdl_context.video().unwrap();
+        let mut oam_window = OamWindow::new(&video_subsystem);
+        let (x, y) = self.canvas.window().position();
+        oam_window.set_position(x + self.layout.win_width as i32 + 10, y);
+        self.oam_window = Some(oam_window);
+    }
+
+    fn toggle_memory_window(&mut self) {
+        if self.memory_window.is_some() {
+            self.memory_window = None;
+            return;
+        }
+
+        let video_subsystem = self._sdl_context.video().unwrap();
+        let mut memory_window = MemoryWindow::new(&video_subsystem);
+        let (x, y) = self.canvas.window().position();
+        memory_window.set_position(x, y + self.layout.win_height as i32 + 40);
+        self.memory_window = Some(memory_window);
+    }
+
+    fn toggle_apu_window(&mut self) {
+        if self.apu_window.is_some() {
+            self.apu_window = None;
+            return;
+        }
+
+        let video_subsystem = self._sdl_context.video().unwrap();
+        let mut apu_window = ApuWindow::new(&video_subsystem);
+        let (x, y) = self.canvas.window().position();
+        apu_window.set_position(x + self.layout.win_width as i32 + 10, y);
+        self.apu_window = Some(apu_window);
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        if let Err(e) = self.canvas.window_mut().set_title(title) {
+            eprintln!("Failed to set window title: {}", e);
+        }
     }
 
     pub fn draw_text(&mut self, text: &str) {
         self.canvas.clear();
 
-        let (win_width, win_height) = self.canvas.window().size();
         let scale = self.config.text_scale;
         let text_width = calc_text_width(text, scale);
         // Calculate the x and y positions to center the text
@@ -148,7 +308,7 @@ impl Ui {
             y,
             scale,
         );
-        let dest_rect = calculate_scaled_rect(win_width, win_height);
+        let dest_rect = self.get_display_rects().screen;
 
         self.canvas
             .copy(&self.overlay_texture, None, Some(dest_rect))
@@ -157,32 +317,92 @@ impl Ui {
         self.canvas.present();
     }
 
-    fn draw_main(&mut self, ppu: &Ppu) {
+    pub fn draw_menu(&mut self, view: &MenuView) {
         self.canvas.clear();
+        fill_texture(&mut self.overlay_texture, self.curr_palette[3]);
+        let color = self.curr_palette[0];
+        draw_text(&mut self.overlay_texture, &view.title, color, MENU_X, MENU_Y, 1);
+
+        for (i, item) in view.items.iter().enumerate() {
+            let prefix = if i == view.cursor { "> " } else { "  " };
+            let y = MENU_Y + (i + 2) * MENU_LINE_HEIGHT;
+            let text = format!("{}{}", prefix, item);
+            draw_text(&mut self.overlay_texture, &text, color, MENU_X, y, 1);
+        }
 
-        self.texture
-            .with_lock(None, |buffer: &mut [u8], pitch: usize| {
-                for y in 0..LCD_Y_RES as usize {
-                    for x in 0..LCD_X_RES as usize {
-                        let pixel = ppu.pipeline.buffer[x + (y * LCD_X_RES as usize)];
-                        let (r, g, b, a) = pixel.color.as_rgba();
-                        let offset = (y * pitch) + (x * BYTES_PER_PIXEL);
-                        buffer[offset] = r;
-                        buffer[offset + 1] = g;
-                        buffer[offset + 2] = b;
-                        buffer[offset + 3] = a;
-                    }
-                }
-            })
+        let dest_rect = self.get_display_rects().screen;
+        self.canvas
+            .copy(&self.overlay_texture, None, Some(dest_rect))
             .unwrap();
+        self.canvas.present();
+    }
 
+    fn get_display_rects(&self) -> DisplayRects {
         let (win_width, win_height) = self.canvas.window().size();
-        let dest_rect = calculate_scaled_rect(win_width, win_height);
 
-        // Copy the texture while maintaining aspect ratio
-        self.canvas
-            .copy(&self.texture, None, Some(dest_rect))
-            .unwrap();
+        DisplayRects::new(
+            win_width,
+            win_height,
+            self.config.display_mode,
+            self.border_texture.is_some(),
+        )
+    }
+
+    /// Remembers the main window geometry, in integer mode the size snaps to the scale.
+    fn on_window_changed(&mut self) {
+        if self.config.is_fullscreen {
+            return;
+        }
+
+        let window = self.canvas.window_mut();
+        let (width, height) = window.size();
+
+        if self.config.display_mode == DisplayMode::Integer {
+            let snapped = snap_window_size(width, height, self.border_texture.is_some());
+
+            if snapped != (width, height) {
+                window.set_size(snapped.0, snapped.1).unwrap();
+            }
+        }
+
+        self.config.window_size = Some(window.size());
+        self.config.window_position = Some(window.position());
+    }
+
+    fn draw_main(&mut self, ppu: &Ppu) {
+        self.canvas.clear();
+
+        let rects = self.get_display_rects();
+        let dest_rect = rects.screen;
+
+        if let (Some(border), Some(texture)) = (rects.border, self.border_texture.as_ref()) {
+            self.canvas.copy(texture, None, Some(border)).unwrap();
+        }
+
+        if self.filters.config.is_enabled() {
+            self.draw_filtered(ppu, dest_rect);
+        } else {
+            self.texture
+                .with_lock(None, |buffer: &mut [u8], pitch: usize| {
+                    for y in 0..LCD_Y_RES as usize {
+                        for x in 0..LCD_X_RES as usize {
+                            let pixel = ppu.pipeline.buffer[x + (y * LCD_X_RES as usize)];
+                            let (r, g, b, a) = pixel.color.as_rgba();
+                            let offset = (y * pitch) + (x * BYTES_PER_PIXEL);
+                            buffer[offset] = r;
+                            buffer[offset + 1] = g;
+                            buffer[offset + 2] = b;
+                            buffer[offset + 3] = a;
+                        }
+                    }
+                })
+                .unwrap();
+
+            // Copy the texture while maintaining aspect ratio
+            self.canvas
+                .copy(&self.texture, None, Some(dest_rect))
+                .unwrap();
+        }
 
         if self.config.show_fps {
             let text = ppu.fps.to_string();
@@ -206,8 +426,28 @@ impl Ui {
                 Event::Quit { .. } => event_handler.on_event(bus, UiEvent::Quit),
                 Event::KeyDown {
                     keycode: Some(keycode),
+                    window_id,
                     ..
                 } => {
+                    if let Some(window) = self.memory_window.as_mut() {
+                        if window.canvas.window().id() == window_id {
+                            window.handle_key(bus, keycode);
+                            continue;
+                        }
+                    }
+
+                    if let Some(window) = self.apu_window.as_mut() {
+                        if window.canvas.window().id() == window_id {
+                            window.handle_key(&mut bus.io.apu, keycode);
+                            continue;
+                        }
+                    }
+
+                    if event_handler.is_menu_open() {
+                        event_handler.on_event(bus, UiEvent::MenuKey(keycode.name()));
+                        continue;
+                    }
+
                     if let Some(evt) = self.handle_key(bus, keycode, true) {
                         event_handler.on_event(bus, evt);
                     }
@@ -215,16 +455,46 @@ impl Ui {
                 Event::KeyUp {
                     keycode: Some(keycode),
                     ..
-                } => {
+                } if !event_handler.is_menu_open() => {
                     if let Some(evt) = self.handle_key(bus, keycode, false) {
                         event_handler.on_event(bus, evt);
                     }
                 }
+                Event::Window {
+                    win_event:
+                        sdl2::event::WindowEvent::SizeChanged(..) | sdl2::event::WindowEvent::Moved(..),
+                    window_id,
+                    ..
+                } if window_id == self.canvas.window().id() => {
+                    self.on_window_changed();
+                    event_handler.on_event(bus, UiEvent::ConfigChanged(self.config.clone()));
+                }
                 Event::Window {
                     win_event: sdl2::event::WindowEvent::Close,
                     window_id,
                     ..
                 } => {
+                    if let Some(window) = self.oam_window.as_mut() {
+                        if window.canvas.window().id() == window_id {
+                            self.oam_window = None;
+                            continue;
+                        }
+                    }
+
+                    if let Some(window) = self.memory_window.as_mut() {
+                        if window.canvas.window().id() == window_id {
+                            self.memory_window = None;
+                            continue;
+                        }
+                    }
+
+                    if let Some(window) = self.apu_window.as_mut() {
+                        if window.canvas.window().id() == window_id {
+                            self.apu_window = None;
+                            continue;
+                        }
+                    }
+
                     if let Some(window) = self.debug_window.as_mut() {
                         if window.canvas.window().id() == window_id {
                             self.debug_window = None;
@@ -239,15 +509,13 @@ impl Ui {
     }
 
     fn handle_key(&mut self, bus: &mut Bus, keycode: Keycode, is_down: bool) -> Option<UiEvent> {
+        if let Some(button) = self.input.find_button(&keycode.name()) {
+            bus.io.joypad.set_button(button, is_down);
+            return None;
+        }
+
         match keycode {
-            Keycode::UP => bus.io.joypad.up = is_down,
-            Keycode::DOWN => bus.io.joypad.down = is_down,
-            Keycode::LEFT => bus.io.joypad.left = is_down,
-            Keycode::RIGHT => bus.io.joypad.right = is_down,
-            Keycode::Z => bus.io.joypad.b = is_down,
-            Keycode::X => bus.io.joypad.a = is_down,
-            Keycode::Return => bus.io.joypad.start = is_down,
-            Keycode::BACKSPACE => bus.io.joypad.select = is_down,
+            Keycode::Escape if !is_down => return Some(UiEvent::ToggleMenu),
             Keycode::LCTRL | Keycode::RCTRL => {
                 return if is_down {
                     Some(UiEvent::Mode(RunMode::Rewind))
@@ -297,6 +565,18 @@ impl Ui {
                     return Some(UiEvent::ConfigChanged(self.config.clone()));
                 }
             }
+            Keycode::M if !is_down => {
+                self.config.display_mode = self.config.display_mode.get_next();
+                self.on_window_changed();
+                return Some(UiEvent::ConfigChanged(self.config.clone()));
+            }
+            Keycode::F2 if !is_down => self.toggle_oam_window(),
+            Keycode::F3 if !is_down => self.toggle_memory_window(),
+            Keycode::F4 if !is_down => return Some(UiEvent::ToggleCheats),
+            Keycode::F5 if !is_down => self.toggle_apu_window(),
+            Keycode::F6 if !is_down => return Some(UiEvent::ToggleAudioRecording),
+            Keycode::F7 if !is_down => return Some(UiEvent::ToggleVideoRecording),
+            Keycode::F12 if !is_down => return Some(UiEvent::Screenshot),
             Keycode::P => {
                 if !is_down {
                     self.config.selected_pallet_idx = get_next_pallet_idx(
@@ -333,29 +613,6 @@ impl Ui {
     }
 }
 
-fn calculate_scaled_rect(window_width: u32, window_height: u32) -> Rect {
-    let screen_aspect = window_width as f32 / window_height as f32;
-    let game_aspect = LCD_X_RES as f32 / LCD_Y_RES as f32;
-
-    let (new_width, new_height) = if screen_aspect > game_aspect {
-        // Screen is wider than game: Fit height, adjust width
-        let height = window_height;
-        let width = ((height as f32) * game_aspect) as u32;
-        (width, height)
-    } else {
-        // Screen is taller than game: Fit width, adjust height
-        let width = window_width;
-        let height = ((width as f32) / game_aspect) as u32;
-        (width, height)
-    };
-
-    // Center the image in the screen
-    let x = ((window_width - new_width) / 2) as i32;
-    let y = ((window_height - new_height) / 2) as i32;
-
-    Rect::new(x, y, new_width, new_height)
-}
-
 pub fn into_pallet(hex_colors: &[String]) -> [PixelColor; 4] {
     let colors: Vec<PixelColor> = hex_colors
         .iter()
@@ -0,0 +1,58 @@
+use crate::TestCpuCtx;
+use gmboy::auxiliary::clock::Clock;
+use gmboy::bus::Bus;
+use gmboy::cart::Cart;
+use gmboy::cpu::Cpu;
+use gmboy::debugger::{CpuLogType, Debugger};
+use gmboy::emu::read_bytes;
+use gmboy::ppu::lcd::BLACK_WHITE_PALLET;
+use gmboy::Ppu;
+use std::path::PathBuf;
+
+/// The test image is static, it is complete long before this frame.
+const ACID2_FRAMES: usize = 30;
+
+#[test]
+fn test_dmg_acid2() {
+    let root = PathBuf::from("tests").join("roms");
+    let cart = Cart::new(read_bytes(root.join("dmg-acid2.gb").to_str().unwrap()).unwrap()).unwrap();
+    let expected = std::fs::read_to_string(root.join("dmg-acid2.txt")).unwrap();
+    let mut callback = TestCpuCtx {
+        clock: Clock::with_ppu(Ppu::with_fps_limit(10000.0)),
+        debugger: Debugger::new(CpuLogType::None, false),
+    };
+    let mut cpu = Cpu::new(Bus::new(cart));
+
+    while callback.clock.ppu.as_ref().unwrap().current_frame < ACID2_FRAMES {
+        cpu.step(&mut callback).unwrap();
+    }
+
+    let actual = render_shades(&callback.clock.ppu.unwrap());
+
+    assert_eq!(actual.lines().count(), expected.lines().count());
+
+    for (y, (actual, expected)) in actual.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(actual, expected, "dmg-acid2: line {y} differs");
+    }
+}
+
+/// Renders the frame buffer as one row of shade indices (0-3) per line.
+fn render_shades(ppu: &Ppu) -> String {
+    ppu.pipeline
+        .buffer
+        .chunks(160)
+        .map(|line| {
+            line.iter()
+                .map(|pixel| {
+                    let shade = BLACK_WHITE_PALLET
+                        .iter()
+                        .position(|color| *color == pixel.color)
+                        .unwrap();
+
+                    char::from(b'0' + shade as u8)
+                })
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
@@ -0,0 +1,226 @@
+use crate::TestCpuCtx;
+use gmboy::auxiliary::clock::Clock;
+use gmboy::bus::Bus;
+use gmboy::cart::Cart;
+use gmboy::cpu::Cpu;
+use gmboy::debugger::{CpuLogType, Debugger};
+use gmboy::emu::read_bytes;
+use gmboy::ppu::lcd::BLACK_WHITE_PALLET;
+use gmboy::Ppu;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// The tests signal completion with `LD B,B`, all of them reach it within a few frames.
+const MAX_FRAMES: usize = 60;
+/// Shades of the DMG-blob expected images, from white to black.
+const EXPECTED_SHADES: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+const LD_B_B: u8 = 0x40;
+
+// The ROMs and the DMG-blob expected images of mealybug-tearoom-tests are not in the tree.
+// They go in tests/mealybug/roms and tests/mealybug/expected, named after the test.
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m2_win_en_toggle() {
+    run_mealybug_rom("m2_win_en_toggle");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_bgp_change() {
+    run_mealybug_rom("m3_bgp_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_bgp_change_sprites() {
+    run_mealybug_rom("m3_bgp_change_sprites");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_bg_en_change() {
+    run_mealybug_rom("m3_lcdc_bg_en_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_bg_map_change() {
+    run_mealybug_rom("m3_lcdc_bg_map_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_obj_en_change() {
+    run_mealybug_rom("m3_lcdc_obj_en_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_obj_en_change_variant() {
+    run_mealybug_rom("m3_lcdc_obj_en_change_variant");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_obj_size_change() {
+    run_mealybug_rom("m3_lcdc_obj_size_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_obj_size_change_scx() {
+    run_mealybug_rom("m3_lcdc_obj_size_change_scx");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_tile_sel_change() {
+    run_mealybug_rom("m3_lcdc_tile_sel_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_tile_sel_win_change() {
+    run_mealybug_rom("m3_lcdc_tile_sel_win_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_win_en_change_multiple() {
+    run_mealybug_rom("m3_lcdc_win_en_change_multiple");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_win_en_change_multiple_wx() {
+    run_mealybug_rom("m3_lcdc_win_en_change_multiple_wx");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_lcdc_win_map_change() {
+    run_mealybug_rom("m3_lcdc_win_map_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_obp0_change() {
+    run_mealybug_rom("m3_obp0_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_scx_high_5_bits() {
+    run_mealybug_rom("m3_scx_high_5_bits");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_scx_low_3_bits() {
+    run_mealybug_rom("m3_scx_low_3_bits");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_scy_change() {
+    run_mealybug_rom("m3_scy_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_window_timing() {
+    run_mealybug_rom("m3_window_timing");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_window_timing_wx_0() {
+    run_mealybug_rom("m3_window_timing_wx_0");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_wx_4_change() {
+    run_mealybug_rom("m3_wx_4_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_wx_4_change_sprites() {
+    run_mealybug_rom("m3_wx_4_change_sprites");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_wx_5_change() {
+    run_mealybug_rom("m3_wx_5_change");
+}
+
+#[test]
+#[ignore = "mealybug-tearoom ROMs are not in the tree"]
+fn test_m3_wx_6_change() {
+    run_mealybug_rom("m3_wx_6_change");
+}
+
+/// Runs the ROM until `LD B,B` and compares the frame with the expected image.
+fn run_mealybug_rom(name: &str) {
+    let root = PathBuf::from("tests").join("mealybug");
+    let rom_path = root.join("roms").join(format!("{name}.gb"));
+    let cart = Cart::new(read_bytes(rom_path.to_str().unwrap()).unwrap()).unwrap();
+    let expected = read_expected_shades(&root.join("expected").join(format!("{name}.png")));
+    let mut callback = TestCpuCtx {
+        clock: Clock::with_ppu(Ppu::with_fps_limit(10000.0)),
+        debugger: Debugger::new(CpuLogType::None, false),
+    };
+    let mut cpu = Cpu::new(Bus::new(cart));
+
+    while cpu.bus.read(cpu.registers.pc) != LD_B_B {
+        cpu.step(&mut callback).unwrap();
+
+        let frame = callback.clock.ppu.as_ref().unwrap().current_frame;
+        assert!(frame < MAX_FRAMES, "{name}: LD B,B not reached");
+    }
+
+    let actual: Vec<u8> = callback
+        .clock
+        .ppu
+        .unwrap()
+        .pipeline
+        .buffer
+        .iter()
+        .map(|pixel| {
+            BLACK_WHITE_PALLET
+                .iter()
+                .position(|color| *color == pixel.color)
+                .unwrap() as u8
+        })
+        .collect();
+
+    assert_eq!(actual.len(), expected.len(), "{name}: image size differs");
+
+    for (y, (actual, expected)) in actual.chunks(160).zip(expected.chunks(160)).enumerate() {
+        assert_eq!(actual, expected, "{name}: line {y} differs");
+    }
+}
+
+/// Reads the expected image as shade indices (0-3), see `EXPECTED_SHADES`.
+fn read_expected_shades(path: &Path) -> Vec<u8> {
+    let mut decoder = png::Decoder::new(File::open(path).unwrap());
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    let channels = info.color_type.samples();
+
+    buf[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| {
+            EXPECTED_SHADES
+                .iter()
+                .position(|shade| *shade == pixel[0])
+                .unwrap_or_else(|| panic!("{}: unexpected shade {}", path.display(), pixel[0]))
+                as u8
+        })
+        .collect()
+}
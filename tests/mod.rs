@@ -3,7 +3,9 @@ use gmboy::bus::Bus;
 use gmboy::debugger::Debugger;
 use gmboy::{Cpu, CpuCallback, DebugCtx};
 
+mod acid2;
 mod blargg;
+mod mealybug;
 mod mooneye;
 mod sm83;
 
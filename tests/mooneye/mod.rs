@@ -4,6 +4,9 @@ use std::time::Duration;
 mod util;
 
 const TIMEOUT: Duration = Duration::from_secs(2);
+/// The MBC1 bits tests write every address of a register range and the sprite timing test
+/// runs many lines of sprites, both slow in debug builds.
+const BITS_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[test]
 fn test_oam_dma_basic() {
@@ -430,3 +433,230 @@ fn test_rst_timing() {
 
     assert_result(name, category, result);
 }
+
+#[test]
+fn test_ppu_hblank_ly_scx_timing_gs() {
+    let name = "hblank_ly_scx_timing-GS";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[ignore] // FIXME: the mode 2 interrupt of line 0 comes an M-cycle early after VBlank
+#[test]
+fn test_ppu_intr_1_2_timing_gs() {
+    let name = "intr_1_2_timing-GS";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[ignore] // FIXME: the mode 0 interrupt comes an M-cycle early, moving it breaks hblank_ly_scx_timing
+#[test]
+fn test_ppu_intr_2_0_timing() {
+    let name = "intr_2_0_timing";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_intr_2_mode0_timing() {
+    let name = "intr_2_mode0_timing";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_intr_2_mode0_timing_sprites() {
+    let name = "intr_2_mode0_timing_sprites";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, BITS_TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_intr_2_mode3_timing() {
+    let name = "intr_2_mode3_timing";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_intr_2_oam_ok_timing() {
+    let name = "intr_2_oam_ok_timing";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_lcdon_timing_gs() {
+    let name = "lcdon_timing-GS";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_lcdon_write_timing_gs() {
+    let name = "lcdon_write_timing-GS";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_stat_irq_blocking() {
+    let name = "stat_irq_blocking";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_stat_lyc_onoff() {
+    let name = "stat_lyc_onoff";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_ppu_vblank_stat_intr_gs() {
+    let name = "vblank_stat_intr-GS";
+    let category = MooneyeRomCategory::Ppu.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_bits_bank1() {
+    let name = "bits_bank1";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, BITS_TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_bits_bank2() {
+    let name = "bits_bank2";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, BITS_TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_bits_mode() {
+    let name = "bits_mode";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, BITS_TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_bits_ramg() {
+    let name = "bits_ramg";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, BITS_TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_multicart_rom_8mb() {
+    let name = "multicart_rom_8Mb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_ram_256kb() {
+    let name = "ram_256kb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_ram_64kb() {
+    let name = "ram_64kb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_rom_16mb() {
+    let name = "rom_16Mb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_rom_1mb() {
+    let name = "rom_1Mb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_rom_2mb() {
+    let name = "rom_2Mb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_rom_4mb() {
+    let name = "rom_4Mb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_rom_512kb() {
+    let name = "rom_512kb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
+
+#[test]
+fn test_mbc1_rom_8mb() {
+    let name = "rom_8Mb";
+    let category = MooneyeRomCategory::Mbc1.into();
+    let result = run_mooneye_rom(name, category, TIMEOUT);
+
+    assert_result(name, category, result);
+}
@@ -63,10 +63,17 @@ pub enum MooneyeRomCategory {
     Interrupts,
     Timer,
     Timing,
+    Ppu,
+    /// Emulator-only MBC1 tests.
+    Mbc1,
 }
 
 pub fn get_mooneye_rom_path(rom_name: &str, category: Option<MooneyeRomCategory>) -> PathBuf {
-    let mut root = PathBuf::from("tests").join("mooneye").join("acceptance");
+    let suite = match category {
+        Some(MooneyeRomCategory::Mbc1) => "emulator-only",
+        _ => "acceptance",
+    };
+    let mut root = PathBuf::from("tests").join("mooneye").join(suite);
 
     if let Some(category) = category {
         root = root.join(category.to_string());
@@ -86,6 +93,8 @@ impl Display for MooneyeRomCategory {
             MooneyeRomCategory::Interrupts => "interrupts",
             MooneyeRomCategory::Timer => "timer",
             MooneyeRomCategory::Timing => "timing",
+            MooneyeRomCategory::Ppu => "ppu",
+            MooneyeRomCategory::Mbc1 => "mbc1",
         };
 
         write!(f, "{}", dir)
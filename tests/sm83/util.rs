@@ -11,9 +11,12 @@ pub fn run_test_case(test_case: &Sm83TestCase, print_result: bool) {
     let title = format!("Test case '{}'", test_case.name);
 
     let mut cpu = setup_cpu(test_case);
-    cpu.step(&mut CounterCpuCallback::default()).unwrap();
+    let mut callback = CounterCpuCallback::default();
+    cpu.step(&mut callback).unwrap();
 
-    let result = test_case.validate_final_state(&cpu);
+    let result = test_case
+        .validate_final_state(&cpu)
+        .and_then(|_| test_case.validate_m_cycles(callback.m_cycles_count));
 
     if let Err(err) = result {
         let inst = INSTRUCTIONS_BY_OPCODES[cpu.current_opcode as usize];
@@ -55,7 +58,7 @@ pub struct Sm83TestCase {
     pub initial_state: CpuState,
     #[serde(rename = "final")]
     pub final_state: CpuState,
-    //pub cycles: Vec<Cycle>,
+    pub cycles: Vec<Cycle>,
 }
 
 impl Sm83TestCase {
@@ -122,6 +125,24 @@ impl Sm83TestCase {
         }
     }
 
+    /// Each bus cycle of the test is one M-cycle.
+    pub fn validate_m_cycles(&self, m_cycles: usize) -> Result<(), String> {
+        // FIXME: STOP and HALT run until woken up
+        let is_known_mismatch = ["10 ", "76 "]
+            .iter()
+            .any(|prefix| self.name.starts_with(prefix));
+
+        if !is_known_mismatch && m_cycles != self.cycles.len() {
+            return Err(format!(
+                "Invalid M-cycles: actual={}, expected={}",
+                m_cycles,
+                self.cycles.len()
+            ));
+        }
+
+        Ok(())
+    }
+
     pub fn validate_final_state(&self, cpu: &Cpu) -> Result<(), String> {
         if cpu.registers.a != self.final_state.a {
             return Err(format!(
@@ -240,7 +261,7 @@ pub struct CpuState {
 pub struct RamState(pub u16, pub u8);
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Cycle(pub u16, pub u8, pub String);
+pub struct Cycle(pub Option<u16>, pub Option<u8>, pub String);
 
 pub fn setup_cpu(test_case: &Sm83TestCase) -> Cpu {
     let mut cpu = Cpu::new(setup_bus(test_case));